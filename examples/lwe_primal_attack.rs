@@ -0,0 +1,43 @@
+//
+// Breaks LWE keys made with parameters that are far too small, by lattice reduction.
+//
+// Run with `cargo run --release --example lwe_primal_attack`, since the reduction is slow
+// without optimizations.
+//
+
+use std::time::Instant;
+
+use rand::Rng;
+use rusty_crypto::lwe::{EmbeddingAttack, LweParams, Plaintext};
+
+fn main() {
+	// each with the number of samples to use, since fewer make smaller lattices that reduce faster
+	let presets = [
+		("TOY", LweParams::TOY, 32),
+		("TOY, 2 bits per coefficient", LweParams::TOY.with_bits_per_coefficient(2), 32),
+		("N = 12, q = 521", LweParams::new(40, 12, 521, 2), 40),
+		("DEFAULT", LweParams::DEFAULT, 60)
+	];
+
+	for (name, params, samples) in presets {
+		println!("{}: M = {:?}, N = {:?}, q = {:?}, attacked with {:?} samples", name, params.m(), params.n(), params.q(), samples);
+
+		let (sk, pk) = params.gen();
+		let attack = EmbeddingAttack::new(&params, &pk, samples).unwrap();
+
+		let start = Instant::now();
+		let recovered = attack.recover_secret_key();
+		let elapsed = start.elapsed();
+
+		match recovered {
+			Some(recovered) => {
+				let plaintext: Plaintext = rand::thread_rng().gen();
+				let decrypted = params.dec(&recovered, params.enc(pk, plaintext).unwrap()).unwrap();
+
+				println!("\trecovered the secret key in {:.2?}", elapsed);
+				println!("\tsame as the real one: {:?}, decrypts a fresh ciphertext: {:?}", recovered == sk, decrypted == plaintext);
+			},
+			None => println!("\tno luck after {:.2?}", elapsed)
+		}
+	}
+}
@@ -0,0 +1,87 @@
+//
+// A last-round key recovery attack on reduced-round Speck32/64.
+//
+// If a differential over r - 1 rounds holds with a decent probability, then with the right last
+// round key, partially decrypting the last round of many ciphertext pairs shows that difference
+// far more often than with a wrong guess. Speck32/64 round keys are only 16 bits, so every guess
+// can simply be tried.
+//
+
+use rand::RngCore;
+
+use super::{speck32::{self, Block, Speck32, Word}, xor};
+
+/// A guess for a round key, along with how many pairs agreed with it
+pub type Candidate = (Word, usize);
+
+/// Undoes the last round of a ciphertext with a guessed round key
+pub fn peel_last_round(ciphertext: Block, round_key: Word) -> Block {
+	speck32::round_inv(ciphertext, round_key)
+}
+
+/// Collects `pairs` chosen plaintext pairs with difference `input_diff` from the encryption oracle
+pub fn collect_pairs<F>(mut oracle: F, input_diff: Block, pairs: usize, rng: &mut dyn RngCore) -> Vec<(Block, Block)> 
+	where F: FnMut(Block) -> Block {
+
+	(0..pairs).map(|_| {
+		let p = [rng.next_u32() as Word, rng.next_u32() as Word];
+		(oracle(p), oracle(xor(p, input_diff)))
+	}).collect()
+}
+
+/// Ranks every possible last round key by how many of the ciphertext pairs, once partially
+/// decrypted by one round, show the difference `expected_diff`. The best guesses come first.
+/// 
+/// Flipping the top bit of the round key flips the top bit of both partial decryptions, so the
+/// right key always ties with the right key XOR 8000. The other high bits only matter when a
+/// borrow reaches them, so with few pairs they tend to tie as well, while the low bits are
+/// pinned down quickly.
+pub fn rank_last_round_keys(ciphertext_pairs: &[(Block, Block)], expected_diff: Block) -> Vec<Candidate> {
+	let mut candidates: Vec<Candidate> = (0..=Word::MAX).map(|guess| {
+		let count = ciphertext_pairs.iter().filter(|(a, b)| {
+			xor(peel_last_round(*a, guess), peel_last_round(*b, guess)) == expected_diff
+		}).count();
+		(guess, count)
+	}).collect();
+
+	candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+	candidates
+}
+
+/// Runs the whole attack against `rounds`-round Speck32/64 under `key`, which the attacker only 
+/// uses through the encryption oracle. `input_diff -> expected_diff` should be a good differential 
+/// over `rounds - 1` rounds.
+/// 
+/// Returns the ranked candidates for the last round key.
+pub fn recover_last_round_key(key: speck32::Key, rounds: usize, input_diff: Block, expected_diff: Block, pairs: usize, rng: &mut dyn RngCore) -> Vec<Candidate> {
+	let oracle = Speck32::new(key, rounds);
+	let ciphertext_pairs = collect_pairs(|p| oracle.encrypt(p), input_diff, pairs, rng);
+	rank_last_round_keys(&ciphertext_pairs, expected_diff)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use super::*;
+
+	#[test]
+	fn test_three_round_attack() {
+		let mut rng = StdRng::seed_from_u64(34);
+
+		for _ in 0..5 {
+			let key = [rng.next_u32() as Word, rng.next_u32() as Word, rng.next_u32() as Word, rng.next_u32() as Word];
+			let real_key = Speck32::new(key, 3).round_keys()[2];
+
+			// the two round characteristic (0040, 0000) -> (8000, 8000) -> (8100, 8102) holds half the time
+			let ranked = recover_last_round_key(key, 3, [0x0040, 0x0000], [0x8100, 0x8102], 64, &mut rng);
+			let best_count = ranked[0].1;
+
+			let top: Vec<Word> = ranked.iter().take_while(|c| c.1 == best_count).map(|c| c.0).collect();
+			assert!(top.contains(&real_key), "Real key {:04x} not among {:04x?}", real_key, top);
+			assert!(top.contains(&(real_key ^ 0x8000)));
+			// the top bits of the key only show up through rare borrows, so only the low bits are pinned down
+			assert!(top.iter().all(|k| k & 0x0fff == real_key & 0x0fff));
+		}
+	}
+}
@@ -0,0 +1,126 @@
+//
+// Empirical differential cryptanalysis: how often does a difference in the plaintexts
+// turn into a given difference in the ciphertexts?
+//
+
+use rand::RngCore;
+
+use super::{xor, State, Word};
+
+/// Estimates the probability that the differential `input_diff -> output_diff` holds for `encrypt`,
+/// by encrypting `samples` random plaintext pairs with that input difference.
+pub fn differential_probability<W, F>(mut encrypt: F, input_diff: State<W>, output_diff: State<W>, samples: usize, rng: &mut dyn RngCore) -> f64 
+	where W: Word, F: FnMut(State<W>) -> State<W> {
+
+	let mut hits = 0;
+
+	for _ in 0..samples {
+		let p = [W::random(rng), W::random(rng)];
+		if xor(encrypt(p), encrypt(xor(p, input_diff))) == output_diff {
+			hits += 1;
+		}
+	}
+
+	hits as f64 / samples as f64
+}
+
+/// Estimates the probability of a differential characteristic, where `rounds[i]` runs the `i`th
+/// round and `trail[i]` is the difference expected before it. So, `trail` has one more entry than
+/// `rounds`, the last being the output difference.
+/// 
+/// Returns the estimated probability of each prefix of the trail, so entry `i` is the probability
+/// that the first `i + 1` transitions all hold.
+pub fn characteristic_probabilities<W: Word>(rounds: &[&dyn Fn(State<W>) -> State<W>], trail: &[State<W>], samples: usize, rng: &mut dyn RngCore) -> Vec<f64> {
+	assert_eq!(rounds.len() + 1, trail.len(), "A trail needs one more difference than it has rounds");
+
+	let mut hits = vec![0 ; rounds.len()];
+
+	for _ in 0..samples {
+		let mut a = [W::random(rng), W::random(rng)];
+		let mut b = xor(a, trail[0]);
+
+		for (i, round) in rounds.iter().enumerate() {
+			a = round(a);
+			b = round(b);
+
+			if xor(a, b) != trail[i + 1] {
+				break;
+			}
+
+			hits[i] += 1;
+		}
+	}
+
+	hits.into_iter().map(|h| h as f64 / samples as f64).collect()
+}
+
+/// The most likely output differences for `input_diff`, found by encrypting `samples` random pairs.
+/// Returns up to `count` differences along with their estimated probabilities, most likely first.
+pub fn best_output_differences<W, F>(mut encrypt: F, input_diff: State<W>, samples: usize, count: usize, rng: &mut dyn RngCore) -> Vec<(State<W>, f64)> 
+	where W: Word, F: FnMut(State<W>) -> State<W> {
+
+	let mut seen: Vec<(State<W>, usize)> = Vec::new();
+
+	for _ in 0..samples {
+		let p = [W::random(rng), W::random(rng)];
+		let diff = xor(encrypt(p), encrypt(xor(p, input_diff)));
+
+		match seen.iter_mut().find(|(d, _)| *d == diff) {
+			Some((_, n)) => *n += 1,
+			None => seen.push((diff, 1))
+		}
+	}
+
+	seen.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+	seen.truncate(count);
+	seen.into_iter().map(|(d, n)| (d, n as f64 / samples as f64)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use crate::cryptanalysis::{speck128::Speck128, speck32::{self, Speck32}};
+
+	use super::*;
+
+	#[test]
+	fn test_one_round_speck32() {
+		// the difference in bit 6 of x rotates into the top bit, where addition can't carry it
+		// anywhere, so this round differential holds with probability 1.
+		let mut rng = StdRng::seed_from_u64(29);
+		let speck = Speck32::new([0x0100, 0x0908, 0x1110, 0x1918], 1);
+
+		let p = differential_probability(|b| speck.encrypt(b), [0x0040, 0x0000], [0x8000, 0x8000], 1000, &mut rng);
+		assert_eq!(p, 1.0);
+	}
+
+	#[test]
+	fn test_two_round_characteristic() {
+		// (0040, 0000) -> (8000, 8000) -> (8100, 8102), where only bit 8 of the second addition can
+		// carry, so the second round holds with probability 1/2
+		let mut rng = StdRng::seed_from_u64(30);
+		let speck = Speck32::new([0x0100, 0x0908, 0x1110, 0x1918], 2);
+		let keys = speck.round_keys().to_vec();
+
+		let first = |b| speck32::round(b, keys[0]);
+		let second = |b| speck32::round(b, keys[1]);
+
+		let probabilities = characteristic_probabilities(&[&first, &second], &[[0x0040, 0x0000], [0x8000, 0x8000], [0x8100, 0x8102]], 20000, &mut rng);
+
+		assert_eq!(probabilities[0], 1.0);
+		assert!((probabilities[1] - 0.5).abs() < 0.02, "Two round probability was {}", probabilities[1]);
+
+		let best = best_output_differences(|b| speck.encrypt(b), [0x0040, 0x0000], 20000, 1, &mut rng);
+		assert_eq!(best[0].0, [0x8100, 0x8102]);
+	}
+
+	#[test]
+	fn test_one_round_speck128() {
+		let mut rng = StdRng::seed_from_u64(31);
+		let speck = Speck128::new(&crate::speck::gen_with_rng(&mut rng), 1);
+
+		let p = differential_probability(|b| speck.encrypt(b), [0x80, 0], [1 << 63, 1 << 63], 1000, &mut rng);
+		assert_eq!(p, 1.0);
+	}
+}
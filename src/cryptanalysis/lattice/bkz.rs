@@ -0,0 +1,275 @@
+//
+// BKZ, which strengthens LLL by finding the shortest vector in each block of consecutive basis
+// vectors, projected orthogonally to everything before the block, and putting it at the front
+// of the block. The search is Schnorr and Euchner's enumeration, in floating point, but every
+// change to the basis is exact.
+//
+
+use super::Basis;
+
+/// A depth-first search for the shortest nonzero vector in the lattice spanned by the Gram-Schmidt
+/// data `mu` and `norms` of a block, which starts at b_0 of the block
+struct Enumeration<'a> {
+	mu: &'a [Vec<f64>],
+	norms: &'a [f64],
+
+	/// The coefficients of the vector being tried
+	x: Vec<i64>,
+
+	/// The shortest vector found so far, and the squared length anything has to beat
+	best: Option<Vec<i64>>,
+	radius: f64
+}
+
+impl Enumeration<'_> {
+
+	/// Tries every x_i that keeps the squared length below the radius, given the coefficients
+	/// above i and the squared length `above` their part already adds up to
+	fn search(&mut self, i: usize, above: f64) {
+		let dim = self.norms.len();
+		let center = -((i + 1)..dim).map(|j| self.x[j] as f64 * self.mu[j][i]).sum::<f64>();
+		let nothing_above = self.x[(i + 1)..].iter().all(|x| *x == 0);
+		let closest = center.round() as i64;
+
+		// outwards from the center in both directions, closest first, until it gets too long.
+		// With nothing above, v and -v are the same vector, so only one sign is needed.
+		for step in [1, -1] {
+			let mut xi = if step == 1 { closest } else { closest - 1 };
+
+			while !(nothing_above && xi < 0) {
+				let length = above + (xi as f64 - center).powi(2) * self.norms[i];
+				if length >= self.radius {
+					break;
+				}
+
+				self.x[i] = xi;
+
+				if i > 0 {
+					self.search(i - 1, length);
+				} else if !(nothing_above && xi == 0) {
+					self.radius = length;
+					self.best = Some(self.x.clone());
+				}
+
+				xi += step;
+			}
+		}
+
+		self.x[i] = 0;
+	}
+}
+
+/// The coefficients of the shortest nonzero vector in a block, if it has squared length under
+/// `radius`
+fn enumerate(mu: &[Vec<f64>], norms: &[f64], radius: f64) -> Option<Vec<i64>> {
+	let mut enumeration = Enumeration { mu, norms, x: vec![0 ; norms.len()], best: None, radius };
+	enumeration.search(norms.len() - 1, 0.0);
+	enumeration.best
+}
+
+/// (g, u, v) with u a + v b = g = gcd(a, b) >= 0
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+	let (mut r0, mut r1) = (a, b);
+	let (mut s0, mut s1) = (1, 0);
+	let (mut t0, mut t1) = (0, 1);
+
+	while r1 != 0 {
+		let quotient = r0 / r1;
+		(r0, r1) = (r1, r0 - quotient * r1);
+		(s0, s1) = (s1, s0 - quotient * s1);
+		(t0, t1) = (t1, t0 - quotient * t1);
+	}
+
+	if r0 < 0 { (-r0, -s0, -t0) } else { (r0, s0, t0) }
+}
+
+/// a p + b q
+fn combine(a: i64, p: &[i64], b: i64, q: &[i64]) -> Vec<i64> {
+	p.iter().zip(q).map(|(x, y)| {
+		a.checked_mul(*x).and_then(|ax| b.checked_mul(*y).and_then(|by| ax.checked_add(by)))
+			.expect("Basis entries overflowed an i64")
+	}).collect()
+}
+
+/// Replaces the vectors from b_start on with another basis of the same lattice, whose first
+/// vector is the sum of x_i b_(start + i). The gcd of the x_i has to be 1.
+///
+/// Each pair of neighbours, from the back, is replaced by (a p + b q, -v p + u q), where
+/// (a, b) is their pair of coefficients over their gcd g, and u a + v b = 1. That matrix has
+/// determinant 1, and leaves g as the coefficient of the first of them, so in the end all of x
+/// is folded into b_start.
+fn insert(rows: &mut [Vec<i64>], start: usize, x: &[i64]) {
+	let mut x = x.to_vec();
+
+	for j in (1..x.len()).rev() {
+		if x[j] == 0 {
+			continue;
+		}
+
+		let (g, u, v) = extended_gcd(x[j - 1], x[j]);
+		let (a, b) = (x[j - 1] / g, x[j] / g);
+		let (p, q) = (&rows[start + j - 1], &rows[start + j]);
+
+		(rows[start + j - 1], rows[start + j]) = (combine(a, p, b, q), combine(-v, p, u, q));
+		(x[j - 1], x[j]) = (g, 0);
+	}
+
+	debug_assert_eq!(x[0].abs(), 1, "The coefficients of an inserted vector have to be coprime");
+	if x[0] < 0 {
+		for entry in rows[start].iter_mut() {
+			*entry = -*entry;
+		}
+	}
+}
+
+impl Basis {
+
+	/// BKZ-reduces the basis with blocks of `block_size` vectors, so that on top of being
+	/// LLL-reduced with `delta`, every b*_k is within a factor delta of the shortest vector of
+	/// the block starting at k, projected away from b_0 .. b_(k - 1).
+	///
+	/// The enumeration takes time exponential in the block size, so this is only practical up
+	/// to block sizes of about 20. A block the size of the whole basis finds a shortest vector.
+	pub fn bkz(&mut self, block_size: usize, delta: (i64, i64)) {
+		assert!(block_size >= 2, "BKZ needs blocks of at least 2 vectors");
+
+		self.lll(delta);
+
+		let n = self.rank();
+		if n < 2 {
+			return;
+		}
+
+		let gs = &mut self.gs;
+		let factor = delta.0 as f64 / delta.1 as f64;
+
+		// go around the blocks until a whole tour goes by without any of them changing
+		let mut unchanged = 0;
+		let mut k = 0;
+
+		while unchanged < n - 1 {
+			let end = (k + block_size).min(n);
+			let mu: Vec<Vec<f64>> = (k..end).map(|i| (k..i).map(|j| gs.mu(i, j)).collect()).collect();
+			let norms: Vec<f64> = (k..end).map(|i| gs.norm_squared(i)).collect();
+
+			match enumerate(&mu, &norms, factor * norms[0]) {
+				Some(mut x) => {
+					// the shortest vector is primitive, but rounding could have found a multiple
+					let g = x.iter().fold(0, |g, xi| extended_gcd(g, *xi).0);
+					x.iter_mut().for_each(|xi| *xi /= g);
+
+					insert(&mut self.rows, k, &x);
+					gs.recompute_from(&self.rows, k);
+					gs.lll(&mut self.rows, k, n, delta);
+
+					unchanged = 0;
+				},
+				None => unchanged += 1
+			}
+
+			k = (k + 1) % (n - 1);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	use crate::cryptanalysis::lattice::DEFAULT_DELTA;
+
+	use super::*;
+
+	fn norm_squared(v: &[i64]) -> i64 {
+		v.iter().map(|x| x * x).sum()
+	}
+
+	/// A basis of the lattice of vectors x with x . a = 0 mod p, a hard-ish "knapsack" lattice
+	fn modular_basis(n: usize, p: i64, rng: &mut impl Rng) -> Basis {
+		let a: Vec<i64> = (1..n).map(|_| rng.gen_range(0..p)).collect();
+
+		// x_0 = -(a_1 x_1 + ... ) mod p, so the rows are (-a_i, e_i) and (p, 0, ..., 0)
+		let mut rows = vec![(0..n).map(|j| if j == 0 { p } else { 0 }).collect::<Vec<i64>>()];
+		for i in 1..n {
+			rows.push((0..n).map(|j| if j == 0 { -a[i - 1] } else if j == i { 1 } else { 0 }).collect());
+		}
+
+		Basis::new(rows)
+	}
+
+	#[test]
+	fn test_extended_gcd() {
+		for (a, b) in [(12, 18), (-12, 18), (0, 5), (5, 0), (0, -5), (7, -3), (-1, -1)] {
+			let (g, u, v) = extended_gcd(a, b);
+			assert!(g >= 0);
+			assert_eq!(u * a + v * b, g);
+			assert!(a % g.max(1) == 0 && b % g.max(1) == 0);
+		}
+	}
+
+	#[test]
+	fn test_insert() {
+		let mut rng = StdRng::seed_from_u64(0x21);
+		let basis = modular_basis(8, 1009, &mut rng);
+		let determinant = basis.determinant_squared();
+
+		for x in [vec![0, 0, 1], vec![3, -2, 5], vec![0, 4, 0, -3, 0], vec![-1]] {
+			let mut rows = basis.rows().to_vec();
+			insert(&mut rows, 2, &x);
+
+			let expected: Vec<i64> = (0..8).map(|j| x.iter().enumerate().map(|(i, xi)| xi * basis.rows()[2 + i][j]).sum()).collect();
+			assert_eq!(rows[2], expected);
+
+			// still a basis of the same lattice
+			assert_eq!(Basis::new(rows).determinant_squared(), determinant);
+		}
+	}
+
+	#[test]
+	fn test_bkz_shortest() {
+		let mut rng = StdRng::seed_from_u64(0x22);
+
+		for _ in 0..4 {
+			let mut basis = modular_basis(6, 10007, &mut rng);
+			basis.lll(DEFAULT_DELTA);
+
+			// every small combination of the LLL-reduced basis, one of which is a shortest vector
+			let rows = basis.rows().to_vec();
+			let mut shortest = i64::MAX;
+			for index in 0..7i64.pow(6) {
+				let x: Vec<i64> = (0..6).map(|i| (index / 7i64.pow(i)) % 7 - 3).collect();
+				let v: Vec<i64> = (0..6).map(|j| (0..6).map(|i| x[i as usize] * rows[i as usize][j]).sum()).collect();
+				if v.iter().any(|vj| *vj != 0) {
+					shortest = shortest.min(norm_squared(&v));
+				}
+			}
+
+			basis.bkz(6, DEFAULT_DELTA);
+			assert_eq!(norm_squared(&basis.rows()[0]), shortest);
+		}
+	}
+
+	#[test]
+	fn test_bkz_beats_lll() {
+		let mut rng = StdRng::seed_from_u64(0x23);
+		let mut total = [0.0 ; 2];
+
+		for _ in 0..4 {
+			let mut lll = modular_basis(40, 1 << 30, &mut rng);
+			let determinant = lll.determinant_squared();
+			let mut bkz = lll.clone();
+
+			lll.lll(DEFAULT_DELTA);
+			bkz.bkz(10, DEFAULT_DELTA);
+
+			assert!(bkz.is_lll_reduced(DEFAULT_DELTA));
+			assert_eq!(bkz, Basis::new(bkz.rows().to_vec()));
+			assert_eq!(bkz.determinant_squared(), determinant);
+
+			total[0] += (norm_squared(&lll.rows()[0]) as f64).sqrt();
+			total[1] += (norm_squared(&bkz.rows()[0]) as f64).sqrt();
+		}
+
+		assert!(total[1] < total[0], "BKZ-10 found vectors of total length {:?}, and LLL {:?}", total[1], total[0]);
+	}
+}
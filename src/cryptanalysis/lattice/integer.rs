@@ -0,0 +1,351 @@
+//
+// Signed integers of any size, just enough of them for exact lattice reduction: the Gram
+// determinants LLL keeps track of grow to hundreds of bits even for tiny lattices.
+//
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[cfg(feature = "bignum")]
+use sylvan_number::bignumber::BigNumber;
+
+pub use crate::words::Word;
+use crate::words::{self, trim};
+
+// MARK: Word Arithmetic
+
+/// The sum of two magnitudes, which may be one word longer than either
+fn magnitude_add(a: &[Word], b: &[Word]) -> Vec<Word> {
+	let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+	let mut sum = Vec::with_capacity(long.len() + 1);
+	sum.extend_from_slice(long);
+	sum.push(0);
+
+	words::add_assign(&mut sum, short);
+	sum
+}
+
+/// a - b, assuming a >= b
+fn magnitude_sub(a: &[Word], b: &[Word]) -> Vec<Word> {
+	let mut difference = a.to_vec();
+
+	words::sub_assign(&mut difference, b);
+	difference
+}
+
+// MARK: Integer
+
+/// A signed integer of any size
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct Integer {
+	/// Whether this is below 0. Zero is never negative.
+	negative: bool,
+
+	/// The absolute value, least significant word first, with no leading 0 words
+	magnitude: Vec<Word>
+}
+
+impl Integer {
+
+	fn from_sign_magnitude(negative: bool, mut magnitude: Vec<Word>) -> Integer {
+		trim(&mut magnitude);
+		Integer { negative: negative && !magnitude.is_empty(), magnitude }
+	}
+
+	pub fn zero() -> Integer {
+		Integer::default()
+	}
+
+	pub fn one() -> Integer {
+		Integer::from(1i64)
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.magnitude.is_empty()
+	}
+
+	pub fn is_negative(&self) -> bool {
+		self.negative
+	}
+
+	pub fn abs(&self) -> Integer {
+		Integer { negative: false, magnitude: self.magnitude.clone() }
+	}
+
+	/// The number of bits it takes to write the absolute value
+	pub fn bits(&self) -> usize {
+		match self.magnitude.last() {
+			Some(top) => 64 * self.magnitude.len() - top.leading_zeros() as usize,
+			None => 0
+		}
+	}
+
+	/// The quotient rounded towards 0, and the remainder, which has the sign of self
+	pub fn div_rem(&self, divisor: &Integer) -> (Integer, Integer) {
+		let (quotient, remainder) = words::div_rem(&self.magnitude, &divisor.magnitude);
+		(
+			Integer::from_sign_magnitude(self.negative != divisor.negative, quotient),
+			Integer::from_sign_magnitude(self.negative, remainder)
+		)
+	}
+
+	/// self / divisor, when divisor is known to divide self, which is the case for every division
+	/// in integral LLL
+	pub fn div_exact(&self, divisor: &Integer) -> Integer {
+		let (quotient, remainder) = self.div_rem(divisor);
+		debug_assert!(remainder.is_zero(), "{:?} does not divide {:?}", divisor, self);
+		quotient
+	}
+
+	/// The largest integer at most self / divisor
+	pub fn div_floor(&self, divisor: &Integer) -> Integer {
+		let (quotient, remainder) = self.div_rem(divisor);
+
+		if !remainder.is_zero() && remainder.negative != divisor.negative {
+			&quotient - &Integer::one()
+		} else {
+			quotient
+		}
+	}
+
+	/// The integer closest to self / divisor, rounding halves up, for a positive divisor
+	pub fn div_round(&self, divisor: &Integer) -> Integer {
+		debug_assert!(!divisor.negative && !divisor.is_zero());
+		let doubled = self + self;
+		(&doubled + divisor).div_floor(&(divisor + divisor))
+	}
+
+	/// This as an i64, if it fits
+	pub fn to_i64(&self) -> Option<i64> {
+		match self.magnitude.as_slice() {
+			[] => Some(0),
+			[x] if self.negative && *x <= 1 << 63 => Some((*x as i64).wrapping_neg()),
+			[x] if *x < 1 << 63 => Some(*x as i64),
+			_ => None
+		}
+	}
+
+	/// The top bits of this as an f64, along with the power of 2 they are scaled by, so that
+	/// numbers far too big for an f64 can still be divided by each other
+	fn to_scaled_f64(&self) -> (f64, i32) {
+		let len = self.magnitude.len();
+		let top = |i: usize| if len > i { self.magnitude[len - 1 - i] as f64 } else { 0.0 };
+
+		let mantissa = top(0) * 2f64.powi(64) + top(1) + top(2) / 2f64.powi(64);
+		let exponent = 64 * (len as i32 - 2);
+
+		(if self.negative { -mantissa } else { mantissa }, exponent)
+	}
+
+	/// An approximation of self / divisor, even when both are far too big for an f64
+	pub fn ratio(&self, divisor: &Integer) -> f64 {
+		let (a, a_exp) = self.to_scaled_f64();
+		let (b, b_exp) = divisor.to_scaled_f64();
+
+		(a / b) * 2f64.powi(a_exp - b_exp)
+	}
+
+	/// A signed integer from sylvan_number's big integers
+	#[cfg(feature = "bignum")]
+	pub fn from_bn(bn: &BigNumber) -> Integer {
+		Integer::from_sign_magnitude(bn.is_negative, bn.magnitude.words.clone())
+	}
+}
+
+impl From<i64> for Integer {
+	fn from(value: i64) -> Self {
+		Integer::from(value as i128)
+	}
+}
+
+impl From<i128> for Integer {
+	fn from(value: i128) -> Self {
+		let magnitude = value.unsigned_abs();
+		Integer::from_sign_magnitude(value < 0, vec![magnitude as Word, (magnitude >> 64) as Word])
+	}
+}
+
+#[cfg(feature = "bignum")]
+impl From<BigNumber> for Integer {
+	fn from(value: BigNumber) -> Self {
+		Integer::from_bn(&value)
+	}
+}
+
+impl Debug for Integer {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.negative {
+			write!(f, "-")?;
+		}
+
+		write!(f, "0x")?;
+		match self.magnitude.split_last() {
+			Some((top, rest)) => {
+				write!(f, "{:X}", top)?;
+				for w in rest.iter().rev() {
+					write!(f, "{:016X}", w)?;
+				}
+				Ok(())
+			},
+			None => write!(f, "0")
+		}
+	}
+}
+
+impl PartialOrd for Integer {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Integer {
+	fn cmp(&self, other: &Self) -> Ordering {
+		match (self.negative, other.negative) {
+			(false, true) => Ordering::Greater,
+			(true, false) => Ordering::Less,
+			(false, false) => words::cmp(&self.magnitude, &other.magnitude),
+			(true, true) => words::cmp(&other.magnitude, &self.magnitude)
+		}
+	}
+}
+
+// MARK: Arithmetic
+
+impl Neg for &Integer {
+	type Output = Integer;
+
+	fn neg(self) -> Integer {
+		Integer::from_sign_magnitude(!self.negative, self.magnitude.clone())
+	}
+}
+
+impl Add for &Integer {
+	type Output = Integer;
+
+	fn add(self, rhs: &Integer) -> Integer {
+		if self.negative == rhs.negative {
+			return Integer::from_sign_magnitude(self.negative, magnitude_add(&self.magnitude, &rhs.magnitude));
+		}
+
+		// the signs differ, so the smaller magnitude comes off the bigger one, which sets the sign
+		match words::cmp(&self.magnitude, &rhs.magnitude) {
+			Ordering::Less => Integer::from_sign_magnitude(rhs.negative, magnitude_sub(&rhs.magnitude, &self.magnitude)),
+			_ => Integer::from_sign_magnitude(self.negative, magnitude_sub(&self.magnitude, &rhs.magnitude))
+		}
+	}
+}
+
+impl Sub for &Integer {
+	type Output = Integer;
+
+	fn sub(self, rhs: &Integer) -> Integer {
+		self + &(-rhs)
+	}
+}
+
+impl Mul for &Integer {
+	type Output = Integer;
+
+	fn mul(self, rhs: &Integer) -> Integer {
+		Integer::from_sign_magnitude(self.negative != rhs.negative, words::mul(&self.magnitude, &rhs.magnitude))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::Rng;
+
+	use super::*;
+
+	fn random_i128(rng: &mut impl Rng) -> i128 {
+		// mostly small numbers, where the edge cases are
+		match rng.gen_range(0..4) {
+			0 => rng.gen_range(-5..=5),
+			1 => rng.gen::<i64>() as i128,
+			_ => rng.gen::<i128>() >> rng.gen_range(1..64)
+		}
+	}
+
+	#[test]
+	fn test_against_i128() {
+		let mut rng = rand::thread_rng();
+
+		for _ in 0..10000 {
+			let (a, b) = (random_i128(&mut rng) >> 1, random_i128(&mut rng) >> 1);
+			let (x, y) = (Integer::from(a), Integer::from(b));
+
+			assert_eq!(&x + &y, Integer::from(a + b));
+			assert_eq!(&x - &y, Integer::from(a - b));
+			assert_eq!(x.cmp(&y), a.cmp(&b));
+
+			let (small_a, small_b) = (a >> 64, b >> 64);
+			assert_eq!(&Integer::from(small_a) * &Integer::from(small_b), Integer::from(small_a * small_b));
+
+			if b != 0 {
+				let (q, r) = x.div_rem(&y);
+				assert_eq!((q, r), (Integer::from(a / b), Integer::from(a % b)), "{:?} / {:?}", a, b);
+				assert_eq!(x.div_floor(&y), Integer::from(a.div_euclid(b) - if b < 0 && a.rem_euclid(b) != 0 { 1 } else { 0 }));
+			}
+
+			if b > 0 {
+				assert_eq!(x.div_round(&y), Integer::from((2 * a + b).div_euclid(2 * b)), "{:?} / {:?}", a, b);
+			}
+		}
+
+		assert_eq!(Integer::from(-7i64).div_round(&Integer::from(2i64)), Integer::from(-3i64));
+		assert_eq!(Integer::from(7i64).div_round(&Integer::from(2i64)), Integer::from(4i64));
+		assert_eq!(Integer::from(-8i64).div_round(&Integer::from(3i64)), Integer::from(-3i64));
+	}
+
+	#[test]
+	fn test_big_division() {
+		let mut rng = rand::thread_rng();
+
+		for _ in 0..1000 {
+			let mut words = |max_len: usize| -> Integer {
+				let len = rng.gen_range(1..max_len);
+				Integer::from_sign_magnitude(rng.gen(), (0..len).map(|_| rng.gen()).collect())
+			};
+
+			let a = words(12);
+			let b = words(8);
+			if b.is_zero() {
+				continue;
+			}
+
+			let (q, r) = a.div_rem(&b);
+			assert_eq!(&(&q * &b) + &r, a);
+			assert!(r.abs() < b.abs());
+			assert!(r.is_zero() || r.is_negative() == a.is_negative());
+
+			// the product of two numbers divides exactly, whatever their sizes
+			assert_eq!((&a * &b).div_exact(&b), a);
+		}
+
+		// the case where the first guess at a quotient word is too big, from Knuth
+		let a = Integer::from_sign_magnitude(false, vec![0, 0, 0x8000_0000_0000_0000]);
+		let b = Integer::from_sign_magnitude(false, vec![1, 0x8000_0000_0000_0000]);
+		let (q, r) = a.div_rem(&b);
+		assert_eq!(&(&q * &b) + &r, a);
+		assert!(r < b);
+	}
+
+	#[test]
+	fn test_conversions() {
+		for x in [0, 1, -1, i64::MAX, i64::MIN, 1 << 40, -(1 << 40)] {
+			assert_eq!(Integer::from(x).to_i64(), Some(x));
+		}
+
+		assert_eq!(Integer::from(i64::MAX as i128 + 1).to_i64(), None);
+		assert_eq!(Integer::from(i64::MIN as i128 - 1).to_i64(), None);
+		assert_eq!(format!("{:?}", Integer::from(-(1i128 << 64) - 10)), "-0x1000000000000000A");
+
+		// ratios of numbers too big for an f64
+		let huge = (0..40).fold(Integer::one(), |x, _| &x * &Integer::from(i64::MAX));
+		let three_huge = &huge * &Integer::from(-3i64);
+		assert!((three_huge.ratio(&huge) + 3.0).abs() < 1e-12);
+		assert!((Integer::from(1i64).ratio(&Integer::from(8i64)) - 0.125).abs() < 1e-15);
+		assert_eq!(Integer::from(-(1i128 << 100)).bits(), 101);
+	}
+}
@@ -0,0 +1,370 @@
+//
+// Lattice basis reduction, for teaching and for attacking toy LWE parameters.
+//
+// LLL here is the integral version from Cohen's "A Course in Computational Algebraic Number
+// Theory" (algorithm 2.6.7): instead of the Gram-Schmidt coefficients mu, which are rationals,
+// it keeps the Gram determinants d_i and lambda_ij = d_j mu_ij, which are integers, so the whole
+// reduction is exact. BKZ (in `bkz`) uses the same exact bookkeeping, and only looks for short
+// vectors inside each block with floating point.
+//
+
+use std::fmt::Debug;
+
+pub mod integer;
+mod bkz;
+
+use integer::Integer;
+
+/// The delta of the Lovasz condition, as a fraction, that `lll` uses by default. Anything in
+/// (1/4, 1) works, and closer to 1 reduces more strongly but takes longer.
+pub const DEFAULT_DELTA: (i64, i64) = (99, 100);
+
+/// A basis of a lattice, given by linearly independent integer vectors, one per row
+#[derive(Clone, PartialEq, Eq)]
+pub struct Basis {
+	rows: Vec<Vec<i64>>,
+
+	/// The Gram-Schmidt data of the rows, which is kept up to date with every change to them
+	gs: GramSchmidt
+}
+
+/// The dot product of two integer vectors, which can't overflow for entries that fit in an i64
+/// and fewer than 2^62 of them
+fn dot(a: &[i64], b: &[i64]) -> Integer {
+	let mut sum = Integer::zero();
+
+	for (x, y) in a.iter().zip(b) {
+		sum = &sum + &Integer::from(*x as i128 * *y as i128);
+	}
+
+	sum
+}
+
+/// b_k -= r b_l, for a multiplier r that fits in an i64
+fn sub_multiple(rows: &mut [Vec<i64>], k: usize, l: usize, r: &Integer) {
+	let r = r.to_i64().expect("Size reduction multiplier does not fit in an i64");
+
+	for j in 0..rows[k].len() {
+		let product = r.checked_mul(rows[l][j]).expect("Basis entries overflowed an i64");
+		rows[k][j] = rows[k][j].checked_sub(product).expect("Basis entries overflowed an i64");
+	}
+}
+
+/// The exact Gram-Schmidt data of a basis, in Cohen's integral form.
+///
+/// `d[i]` is the Gram determinant of the first i vectors, so that |b*_i|^2 = d[i + 1] / d[i], and
+/// `lambda[k][j] = d[j + 1] mu_kj` for j < k.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct GramSchmidt {
+	d: Vec<Integer>,
+	lambda: Vec<Vec<Integer>>
+}
+
+impl GramSchmidt {
+
+	fn new(rows: &[Vec<i64>]) -> GramSchmidt {
+		let mut gs = GramSchmidt {
+			d: vec![Integer::one() ; rows.len() + 1],
+			lambda: (0..rows.len()).map(|k| vec![Integer::zero() ; k]).collect()
+		};
+
+		gs.recompute_from(rows, 0);
+		gs
+	}
+
+	/// Recomputes everything for the vectors from index `start` on, after they have changed
+	fn recompute_from(&mut self, rows: &[Vec<i64>], start: usize) {
+		for k in start..rows.len() {
+			for j in 0..=k {
+				let mut u = dot(&rows[k], &rows[j]);
+
+				for i in 0..j {
+					u = (&(&self.d[i + 1] * &u) - &(&self.lambda[k][i] * &self.lambda[j][i])).div_exact(&self.d[i]);
+				}
+
+				if j < k {
+					self.lambda[k][j] = u;
+				} else {
+					assert!(!u.is_zero(), "The basis vectors are linearly dependent");
+					self.d[k + 1] = u;
+				}
+			}
+		}
+	}
+
+	/// |b*_i|^2, approximately
+	fn norm_squared(&self, i: usize) -> f64 {
+		self.d[i + 1].ratio(&self.d[i])
+	}
+
+	/// mu_kj, approximately
+	fn mu(&self, k: usize, j: usize) -> f64 {
+		self.lambda[k][j].ratio(&self.d[j + 1])
+	}
+
+	/// Size-reduces b_k against b_l, so that |mu_kl| <= 1/2 (Cohen's RED)
+	fn reduce(&mut self, rows: &mut [Vec<i64>], k: usize, l: usize) {
+		let doubled = &self.lambda[k][l] + &self.lambda[k][l];
+		if doubled.abs() <= self.d[l + 1] {
+			return;
+		}
+
+		let r = self.lambda[k][l].div_round(&self.d[l + 1]);
+		sub_multiple(rows, k, l, &r);
+
+		self.lambda[k][l] = &self.lambda[k][l] - &(&r * &self.d[l + 1]);
+		for i in 0..l {
+			self.lambda[k][i] = &self.lambda[k][i] - &(&r * &self.lambda[l][i]);
+		}
+	}
+
+	/// Whether b_k and b_(k - 1) satisfy the Lovasz condition,
+	/// |b*_k|^2 >= (delta - mu_k(k-1)^2) |b*_(k-1)|^2, which multiplied out is
+	/// den (d_(k+1) d_(k-1) + lambda^2) >= num d_k^2
+	fn lovasz(&self, k: usize, (num, den): (i64, i64)) -> bool {
+		let lambda = &self.lambda[k][k - 1];
+		let left = &(&(&self.d[k + 1] * &self.d[k - 1]) + &(lambda * lambda)) * &Integer::from(den);
+		let right = &(&self.d[k] * &self.d[k]) * &Integer::from(num);
+
+		left >= right
+	}
+
+	/// Swaps b_k and b_(k - 1), updating the Gram-Schmidt data (Cohen's SWAP)
+	fn swap(&mut self, rows: &mut [Vec<i64>], k: usize) {
+		rows.swap(k, k - 1);
+
+		for j in 0..(k - 1) {
+			let (above, below) = self.lambda.split_at_mut(k);
+			std::mem::swap(&mut above[k - 1][j], &mut below[0][j]);
+		}
+
+		let lambda = self.lambda[k][k - 1].clone();
+		let b = (&(&self.d[k - 1] * &self.d[k + 1]) + &(&lambda * &lambda)).div_exact(&self.d[k]);
+
+		for i in (k + 1)..rows.len() {
+			let t = self.lambda[i][k].clone();
+			self.lambda[i][k] = (&(&self.d[k + 1] * &self.lambda[i][k - 1]) - &(&lambda * &t)).div_exact(&self.d[k]);
+			self.lambda[i][k - 1] = (&(&b * &t) + &(&lambda * &self.lambda[i][k])).div_exact(&self.d[k + 1]);
+		}
+
+		self.d[k] = b;
+	}
+
+	/// Runs LLL on the first `end` vectors, assuming the first `start` of them are already reduced
+	fn lll(&mut self, rows: &mut [Vec<i64>], start: usize, end: usize, delta: (i64, i64)) {
+		let mut k = start.max(1);
+
+		while k < end {
+			self.reduce(rows, k, k - 1);
+
+			if self.lovasz(k, delta) {
+				for l in (0..(k - 1)).rev() {
+					self.reduce(rows, k, l);
+				}
+				k += 1;
+			} else {
+				self.swap(rows, k);
+				k = (k - 1).max(1);
+			}
+		}
+	}
+}
+
+impl Basis {
+
+	/// A basis made of `rows`, which must all be the same length and linearly independent
+	pub fn new(rows: Vec<Vec<i64>>) -> Basis {
+		assert!(!rows.is_empty(), "A basis needs at least one vector");
+		assert!(rows.iter().all(|row| row.len() == rows[0].len()), "Every basis vector has to have the same length");
+
+		// this panics if the rows are linearly dependent
+		let gs = GramSchmidt::new(&rows);
+
+		Basis { rows, gs }
+	}
+
+	/// The basis vectors
+	pub fn rows(&self) -> &[Vec<i64>] {
+		&self.rows
+	}
+
+	/// The number of basis vectors, which is the dimension of the lattice
+	pub fn rank(&self) -> usize {
+		self.rows.len()
+	}
+
+	/// The squared lengths of the Gram-Schmidt vectors b*_i. How quickly these fall off is a
+	/// good picture of how reduced a basis is.
+	pub fn profile(&self) -> Vec<f64> {
+		(0..self.rank()).map(|i| self.gs.norm_squared(i)).collect()
+	}
+
+	/// The squared volume of the lattice, which no reduction changes
+	pub fn determinant_squared(&self) -> Integer {
+		self.gs.d[self.rank()].clone()
+	}
+
+	/// Kannan's embedding of `target`: every basis vector with a 0 on the end, and then `target`
+	/// with `scale` on the end. If `target` is close to a lattice vector v, then (target - v, scale)
+	/// is an unusually short vector of the new lattice, which reduction tends to find.
+	///
+	/// The Gram-Schmidt data of the basis carries over, so embedding into an already reduced
+	/// basis is cheap.
+	pub fn embed(&self, target: &[i64], scale: i64) -> Basis {
+		assert_eq!(target.len(), self.rows[0].len(), "The target has to be as long as the basis vectors");
+
+		let mut rows: Vec<Vec<i64>> = self.rows.iter().map(|row| row.iter().copied().chain([0]).collect()).collect();
+		rows.push(target.iter().copied().chain([scale]).collect());
+
+		let mut gs = self.gs.clone();
+		gs.d.push(Integer::one());
+		gs.lambda.push(vec![Integer::zero() ; self.rank()]);
+		gs.recompute_from(&rows, self.rank());
+
+		Basis { rows, gs }
+	}
+
+	/// LLL-reduces the basis with the Lovasz constant `delta = (numerator, denominator)`, so that
+	/// every |mu_ij| <= 1/2 and |b*_k|^2 >= (delta - mu_k(k-1)^2) |b*_(k-1)|^2. The first vector
+	/// is then at most (4 / (4 delta - 1))^((n - 1) / 2) times as long as the shortest one in
+	/// the lattice, and in practice much closer.
+	pub fn lll(&mut self, delta: (i64, i64)) {
+		assert!(4 * delta.0 > delta.1 && delta.0 <= delta.1 && delta.1 > 0, "delta has to be in (1/4, 1]");
+
+		let rank = self.rank();
+		self.gs.lll(&mut self.rows, 1, rank, delta);
+	}
+
+	/// Whether the basis is LLL-reduced with the Lovasz constant `delta`, checked exactly
+	pub fn is_lll_reduced(&self, delta: (i64, i64)) -> bool {
+		let gs = &self.gs;
+
+		let size_reduced = (0..self.rank()).all(|k| (0..k).all(|j| {
+			(&gs.lambda[k][j] + &gs.lambda[k][j]).abs() <= gs.d[j + 1]
+		}));
+
+		size_reduced && (1..self.rank()).all(|k| gs.lovasz(k, delta))
+	}
+}
+
+impl Debug for Basis {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for row in &self.rows {
+			writeln!(f, "{:?}", row)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	use super::*;
+
+	fn norm_squared(v: &[i64]) -> i64 {
+		v.iter().map(|x| x * x).sum()
+	}
+
+	/// A random full-rank basis, as an upper triangular matrix with a nonzero diagonal
+	fn random_basis(n: usize, bound: i64, rng: &mut impl Rng) -> Basis {
+		Basis::new((0..n).map(|i| (0..n).map(|j| match j.cmp(&i) {
+			std::cmp::Ordering::Less => 0,
+			std::cmp::Ordering::Equal => rng.gen_range(1..bound),
+			std::cmp::Ordering::Greater => rng.gen_range(-bound..bound)
+		}).collect()).collect())
+	}
+
+	#[test]
+	fn test_small_example() {
+		// the example from Hoffstein, Pipher and Silverman, section 7.13.2
+		let mut basis = Basis::new(vec![
+			vec![19, 2, 32, 46, 3, 33],
+			vec![15, 42, 11, 0, 3, 24],
+			vec![43, 15, 0, 24, 4, 16],
+			vec![20, 44, 44, 0, 18, 15],
+			vec![0, 48, 35, 16, 31, 31],
+			vec![48, 33, 32, 9, 1, 29]
+		]);
+
+		let determinant = basis.determinant_squared();
+		basis.lll((3, 4));
+
+		assert!(basis.is_lll_reduced((3, 4)));
+		assert_eq!(basis.determinant_squared(), determinant);
+		assert_eq!(basis.rows()[0], [7, -12, -8, 4, 19, 9]);
+	}
+
+	#[test]
+	fn test_lll_random() {
+		let mut rng = StdRng::seed_from_u64(0x11);
+
+		for n in [2, 5, 10, 20] {
+			let mut basis = random_basis(n, 1 << 20, &mut rng);
+			let determinant = basis.determinant_squared();
+
+			basis.lll(DEFAULT_DELTA);
+
+			assert!(basis.is_lll_reduced(DEFAULT_DELTA));
+			assert_eq!(basis.determinant_squared(), determinant);
+
+			// the Gram-Schmidt data kept along the way is the same as computing it from scratch
+			assert_eq!(basis, Basis::new(basis.rows().to_vec()));
+
+			// the first vector is as short as LLL promises, measured against the volume
+			let volume = determinant.ratio(&Integer::one()).sqrt();
+			let gamma: f64 = 4.0 / (4.0 * 0.99 - 1.0);
+			let bound = gamma.powf((n - 1) as f64 / 4.0) * volume.powf(1.0 / n as f64);
+			assert!((norm_squared(&basis.rows()[0]) as f64).sqrt() <= bound * 1.0001);
+		}
+	}
+
+	#[test]
+	fn test_finds_planted_vector() {
+		// a lattice with one vector far shorter than the volume suggests
+		let mut rng = StdRng::seed_from_u64(0x12);
+		let n = 12;
+		let q = 10007;
+
+		let mut short: Vec<i64> = (0..n).map(|_| rng.gen_range(-1..=1)).collect();
+		short[n - 1] = 1;
+		let mut rows: Vec<Vec<i64>> = (0..(n - 1)).map(|i| (0..n).map(|j| if i == j { q } else { 0 }).collect()).collect();
+		rows.push(short.clone());
+
+		// hide it behind random multiples of the others
+		for i in 0..(n - 1) {
+			let r = rng.gen_range(-50..50);
+			for j in 0..n {
+				rows[n - 1][j] += r * rows[i][j];
+			}
+		}
+
+		let mut basis = Basis::new(rows);
+		basis.lll(DEFAULT_DELTA);
+
+		assert_eq!(norm_squared(&basis.rows()[0]), norm_squared(&short));
+	}
+
+	#[test]
+	fn test_embed() {
+		let mut rng = StdRng::seed_from_u64(0x13);
+		let mut basis = random_basis(8, 1000, &mut rng);
+		basis.lll(DEFAULT_DELTA);
+
+		let target: Vec<i64> = (0..8).map(|_| rng.gen_range(-1000..1000)).collect();
+		let embedded = basis.embed(&target, 3);
+
+		let mut rows: Vec<Vec<i64>> = basis.rows().iter().map(|row| [row.as_slice(), &[0]].concat()).collect();
+		rows.push([target.as_slice(), &[3]].concat());
+
+		assert_eq!(embedded, Basis::new(rows));
+		assert_eq!(embedded.determinant_squared(), &basis.determinant_squared() * &Integer::from(9i64));
+	}
+
+	#[test]
+	#[should_panic(expected = "linearly dependent")]
+	fn test_dependent() {
+		Basis::new(vec![vec![1, 2, 3], vec![2, 4, 6]]);
+	}
+}
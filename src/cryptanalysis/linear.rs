@@ -0,0 +1,68 @@
+//
+// Empirical linear cryptanalysis: how far from a coin flip is the parity of some
+// plaintext bits XORed with some ciphertext bits?
+//
+
+use rand::RngCore;
+
+use super::{dot, State, Word};
+
+/// Estimates the bias of the linear approximation `<p, input_mask> = <c, output_mask>` for
+/// `encrypt` over `samples` random plaintexts. That is, the probability that the approximation
+/// holds, minus one half. 
+/// 
+/// The sign of the bias usually depends on the key, so it's the magnitude that matters.
+pub fn linear_bias<W, F>(mut encrypt: F, input_mask: State<W>, output_mask: State<W>, samples: usize, rng: &mut dyn RngCore) -> f64 
+	where W: Word, F: FnMut(State<W>) -> State<W> {
+
+	let mut holds = 0;
+
+	for _ in 0..samples {
+		let p = [W::random(rng), W::random(rng)];
+		if dot(p, input_mask) == dot(encrypt(p), output_mask) {
+			holds += 1;
+		}
+	}
+
+	holds as f64 / samples as f64 - 0.5
+}
+
+/// The correlation of a linear approximation, which is just twice its bias
+pub fn correlation<W, F>(encrypt: F, input_mask: State<W>, output_mask: State<W>, samples: usize, rng: &mut dyn RngCore) -> f64 
+	where W: Word, F: FnMut(State<W>) -> State<W> {
+	2.0 * linear_bias(encrypt, input_mask, output_mask, samples, rng)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use crate::cryptanalysis::speck32::Speck32;
+
+	use super::*;
+
+	#[test]
+	fn test_lsb_of_addition() {
+		// the lowest bit of a sum has no carry coming in, so after one round, bit 0 of x is
+		// bit 7 of x XOR bit 0 of y XOR bit 0 of the round key. Always!
+		let mut rng = StdRng::seed_from_u64(32);
+
+		for _ in 0..10 {
+			let key = [rng.next_u32() as u16, rng.next_u32() as u16, rng.next_u32() as u16, rng.next_u32() as u16];
+			let speck = Speck32::new(key, 1);
+
+			let bias = linear_bias(|b| speck.encrypt(b), [0x0080, 0x0001], [0x0001, 0x0000], 1000, &mut rng);
+			assert_eq!(bias.abs(), 0.5);
+		}
+	}
+
+	#[test]
+	fn test_random_masks_are_unbiased() {
+		// after the full cipher, there shouldn't be anything to see
+		let mut rng = StdRng::seed_from_u64(33);
+		let speck = Speck32::new([0x0100, 0x0908, 0x1110, 0x1918], 22);
+
+		let bias = linear_bias(|b| speck.encrypt(b), [0x0080, 0x0001], [0x0001, 0x0000], 100000, &mut rng);
+		assert!(bias.abs() < 0.01, "Full Speck32/64 had bias {}", bias);
+	}
+}
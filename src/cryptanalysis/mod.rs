@@ -0,0 +1,62 @@
+//
+// Tools for attacking reduced-round versions of Speck, for teaching!
+//
+// Everything here works on a cipher given as a closure from a two-word state to a
+// two-word state, so the same estimators run against the crate's own Speck128/256
+// (see `speck128`) and the tiny Speck32/64 (see `speck32`), where 2^16 round keys
+// can be brute-forced in a blink.
+//
+// `lattice` is the odd one out: LLL and BKZ reduction, which `lwe::EmbeddingAttack` uses
+// to break toy LWE parameters.
+//
+
+use std::{fmt::Debug, ops::{BitAnd, BitXor}};
+
+use rand::RngCore;
+
+pub mod speck32;
+pub mod speck128;
+pub mod differential;
+pub mod linear;
+pub mod attack;
+pub mod lattice;
+
+/// A Speck word that the analysis tools know how to handle
+pub trait Word: Copy + PartialEq + Debug + BitXor<Output = Self> + BitAnd<Output = Self> {
+	/// The XOR of all of the bits of this word
+	fn parity(self) -> bool;
+
+	/// A uniformly random word
+	fn random(rng: &mut dyn RngCore) -> Self;
+}
+
+macro_rules! impl_word {
+	($t: ty) => {
+		impl Word for $t {
+			fn parity(self) -> bool {
+				self.count_ones() % 2 == 1
+			}
+
+			fn random(rng: &mut dyn RngCore) -> Self {
+				rng.next_u64() as $t
+			}
+		}
+	};
+}
+
+impl_word!(u16);
+impl_word!(u32);
+impl_word!(u64);
+
+/// The state of a Speck instance: the two words (x, y)
+pub type State<W> = [W ; 2];
+
+/// XORs two states together
+pub fn xor<W: Word>(a: State<W>, b: State<W>) -> State<W> {
+	[a[0] ^ b[0], a[1] ^ b[1]]
+}
+
+/// The dot product of a state and a mask over GF(2), in other words, the parity of the masked bits
+pub fn dot<W: Word>(state: State<W>, mask: State<W>) -> bool {
+	(state[0] & mask[0]).parity() ^ (state[1] & mask[1]).parity()
+}
@@ -0,0 +1,39 @@
+//
+// The crate's own Speck128/256, reduced to some number of rounds, in the form the analysis tools expect
+//
+
+use crate::{secret::Secret, speck::{self, Key}};
+
+pub type Word = speck::Word;
+pub type Block = [Word ; 2];
+
+/// Speck128/256 reduced to some number of rounds, with its key already expanded
+#[derive(Clone, Debug)]
+pub struct Speck128 {
+	round_keys: Secret<[Word ; speck::ROUNDS]>,
+	rounds: usize
+}
+
+impl Speck128 {
+	/// Speck128/256 with only the first `rounds` of its 34 rounds
+	pub fn new(key: &Key, rounds: usize) -> Speck128 {
+		assert!(rounds <= speck::ROUNDS, "Speck128/256 only has {} rounds", speck::ROUNDS);
+		Speck128 { round_keys: speck::speck128256_key_schedule(key), rounds }
+	}
+
+	pub fn rounds(&self) -> usize {
+		self.rounds
+	}
+
+	pub fn round_keys(&self) -> &[Word] {
+		&self.round_keys.expose()[..self.rounds]
+	}
+
+	pub fn encrypt(&self, plaintext: Block) -> Block {
+		speck::enc_words_rounds(self.round_keys.expose(), plaintext, self.rounds)
+	}
+
+	pub fn decrypt(&self, ciphertext: Block) -> Block {
+		speck::dec_words_rounds(self.round_keys.expose(), ciphertext, self.rounds)
+	}
+}
@@ -0,0 +1,97 @@
+//
+// Speck32/64, the smallest member of the Speck family, with a configurable number of rounds.
+//
+// A 32 bit block of two 16 bit words, a 64 bit key of four words, and 22 rounds in the full cipher.
+//
+
+/// The number of rounds in the full Speck32/64
+pub const ROUNDS: usize = 22;
+
+pub type Word = u16;
+pub type Block = [Word ; 2];
+
+/// A key, as the words (k0, l0, l1, l2). The implementation guide writes keys the other
+/// way around, so the key `1918 1110 0908 0100` is `[0x0100, 0x0908, 0x1110, 0x1918]`.
+pub type Key = [Word ; 4];
+
+const ALPHA: u32 = 7;
+const BETA: u32 = 2;
+
+/// One round of Speck32/64
+pub const fn round(x: Block, round_key: Word) -> Block {
+	let xored = x[0].rotate_right(ALPHA).wrapping_add(x[1]) ^ round_key;
+	[xored, x[1].rotate_left(BETA) ^ xored]
+}
+
+/// The inverse of one round of Speck32/64
+pub const fn round_inv(x: Block, round_key: Word) -> Block {
+	let y = (x[0] ^ x[1]).rotate_right(BETA);
+	[(x[0] ^ round_key).wrapping_sub(y).rotate_left(ALPHA), y]
+}
+
+/// Expands a key into the first `rounds` round keys
+pub fn key_schedule(key: Key, rounds: usize) -> Vec<Word> {
+	let mut keys = vec![0 ; rounds];
+	let mut k = key[0];
+	let mut l = vec![key[1], key[2], key[3]];
+
+	for i in 0..rounds {
+		keys[i] = k;
+		let new_l = (k.wrapping_add(l[i].rotate_right(ALPHA))) ^ (i as Word);
+		k = k.rotate_left(BETA) ^ new_l;
+		l.push(new_l);
+	}
+
+	keys
+}
+
+/// Speck32/64 reduced to some number of rounds, with its key already expanded
+#[derive(Clone, Debug)]
+pub struct Speck32 {
+	round_keys: Vec<Word>
+}
+
+impl Speck32 {
+	/// Speck32/64 with `rounds` rounds, which may be anywhere from 0 to as many as you'd like
+	pub fn new(key: Key, rounds: usize) -> Speck32 {
+		Speck32 { round_keys: key_schedule(key, rounds) }
+	}
+
+	pub fn rounds(&self) -> usize {
+		self.round_keys.len()
+	}
+
+	pub fn round_keys(&self) -> &[Word] {
+		&self.round_keys
+	}
+
+	pub fn encrypt(&self, plaintext: Block) -> Block {
+		self.round_keys.iter().fold(plaintext, |x, k| round(x, *k))
+	}
+
+	pub fn decrypt(&self, ciphertext: Block) -> Block {
+		self.round_keys.iter().rev().fold(ciphertext, |x, k| round_inv(x, *k))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_known_answer() {
+		// from the Simon and Speck paper
+		let speck = Speck32::new([0x0100, 0x0908, 0x1110, 0x1918], ROUNDS);
+
+		assert_eq!(speck.encrypt([0x6574, 0x694c]), [0xa868, 0x42f2]);
+		assert_eq!(speck.decrypt([0xa868, 0x42f2]), [0x6574, 0x694c]);
+	}
+
+	#[test]
+	fn test_round_inverse() {
+		for i in 0..1000u16 {
+			let x = [i.wrapping_mul(0x9e37), i ^ 0x5a5a];
+			assert_eq!(round_inv(round(x, i), i), x);
+		}
+	}
+}
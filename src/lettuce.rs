@@ -1,19 +1,38 @@
-use crate::speck;
+use rand::{CryptoRng, RngCore};
 
+use crate::{lwe::expand::Sha512Xof, secret::{Scrub, Secret}, speck};
+
+#[cfg(feature = "mlkem")]
+use crate::mlkem::MlKemParams;
+
+// With the `mlkem` feature, the KEM is the in-crate ML-KEM-768 instead of pqc_kyber's round-3
+// Kyber. The sizes are the same, but the two don't interoperate.
+
+/// The KEM everything here is built on
+#[cfg(feature = "mlkem")]
+const KEM: MlKemParams = MlKemParams::ML_KEM_768;
+
+#[cfg(not(feature = "mlkem"))]
 pub const PK_BYTES: usize = pqc_kyber::KYBER_PUBLICKEYBYTES;
+#[cfg(not(feature = "mlkem"))]
 pub const SK_BYTES: usize = pqc_kyber::KYBER_SECRETKEYBYTES;
-const SHARED_SECRET_BYTES: usize = pqc_kyber::KYBER_SSBYTES;
+#[cfg(not(feature = "mlkem"))]
 const CIPHERTEXT_KEM_BYTES: usize = pqc_kyber::KYBER_CIPHERTEXTBYTES;
 
+#[cfg(feature = "mlkem")]
+pub const PK_BYTES: usize = KEM.encapsulation_key_len();
+#[cfg(feature = "mlkem")]
+pub const SK_BYTES: usize = KEM.decapsulation_key_len();
+#[cfg(feature = "mlkem")]
+const CIPHERTEXT_KEM_BYTES: usize = KEM.ciphertext_len();
+
 pub type PublicKey = [u8 ; PK_BYTES];
-pub type SecretKey = [u8 ; SK_BYTES];
+pub type SecretKey = Secret<[u8 ; SK_BYTES]>;
 
 /// A type for the plaintext, an abitrary string of bytes.
 pub type Plaintext = Vec<u8>;
 pub type Ciphertext = Vec<u8>;
 
-type SharedSecret = [u8 ; SHARED_SECRET_BYTES];
-type CiphertextKEM = [u8 ; CIPHERTEXT_KEM_BYTES];
 
 /// A struct holding both a secret and public lettuce key.
 ///
@@ -27,32 +46,144 @@ pub struct KeyPair {
 
 impl From<pqc_kyber::Keypair> for KeyPair {
     fn from(value: pqc_kyber::Keypair) -> Self {
-        KeyPair {
-            secret_key: value.secret,
+        let mut value = value;
+        let key_pair = KeyPair {
+            secret_key: SecretKey::new(value.secret),
             public_key: value.public
-        }
+        };
+
+        // don't leave a copy of the secret key lying around in the Kyber key pair
+        value.secret.scrub();
+        key_pair
     }
 }
 
+/// The length, in bytes, of a seed that a key pair can be regenerated from
+pub const KEY_SEED_LEN: usize = 32;
+
+/// The domain for version 2 of deriving keys from a seed. Any change to the derivation gets a new
+/// version with its own domain. Version 1 depended on how Kyber happened to call its RNG, and has
+/// been dropped rather than kept.
+const KEYGEN_DOMAIN_V2: &[u8] = b"rusty_crypto lettuce keygen v2";
+
+/// The length, in bytes, of each of the seeds d and z that the KEM derives a key pair from
+const KEM_SEED_LEN: usize = 32;
+
 /// Generates a secret and public key pair.
 pub fn gen() -> KeyPair {
-    let mut rng = rand::thread_rng();
-    match pqc_kyber::keypair(&mut rng) {
+    gen_with_rng(&mut rand::thread_rng())
+}
+
+/// Generates a secret and public key pair, drawing all randomness from `rng`.
+#[cfg(not(feature = "mlkem"))]
+pub fn gen_with_rng(rng: &mut (impl RngCore + CryptoRng)) -> KeyPair {
+    match pqc_kyber::keypair(rng) {
         Ok(kp) => kp.into(),
         Err(_) => panic!("Error generating key pair")
     }
 }
 
+/// Generates a secret and public key pair, drawing all randomness from `rng`.
+#[cfg(feature = "mlkem")]
+pub fn gen_with_rng(rng: &mut (impl RngCore + CryptoRng)) -> KeyPair {
+    let (ek, dk) = KEM.keygen_with_rng(rng);
+
+    let mut secret_key = SecretKey::new([0 ; SK_BYTES]);
+    secret_key.expose_mut().copy_from_slice(dk.expose());
+
+    KeyPair { secret_key, public_key: ek.try_into().unwrap() }
+}
+
+/// Regenerates the same key pair every time from a 32-byte seed, which is all that needs to be
+/// backed up (or Shamir-shared with `secsharing::sharing::distribute`).
+///
+/// Version 2 of the derivation is SHA-512("rusty_crypto lettuce keygen v2" || seed || i), with i
+/// the big-endian u64 0, split into the seeds d and z, 32 bytes each. The KEM makes its key pair
+/// from those directly, which is round-3 Kyber's deterministic key generation, or
+/// ML-KEM.KeyGen_internal(d, z) with the `mlkem` feature. The two are different KEMs, so the same
+/// seed still gives different keys with and without the feature.
+pub fn gen_from_seed(seed: [u8 ; KEY_SEED_LEN]) -> KeyPair {
+    let mut seed = seed;
+    let mut d_z = [0 ; 2 * KEM_SEED_LEN];
+    Sha512Xof::new(KEYGEN_DOMAIN_V2, &seed).fill_bytes(&mut d_z);
+    seed.scrub();
+
+    let key_pair = keygen_from_d_z(&d_z);
+    d_z.scrub();
+
+    key_pair
+}
+
+/// The KEM's key pair for the seeds d and z, one after the other in `d_z`
+#[cfg(not(feature = "mlkem"))]
+fn keygen_from_d_z(d_z: &[u8 ; 2 * KEM_SEED_LEN]) -> KeyPair {
+    match pqc_kyber::derive(d_z) {
+        Ok(kp) => kp.into(),
+        Err(_) => panic!("Error deriving key pair")
+    }
+}
+
+/// The KEM's key pair for the seeds d and z, one after the other in `d_z`
+#[cfg(feature = "mlkem")]
+fn keygen_from_d_z(d_z: &[u8 ; 2 * KEM_SEED_LEN]) -> KeyPair {
+    let (d, z) = d_z.split_at(KEM_SEED_LEN);
+    let (ek, dk) = KEM.keygen_internal(d.try_into().unwrap(), z.try_into().unwrap());
+
+    let mut secret_key = SecretKey::new([0 ; SK_BYTES]);
+    secret_key.expose_mut().copy_from_slice(dk.expose());
+
+    KeyPair { secret_key, public_key: ek.try_into().unwrap() }
+}
+
+/// Encapsulates a fresh shared secret under `public_key`
+#[cfg(not(feature = "mlkem"))]
+fn encapsulate(public_key: &PublicKey, rng: &mut (impl RngCore + CryptoRng)) -> (Vec<u8>, speck::Key) {
+    match pqc_kyber::encapsulate(public_key.as_slice(), rng) {
+        Ok((ct_kem, secret)) => (ct_kem.to_vec(), speck::Key::new(secret)),
+        Err(_) => panic!("Error encapsulating key")
+    }
+}
+
+/// Encapsulates a fresh shared secret under `public_key`
+#[cfg(feature = "mlkem")]
+fn encapsulate(public_key: &PublicKey, rng: &mut (impl RngCore + CryptoRng)) -> (Vec<u8>, speck::Key) {
+    match KEM.encaps_with_rng(public_key, rng) {
+        Ok(encapsulated) => encapsulated,
+        Err(e) => panic!("Error encapsulating key: {}", e)
+    }
+}
+
+/// Recovers the shared secret in `ct_kem`
+#[cfg(not(feature = "mlkem"))]
+fn decapsulate(secret_key: &SecretKey, ct_kem: &[u8]) -> speck::Key {
+    match pqc_kyber::decapsulate(ct_kem, secret_key.expose()) {
+        Ok(s) => speck::Key::new(s),
+        Err(_) => panic!("Error decapsulating")
+    }
+}
+
+/// Recovers the shared secret in `ct_kem`
+#[cfg(feature = "mlkem")]
+fn decapsulate(secret_key: &SecretKey, ct_kem: &[u8]) -> speck::Key {
+    let dk = Secret::new(secret_key.expose().to_vec());
+
+    match KEM.decaps(&dk, ct_kem) {
+        Ok(s) => s,
+        Err(e) => panic!("Error decapsulating: {}", e)
+    }
+}
+
 /// Encrypts a message using another party's public key
 pub fn enc(public_key: PublicKey, plaintext: Plaintext) -> Ciphertext {
-    let mut rng = rand::thread_rng();
+    enc_with_rng(public_key, plaintext, &mut rand::thread_rng())
+}
 
-    let (ct_kem, secret) = match pqc_kyber::encapsulate(public_key.as_slice(), &mut rng) {
-        Ok(t) => t,
-        Err(_) => panic!("Error encapsulating key")
-    };
+/// Encrypts a message using another party's public key, drawing the encapsulation
+/// randomness from `rng`.
+pub fn enc_with_rng(public_key: PublicKey, plaintext: Plaintext, rng: &mut (impl RngCore + CryptoRng)) -> Ciphertext {
+    let (ct_kem, secret) = encapsulate(&public_key, rng);
 
-    let inner_ciphertext = speck::enc_vec(secret, plaintext);
+    let inner_ciphertext = speck::enc_vec(&secret, plaintext);
     let mut ct = vec![0 ; CIPHERTEXT_KEM_BYTES + inner_ciphertext.len()];
 
     for i in 0..CIPHERTEXT_KEM_BYTES {
@@ -67,14 +198,11 @@ pub fn enc(public_key: PublicKey, plaintext: Plaintext) -> Ciphertext {
 }
 
 /// Decrypts a ciphertext using this party's secret key
-pub fn dec(secret_key: SecretKey, ciphertext: Ciphertext) -> Plaintext {    
+pub fn dec(secret_key: &SecretKey, ciphertext: Ciphertext) -> Plaintext {    
     let ct_kem = &ciphertext[..CIPHERTEXT_KEM_BYTES];
     let inner_ciphertext = &ciphertext[CIPHERTEXT_KEM_BYTES..];
-    let shared_secret = match pqc_kyber::decapsulate(ct_kem, &secret_key) {
-        Ok(s) => s,
-        Err(_) => panic!("Error decapsulating")
-    };
-    speck::dec_vec(shared_secret, inner_ciphertext.to_vec())
+    let shared_secret = decapsulate(secret_key, ct_kem);
+    speck::dec_vec(&shared_secret, inner_ciphertext.to_vec())
 }
 
 
@@ -95,11 +223,52 @@ mod tests {
             let bobs_keys = super::gen();
             
             let alices_ciphertext = super::enc(bobs_keys.public_key, plaintext.clone());
-            let bobs_decrypted_pt = super::dec(bobs_keys.secret_key, alices_ciphertext);
+            let bobs_decrypted_pt = super::dec(&bobs_keys.secret_key, alices_ciphertext);
 
             assert_eq!(plaintext, bobs_decrypted_pt)
         }
 
     }
 
+    #[test]
+    fn test_seeded_reproducibility() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let plaintext = b"the same seed gives the same bytes".to_vec();
+
+        let first_keys = super::gen_with_rng(&mut StdRng::seed_from_u64(26));
+        let second_keys = super::gen_with_rng(&mut StdRng::seed_from_u64(26));
+
+        assert_eq!(first_keys.public_key, second_keys.public_key);
+        assert_eq!(first_keys.secret_key, second_keys.secret_key);
+
+        let first_ct = super::enc_with_rng(first_keys.public_key, plaintext.clone(), &mut StdRng::seed_from_u64(27));
+        let second_ct = super::enc_with_rng(second_keys.public_key, plaintext.clone(), &mut StdRng::seed_from_u64(27));
+
+        assert_eq!(first_ct, second_ct);
+        assert_eq!(super::dec(&first_keys.secret_key, first_ct), plaintext);
+    }
+
+    #[test]
+    fn test_gen_from_seed() {
+        let first_keys = super::gen_from_seed([0x46 ; super::KEY_SEED_LEN]);
+        let second_keys = super::gen_from_seed([0x46 ; super::KEY_SEED_LEN]);
+
+        assert_eq!(first_keys.public_key, second_keys.public_key);
+        assert_eq!(first_keys.secret_key, second_keys.secret_key);
+        assert_ne!(super::gen_from_seed([0x47 ; super::KEY_SEED_LEN]).public_key, first_keys.public_key);
+
+        let plaintext = b"restored from a backed up seed".to_vec();
+        assert_eq!(super::dec(&second_keys.secret_key, super::enc(first_keys.public_key, plaintext.clone())), plaintext);
+
+        // version 2 of the derivation has to give these exact keys in every release
+        let fingerprint = |bytes: &[u8]| crate::sha512::hash(bytes.to_vec())[0..8].iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+        #[cfg(not(feature = "mlkem"))]
+        assert_eq!((fingerprint(first_keys.secret_key.expose()), fingerprint(&first_keys.public_key)), ("e504ddc4b5451ccf".to_string(), "894ac06e7d606c65".to_string()));
+
+        #[cfg(feature = "mlkem")]
+        assert_eq!((fingerprint(first_keys.secret_key.expose()), fingerprint(&first_keys.public_key)), ("749cd87979f90031".to_string(), "99da00de1bce6be3".to_string()));
+    }
+
 }
\ No newline at end of file
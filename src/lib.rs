@@ -1,13 +1,16 @@
-#![allow(incomplete_features)]
-#![feature(slice_flatten)]
-#![feature(const_mut_refs)]
-#![feature(generic_const_exprs)]
-
-
 pub mod speck;
 pub mod lwe;
 pub mod secsharing;
 pub mod utility;
 pub mod padding;
 pub mod sha512;
-pub mod lettuce;
\ No newline at end of file
+pub mod sha3;
+pub mod lettuce;
+pub mod cryptanalysis;
+pub mod secret;
+pub mod ntt;
+pub mod mlwe;
+pub mod mlkem;
+pub mod mldsa;
+
+pub(crate) mod words;
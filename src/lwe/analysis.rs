@@ -0,0 +1,226 @@
+//
+// The exact distribution of the noise left in a ciphertext coefficient, worked out by convolving
+// the error distribution with itself once for every equation, and how often it makes decryption
+// go wrong
+//
+
+use rand::{CryptoRng, Rng, RngCore};
+
+use super::{decode_value, dec_mat, enc_mat, encode_value, gen_mat, LweParams};
+
+/// A distribution over a range of integers, given by the probability of each one
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseDistribution {
+	/// The smallest value with a probability stored
+	min: i64,
+
+	/// The probability of min, min + 1, and so on
+	probabilities: Vec<f64>
+}
+
+impl NoiseDistribution {
+
+	/// Always 0
+	fn zero() -> NoiseDistribution {
+		NoiseDistribution { min: 0, probabilities: vec![1.0] }
+	}
+
+	/// The smallest value this can take
+	pub fn min(&self) -> i64 {
+		self.min
+	}
+
+	/// The largest value this can take
+	pub fn max(&self) -> i64 {
+		self.min + self.probabilities.len() as i64 - 1
+	}
+
+	/// The probability of exactly `x`
+	pub fn probability(&self, x: i64) -> f64 {
+		if x < self.min || x > self.max() {
+			0.0
+		} else {
+			self.probabilities[(x - self.min) as usize]
+		}
+	}
+
+	/// Every value with its probability, from smallest to largest
+	pub fn iter(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+		self.probabilities.iter().enumerate().map(|(i, p)| (self.min + i as i64, *p))
+	}
+
+	/// The mean
+	pub fn mean(&self) -> f64 {
+		self.iter().map(|(x, p)| x as f64 * p).sum()
+	}
+
+	/// The variance
+	pub fn variance(&self) -> f64 {
+		let mean = self.mean();
+		self.iter().map(|(x, p)| (x as f64 - mean).powi(2) * p).sum()
+	}
+
+	/// The probability of being more than `bound` away from 0, added up from the outside in so
+	/// that tiny tails aren't lost to rounding
+	pub fn tail(&self, bound: i64) -> f64 {
+		let lower = (self.min..-bound).map(|x| self.probability(x)).sum::<f64>();
+		let upper = (bound + 1..=self.max()).rev().map(|x| self.probability(x)).sum::<f64>();
+		lower + upper
+	}
+
+	/// The distribution of the sum of an independent sample from each
+	fn convolve(&self, other: &NoiseDistribution) -> NoiseDistribution {
+		let mut probabilities = vec![0.0 ; self.probabilities.len() + other.probabilities.len() - 1];
+
+		for (i, p) in self.probabilities.iter().enumerate() {
+			for (j, r) in other.probabilities.iter().enumerate() {
+				probabilities[i + j] += p * r;
+			}
+		}
+
+		// anything that underflowed to 0 at the ends can go, which keeps wide distributions
+		// from growing forever
+		let mut min = self.min + other.min;
+		let first = probabilities.iter().position(|p| *p > 0.0).unwrap_or(0);
+		let last = probabilities.iter().rposition(|p| *p > 0.0).unwrap_or(0);
+
+		min += first as i64;
+		probabilities.truncate(last + 1);
+		probabilities.drain(0..first);
+
+		NoiseDistribution { min, probabilities }
+	}
+}
+
+impl LweParams {
+
+	/// The exact distribution of the noise in a coefficient of a fresh ciphertext, over random
+	/// keys and encryptions.
+	///
+	/// Encryption adds up a random selection of the M equations, so the noise is a sum of M
+	/// independent terms, each of which is 0 with probability 1/2 and otherwise an error drawn
+	/// from `error`. Different coefficients use different errors and selections, so their noise
+	/// is independent too.
+	pub fn noise_distribution(&self) -> NoiseDistribution {
+		let bound = self.error.max_abs();
+
+		// one term, t * e with t a random bit
+		let mut probabilities: Vec<f64> = self.error.probabilities().iter().map(|p| p / 2.0).collect();
+		probabilities[bound as usize] += 0.5;
+		let term = NoiseDistribution { min: -bound, probabilities };
+
+		(0..self.m).fold(NoiseDistribution::zero(), |sum, _| sum.convolve(&term))
+	}
+
+	/// The probability that one coefficient of a random plaintext decrypts incorrectly, which is
+	/// a single bit with one bit per coefficient
+	pub fn coefficient_failure_probability(&self) -> f64 {
+		let noise = self.noise_distribution();
+		let bits = self.bits_per_coefficient;
+		let values = 1i64 << bits;
+
+		// every value is as likely as every other in a random plaintext
+		(0..values).map(|v| {
+			let encoded = encode_value(v, self.q, bits);
+
+			noise.iter()
+				.filter(|(x, _)| decode_value((encoded + x).rem_euclid(self.q), self.q, bits) != v)
+				.map(|(_, p)| p)
+				.sum::<f64>()
+		}).sum::<f64>() / values as f64
+	}
+
+	/// The probability that a random 256-bit plaintext doesn't decrypt to exactly itself
+	pub fn message_failure_probability(&self) -> f64 {
+		// 1 - (1 - p)^k, without losing a tiny p to rounding
+		let p = self.coefficient_failure_probability();
+		-(self.plaintext_coefficients() as f64 * (-p).ln_1p()).exp_m1()
+	}
+
+	/// Measures how often a coefficient decrypts incorrectly by actually encrypting and decrypting
+	/// `messages` random plaintexts, each under a fresh key, to check `coefficient_failure_probability`
+	pub fn sample_failure_rate(&self, messages: usize, rng: &mut (impl RngCore + CryptoRng)) -> f64 {
+		let k = self.plaintext_coefficients();
+		let bits = self.bits_per_coefficient;
+		let mut failures = 0;
+
+		for _ in 0..messages {
+			// a fresh key for every message, since one key's errors are fixed for all of its ciphertexts
+			let (sk_mat, pk_mat, _) = gen_mat(self, k, rng);
+
+			let m: Vec<i64> = (0..k).map(|_| rng.gen_range(0..(1 << bits))).collect();
+			let decrypted = dec_mat(&sk_mat, &enc_mat(&pk_mat, &m, bits, rng), bits);
+
+			failures += m.iter().zip(decrypted).filter(|(a, b)| **a != *b).count();
+		}
+
+		failures as f64 / (messages * k) as f64
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use crate::lwe::{ErrorDistribution, LweParams};
+
+	#[test]
+	fn test_noise_distribution() {
+		for params in [LweParams::TOY, LweParams::DEFAULT, LweParams::new(64, 16, 3329, 0).with_error(ErrorDistribution::CenteredBinomial { eta: 3 })] {
+			let noise = params.noise_distribution();
+
+			assert_eq!(noise.max(), params.max_error());
+			assert_eq!(noise.min(), -params.max_error());
+			assert!((noise.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+			assert!(noise.mean().abs() < 1e-9);
+			assert_eq!(noise.tail(params.max_error()), 0.0);
+
+			// each of the M terms has half the variance of an error
+			let expected = params.m as f64 * params.error.variance() / 2.0;
+			assert!((noise.variance() - expected).abs() < 1e-6 * expected);
+		}
+
+		// two equations with errors in [-1, 1]: each term is 0 with probability 2/3 and +-1 with 1/6
+		let noise = LweParams::new(2, 2, 17, 1).noise_distribution();
+		let expected = [1.0 / 36.0, 8.0 / 36.0, 18.0 / 36.0, 8.0 / 36.0, 1.0 / 36.0];
+		for (x, p) in (-2..=2).zip(expected) {
+			assert!((noise.probability(x) - p).abs() < 1e-15);
+		}
+		assert!((noise.tail(1) - 2.0 / 36.0).abs() < 1e-15);
+	}
+
+	#[test]
+	fn test_failure_probability() {
+		// the presets can't fail at all, since even the largest noise fits in the margin
+		for params in [LweParams::TOY, LweParams::DEFAULT] {
+			assert_eq!(params.message_failure_probability(), 0.0);
+		}
+
+		// anywhere the worst case doesn't fit, the exact figure is below the tail bound
+		for params in [LweParams::DEFAULT.with_bits_per_coefficient(2), LweParams::new(1000, 20, 3329, 2),
+			LweParams::new(1000, 20, 3329, 0).with_error(ErrorDistribution::DiscreteGaussian { sigma: 1.0 })] {
+			let exact = params.message_failure_probability();
+			println!("{}\n  exact failure probability: {:e}", params, exact);
+
+			assert!(exact > 0.0);
+			assert!(exact <= params.failure_bound());
+		}
+	}
+
+	#[test]
+	fn test_monte_carlo() {
+		// parameters that fail often enough to see, with one and with two bits per coefficient
+		for params in [LweParams::new(40, 8, 97, 4), LweParams::new(40, 8, 193, 4).with_bits_per_coefficient(2)] {
+			let expected = params.coefficient_failure_probability();
+			let measured = params.sample_failure_rate(200, &mut StdRng::seed_from_u64(0x48));
+
+			// within five standard errors of the exact figure
+			let samples = (200 * params.plaintext_coefficients()) as f64;
+			let tolerance = 5.0 * (expected * (1.0 - expected) / samples).sqrt();
+
+			println!("expected {:?}, measured {:?}", expected, measured);
+			assert!(expected > 0.01);
+			assert!((measured - expected).abs() < tolerance);
+		}
+	}
+}
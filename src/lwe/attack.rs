@@ -0,0 +1,312 @@
+//
+// The primal attack on LWE, for toy parameters. Kannan's embedding puts each column b = As + e
+// of a public key next to the q-ary lattice of vectors As mod q, where (e, 1) becomes an unusually
+// short vector that lattice reduction can find. Once e is known, s is just linear algebra.
+//
+
+use crate::cryptanalysis::lattice::{Basis, DEFAULT_DELTA};
+use crate::secsharing::sharing::inverse_mod;
+
+use super::matrix::Matrix;
+use super::threshold::is_prime;
+use super::{matrix_rep_to_sk, pk_to_matrix_rep, LweError, LweParams, SecretKey};
+
+/// A primal attack on one public key, using the first `samples` of its equations
+#[derive(Clone, Debug)]
+pub struct EmbeddingAttack {
+	params: LweParams,
+
+	/// The public key [A | B]
+	pubkey: Matrix,
+
+	/// The equations the lattice is built from, in the order of its coordinates, starting with
+	/// N of them whose rows of A are invertible
+	equations: Vec<usize>,
+
+	/// The inverse mod q of those first N rows of A
+	inverse: Vec<Vec<i64>>,
+
+	/// The BKZ block size, or 0 to only run LLL
+	block_size: usize
+}
+
+/// Reduces x mod q into (-q/2, q/2]
+fn centered(x: i64, q: i64) -> i64 {
+	let x = x.rem_euclid(q);
+	if x > q / 2 { x - q } else { x }
+}
+
+/// The inverse mod a prime q of the square matrix `a`, given as rows, by Gauss-Jordan elimination,
+/// or None if it is singular
+fn invert_mod(a: &[Vec<i64>], q: i64) -> Option<Vec<Vec<i64>>> {
+	let n = a.len();
+	let mut augmented: Vec<Vec<i64>> = a.iter().enumerate()
+		.map(|(i, row)| row.iter().copied().chain((0..n).map(|j| (i == j) as i64)).collect())
+		.collect();
+
+	for col in 0..n {
+		let pivot = (col..n).find(|r| augmented[*r][col] != 0)?;
+		augmented.swap(col, pivot);
+
+		let scale = inverse_mod(augmented[col][col], q);
+		for x in augmented[col].iter_mut() {
+			*x = (*x * scale) % q;
+		}
+
+		let pivot_row = augmented[col].clone();
+		for (r, row) in augmented.iter_mut().enumerate() {
+			let factor = row[col];
+			if r == col || factor == 0 {
+				continue;
+			}
+
+			for (x, y) in row.iter_mut().zip(&pivot_row) {
+				*x = (*x - factor * y).rem_euclid(q);
+			}
+		}
+	}
+
+	Some(augmented.into_iter().map(|row| row[n..].to_vec()).collect())
+}
+
+impl EmbeddingAttack {
+
+	/// Sets up an attack on `pubkey`, which has the parameters `params`, using `samples` of its
+	/// M equations. More samples make the short vector stand out more, but the lattices bigger.
+	///
+	/// q has to be prime, and some N of the equations used have to be linearly independent
+	/// mod q, which they are with overwhelming probability once there are a few more than N.
+	/// The only error is `pubkey` being the wrong length for `params`.
+	pub fn new(params: &LweParams, pubkey: &[u8], samples: usize) -> Result<EmbeddingAttack, LweError> {
+		assert!(is_prime(params.q), "The embedding attack needs a prime modulus, not {:?}", params.q);
+		assert!((params.n..=params.m).contains(&samples), "Can only use between N = {:?} and M = {:?} samples, not {:?}", params.n, params.m, samples);
+
+		let pubkey = pk_to_matrix_rep(params, pubkey)?;
+		let q = params.q;
+		let row = |i: usize| -> Vec<i64> { (0..params.n).map(|j| pubkey[(i, j)]).collect() };
+
+		// greedily pick equations that are independent of the ones before, by keeping the picked
+		// rows in echelon form, each with its own pivot column
+		let mut echelon: Vec<(usize, Vec<i64>)> = vec![];
+		let mut independent = vec![];
+
+		for i in 0..samples {
+			let mut reduced = row(i);
+
+			for (pivot, picked) in &echelon {
+				let factor = reduced[*pivot] * inverse_mod(picked[*pivot], q) % q;
+				for (x, y) in reduced.iter_mut().zip(picked) {
+					*x = (*x - factor * y).rem_euclid(q);
+				}
+			}
+
+			if let Some(pivot) = reduced.iter().position(|x| *x != 0) {
+				echelon.push((pivot, reduced));
+				independent.push(i);
+
+				if independent.len() == params.n {
+					break;
+				}
+			}
+		}
+
+		assert_eq!(independent.len(), params.n, "The first {:?} rows of A do not have full rank mod q", samples);
+
+		let inverse = invert_mod(&independent.iter().map(|i| row(*i)).collect::<Vec<_>>(), q).unwrap();
+		let equations = independent.iter().copied().chain((0..samples).filter(|i| !independent.contains(i))).collect();
+
+		Ok(EmbeddingAttack { params: *params, pubkey, equations, inverse, block_size: 0 })
+	}
+
+	/// The same attack, but reducing with BKZ with blocks of `block_size` vectors after LLL,
+	/// which is slower but finds the error in harder instances
+	pub fn with_block_size(self, block_size: usize) -> EmbeddingAttack {
+		assert!(block_size == 0 || block_size >= 2, "BKZ needs blocks of at least 2 vectors");
+		EmbeddingAttack { block_size, ..self }
+	}
+
+	fn reduce(&self, basis: &mut Basis) {
+		if self.block_size == 0 {
+			basis.lll(DEFAULT_DELTA);
+		} else {
+			basis.bkz(self.block_size, DEFAULT_DELTA);
+		}
+	}
+
+	/// A basis of the q-ary lattice of every As mod q, in the coordinates of the equations used.
+	///
+	/// Writing A1 for the first N of those rows of A and A2 for the rest, As is (t, A2 A1^-1 t)
+	/// with t = A1 s, so the rows (e_j, column j of A2 A1^-1) for j < N, together with q times
+	/// each of the remaining unit vectors, are a basis.
+	pub fn q_ary_basis(&self) -> Basis {
+		let (n, q) = (self.params.n, self.params.q);
+		let samples = self.equations.len();
+
+		let c: Vec<Vec<i64>> = self.equations[n..].iter().map(|i| {
+			(0..n).map(|j| (0..n).map(|l| self.pubkey[(*i, l)] * self.inverse[l][j] % q).sum::<i64>() % q).collect()
+		}).collect();
+
+		let mut rows: Vec<Vec<i64>> = (0..n).map(|j| {
+			(0..n).map(|l| (l == j) as i64).chain(c.iter().map(|c_row| c_row[j])).collect()
+		}).collect();
+
+		rows.extend((n..samples).map(|i| (0..samples).map(|l| if l == i { q } else { 0 }).collect()));
+
+		Basis::new(rows)
+	}
+
+	/// Kannan's embedding for column `column` of B: every vector of `q_ary` with a 0 on the end,
+	/// and (b, 1), so that (b, 1) - (As, 0) = (e, 1) is in the lattice
+	fn embed(&self, q_ary: &Basis, column: usize) -> Basis {
+		let b = self.params.n + column;
+		let target: Vec<i64> = self.equations.iter().map(|i| self.pubkey[(*i, b)]).collect();
+
+		q_ary.embed(&target, 1)
+	}
+
+	/// The embedding lattice for column `column` of B, before any reduction
+	pub fn embedding_basis(&self, column: usize) -> Basis {
+		self.embed(&self.q_ary_basis(), column)
+	}
+
+	/// Whether `s` is the secret of column `column`, because b - As is within the error bound in
+	/// every one of the M equations, not just the ones used for the lattice
+	fn is_secret(&self, s: &[i64], column: usize) -> bool {
+		let LweParams { m, n, q, .. } = self.params;
+
+		(0..m).all(|i| {
+			let a_s = (0..n).map(|j| self.pubkey[(i, j)] * s[j] % q).sum::<i64>();
+			centered(self.pubkey[(i, n + column)] - a_s, q).abs() <= self.params.error.max_abs()
+		})
+	}
+
+	/// The secret of column `column`, found by reducing its embedding lattice, starting from the
+	/// already reduced `q_ary` basis that every column shares
+	fn attack_column(&self, q_ary: &Basis, column: usize) -> Option<Vec<i64>> {
+		let (n, q) = (self.params.n, self.params.q);
+		let samples = self.equations.len();
+
+		let mut basis = self.embed(q_ary, column);
+		self.reduce(&mut basis);
+
+		// any vector ending in +-1 could be +-(e, 1)
+		basis.rows().iter().filter(|v| v[samples].abs() == 1).find_map(|v| {
+			let t: Vec<i64> = (0..n).map(|j| (self.pubkey[(self.equations[j], n + column)] - v[samples] * v[j]).rem_euclid(q)).collect();
+			let s: Vec<i64> = self.inverse.iter().map(|row| row.iter().zip(&t).map(|(x, y)| x * y % q).sum::<i64>() % q).collect();
+
+			Some(s).filter(|s| self.is_secret(s, column))
+		})
+	}
+
+	/// The secret behind column `column` of B, which is column `column` of the secret key, or
+	/// None if the reduction didn't find the error
+	pub fn recover_column(&self, column: usize) -> Option<Vec<i64>> {
+		let mut q_ary = self.q_ary_basis();
+		self.reduce(&mut q_ary);
+
+		self.attack_column(&q_ary, column)
+	}
+
+	/// The whole secret key, recovered one column at a time, or None if any of the columns
+	/// couldn't be
+	pub fn recover_secret_key(&self) -> Option<SecretKey> {
+		let LweParams { n, q, .. } = self.params;
+		let columns = self.params.plaintext_coefficients();
+
+		// the q-ary part is the same for every column, so it only has to be reduced once
+		let mut q_ary = self.q_ary_basis();
+		self.reduce(&mut q_ary);
+
+		let mut secret = Matrix::zero(n, columns, q);
+		for column in 0..columns {
+			let s = self.attack_column(&q_ary, column)?;
+
+			for (j, x) in s.into_iter().enumerate() {
+				secret[(j, column)] = x;
+			}
+		}
+
+		Some(matrix_rep_to_sk(&secret))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	use crate::cryptanalysis::lattice::integer::Integer;
+	use crate::lwe::{sk_to_matrix_rep, Plaintext};
+
+	use super::*;
+
+	#[test]
+	fn test_invert_mod() {
+		let a = vec![vec![2, 3, 5], vec![7, 11, 13], vec![17, 19, 23]];
+		let inverse = invert_mod(&a, 257).unwrap();
+
+		for i in 0..3 {
+			for j in 0..3 {
+				let product = (0..3).map(|l| a[i][l] * inverse[l][j]).sum::<i64>() % 257;
+				assert_eq!(product, (i == j) as i64);
+			}
+		}
+
+		assert_eq!(invert_mod(&[vec![1, 2], vec![2, 4]], 257), None);
+	}
+
+	#[test]
+	fn test_embedding_basis() {
+		let params = LweParams::TOY;
+		let (sk, pk) = params.gen_with_rng(&mut StdRng::seed_from_u64(0x50));
+		let attack = EmbeddingAttack::new(&params, &pk, 20).unwrap();
+
+		let basis = attack.embedding_basis(3);
+		assert_eq!(basis.rank(), 21);
+
+		// the lattice has volume q^(samples - N)
+		assert_eq!(basis.determinant_squared(), attack.q_ary_basis().determinant_squared());
+		let volume = (basis.determinant_squared().ratio(&Integer::one())).sqrt();
+		assert!((volume.ln() - 12.0 * 257f64.ln()).abs() < 1e-9);
+
+		// and (e, 1) is in it, which the reduction finds
+		let secret = sk_to_matrix_rep(&params, &sk).unwrap();
+		let s: Vec<i64> = (0..params.n).map(|j| secret[(j, 3)]).collect();
+		assert_eq!(attack.recover_column(3), Some(s));
+	}
+
+	#[test]
+	fn test_recover_toy_key() {
+		// 2 bits per coefficient, so only 128 columns to go through
+		let mut rng = StdRng::seed_from_u64(0x51);
+		let params = LweParams::TOY.with_seeded_matrix().with_bits_per_coefficient(2);
+
+		let (sk, pk) = params.gen_with_rng(&mut rng);
+		let recovered = EmbeddingAttack::new(&params, &pk, params.m).unwrap().recover_secret_key().unwrap();
+
+		assert_eq!(recovered, sk);
+
+		let plaintext: Plaintext = rng.gen();
+		assert_eq!(params.dec(&recovered, params.enc(pk, plaintext).unwrap()), Ok(plaintext));
+	}
+
+	#[test]
+	fn test_bkz_attack() {
+		// a bigger secret than TOY, from fewer samples, reduced with BKZ
+		let params = LweParams::new(40, 12, 521, 2);
+		let (sk, pk) = params.gen_with_rng(&mut StdRng::seed_from_u64(0x52));
+		let secret = sk_to_matrix_rep(&params, &sk).unwrap();
+
+		let attack = EmbeddingAttack::new(&params, &pk, 30).unwrap().with_block_size(10);
+		for column in [0, 100, 255] {
+			let s: Vec<i64> = (0..params.n).map(|j| secret[(j, column)]).collect();
+			assert_eq!(attack.recover_column(column), Some(s));
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "prime modulus")]
+	fn test_composite_modulus() {
+		let params = LweParams::new(32, 8, 256, 1);
+		EmbeddingAttack::new(&params, &params.gen().1, 32).unwrap();
+	}
+}
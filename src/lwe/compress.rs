@@ -0,0 +1,252 @@
+//
+// Lossy ciphertext compression in the style of Kyber, where each coefficient is rounded to its
+// top few bits. Decryption only looks at which multiple of q / 2^bits the constant term is near,
+// so those low-order bits are mostly noise anyway.
+//
+
+use super::packing::{pack_coefficients, packed_len, unpack_coefficients};
+use super::{check_len, decode_coefficients, encode_coefficients, Ciphertext, LweError, LweParams, PackingError};
+
+/// How many bits each coefficient of a ciphertext is rounded to: `du` for the vector of
+/// coefficients that gets multiplied by the secret, and `dv` for the constant terms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CiphertextCompression {
+	/// The bits kept of each coefficient in the first N columns
+	pub du: usize,
+
+	/// The bits kept of each constant term, in the last column
+	pub dv: usize
+}
+
+/// Compress_d from FIPS 203, round(2^d / q * x) mod 2^d
+fn compress(x: i64, d: usize, q: i64) -> i64 {
+	// doubled so the rounding stays in whole numbers, and in i128 so nothing overflows for a q
+	// near 2^31
+	let x = x as i128;
+	let q = q as i128;
+	((((x << (d + 1)) + q) / (2 * q)) & ((1 << d) - 1)) as i64
+}
+
+/// Decompress_d from FIPS 203, round(q / 2^d * y)
+fn decompress(y: i64, d: usize, q: i64) -> i64 {
+	let y = y as i128;
+	let q = q as i128;
+	(((2 * y * q) + (1 << d)) >> (d + 1)) as i64
+}
+
+impl LweParams {
+
+	/// The first of `du` and `dv` that isn't between 1 and `coefficient_bits()`, if there is one
+	fn invalid_bits(&self, compression: &CiphertextCompression) -> Option<usize> {
+		[compression.du, compression.dv].into_iter().find(|d| !(1..=self.coefficient_bits()).contains(d))
+	}
+
+	/// Checks that `compression` keeps between 1 and `coefficient_bits()` bits of each coefficient
+	fn check_compression(&self, compression: &CiphertextCompression) -> Result<(), LweError> {
+		match self.invalid_bits(compression) {
+			Some(bits) => Err(LweError::InvalidCompression { bits, max: self.coefficient_bits() }),
+			None => Ok(())
+		}
+	}
+
+	/// The largest rounding error compressing a coefficient to `d` bits and back can add,
+	/// round(q / 2^(d + 1)), or 0 once 2^d is at least q, since then nothing is lost
+	pub fn compression_error(&self, d: usize) -> i64 {
+		// q is an i64, so it is below 2^d for any d too big to shift by
+		if d >= 63 || (1 << d) >= self.q { 0 } else { (self.q + (1 << d)) >> (d + 1) }
+	}
+
+	/// The largest error compression can add to what's left of a ciphertext coefficient once the
+	/// secret is taken away.
+	///
+	/// Rounding the constant term adds at most `compression_error(dv)`. Rounding the vector adds
+	/// an error to each of its N coefficients that then gets multiplied by the secret, and since
+	/// the secret here is uniform mod q, rather than small as it is in Kyber, that can be as big
+	/// as N * compression_error(du) * q / 2. So the vector can only be compressed losslessly.
+	///
+	/// This fails if `compression` keeps fewer than 1 or more than `coefficient_bits()` bits, as do
+	/// the rest of these.
+	pub fn compression_noise(&self, compression: &CiphertextCompression) -> Result<i64, LweError> {
+		self.check_compression(compression)?;
+
+		// saturating, since for a big q and lossy vector this is far beyond anything that matters
+		let vector = (self.n as i64).saturating_mul(self.compression_error(compression.du)).saturating_mul(self.q / 2);
+		Ok(self.compression_error(compression.dv).saturating_add(vector))
+	}
+
+	/// An upper bound on the probability that a compressed ciphertext decrypts incorrectly, the
+	/// same as `failure_bound` but with the rounding error from `compression_noise` on top, taken
+	/// to be the worst it could be
+	pub fn compressed_failure_bound(&self, compression: &CiphertextCompression) -> Result<f64, LweError> {
+		let noise = self.compression_noise(compression)?;
+		Ok(self.failure_bound_with(self.max_error().saturating_add(noise), noise, self.fresh_variance()))
+	}
+
+	/// The length, in bytes, of a compressed ciphertext
+	pub fn compressed_ciphertext_len(&self, compression: &CiphertextCompression) -> Result<usize, LweError> {
+		self.check_compression(compression)?;
+
+		let k = self.plaintext_coefficients();
+		Ok(packed_len(k * self.n, compression.du) + packed_len(k, compression.dv))
+	}
+
+	/// Compresses a ciphertext, rounding its vector to `du` bits per coefficient and its constant
+	/// terms to `dv` bits, each packed as tightly as they go, or fails if `ct` is the wrong length
+	/// for these parameters
+	pub fn compress_ciphertext(&self, ct: &Ciphertext, compression: &CiphertextCompression) -> Result<Vec<u8>, LweError> {
+		check_len(ct, self.ciphertext_len())?;
+		self.check_compression(compression)?;
+
+		// the ciphertext is stored column by column, so the constant terms come last
+		let coefficients = decode_coefficients(ct, self.q);
+		let (vector, constants) = coefficients.split_at(self.n * self.plaintext_coefficients());
+
+		let compressed_vector: Vec<i64> = vector.iter().map(|x| compress(*x, compression.du, self.q)).collect();
+		let compressed_constants: Vec<i64> = constants.iter().map(|x| compress(*x, compression.dv, self.q)).collect();
+
+		let mut packed = pack_coefficients(&compressed_vector, compression.du);
+		packed.extend(pack_coefficients(&compressed_constants, compression.dv));
+
+		Ok(packed)
+	}
+
+	/// Reads a compressed ciphertext back into one that can be decrypted as usual
+	pub fn decompress_ciphertext(&self, packed: &[u8], compression: &CiphertextCompression) -> Result<Ciphertext, PackingError> {
+		if let Some(bits) = self.invalid_bits(compression) {
+			return Err(PackingError::InvalidCompression { bits, max: self.coefficient_bits() });
+		}
+
+		let k = self.plaintext_coefficients();
+		let expected = packed_len(k * self.n, compression.du) + packed_len(k, compression.dv);
+		if packed.len() != expected {
+			return Err(PackingError::WrongLength { expected, actual: packed.len() });
+		}
+
+		let (packed_vector, packed_constants) = packed.split_at(packed_len(k * self.n, compression.du));
+
+		let mut coefficients: Vec<i64> = unpack_coefficients(packed_vector, k * self.n, compression.du, 1 << compression.du)?
+			.into_iter()
+			.map(|y| decompress(y, compression.du, self.q))
+			.collect();
+
+		coefficients.extend(unpack_coefficients(packed_constants, k, compression.dv, 1 << compression.dv)?
+			.into_iter()
+			.map(|y| decompress(y, compression.dv, self.q)));
+
+		let mut ct = vec![0 ; self.ciphertext_len()];
+		encode_coefficients(&coefficients, &mut ct);
+
+		Ok(ct)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::Rng;
+
+	use crate::lwe::{LweError, LweParams, Plaintext};
+
+	use super::{compress, decompress, CiphertextCompression, PackingError};
+
+	#[test]
+	fn test_rounding_error() {
+		// every x mod q comes back within the promised distance, going around mod q
+		for params in [LweParams::DEFAULT, LweParams::TOY, LweParams::new(8, 8, 4096, 1)] {
+			for d in 1..=params.coefficient_bits() {
+				let bound = params.compression_error(d);
+
+				for x in 0..params.q {
+					let y = compress(x, d, params.q);
+					assert!(y < (1 << d));
+
+					let back = decompress(y, d, params.q);
+					assert!((0..params.q).contains(&back));
+
+					let difference = (back - x).rem_euclid(params.q);
+					assert!(difference.min(params.q - difference) <= bound, "q = {:?}, d = {:?}, x = {:?}", params.q, d, x);
+				}
+			}
+		}
+
+		let params = LweParams::DEFAULT;
+		assert_eq!(params.compression_error(1), 832);
+		assert_eq!(params.compression_error(10), 2);
+		assert_eq!(params.compression_error(11), 1);
+		assert_eq!(params.compression_error(12), 0);
+	}
+
+	#[test]
+	fn test_compressed_decryption() {
+		for (params, dv) in [(LweParams::DEFAULT, 6), (LweParams::TOY, 3), (LweParams::new(100, 30, 1 << 20, 8), 2), (LweParams::new(100, 30, 1 << 20, 8).with_bits_per_coefficient(4), 5)] {
+			// the vector has to stay lossless, but the constant terms can lose most of their bits
+			let compression = CiphertextCompression { du: params.coefficient_bits(), dv };
+			assert_eq!(params.compressed_failure_bound(&compression).unwrap(), 0.0);
+			assert!(params.compressed_ciphertext_len(&compression).unwrap() < params.packed_ciphertext_len());
+
+			let (sk, pk) = params.gen();
+
+			for _ in 0..4 {
+				let plaintext: Plaintext = rand::thread_rng().gen();
+				let compressed = params.compress_ciphertext(&params.enc(pk.clone(), plaintext).unwrap(), &compression).unwrap();
+
+				assert_eq!(compressed.len(), params.compressed_ciphertext_len(&compression).unwrap());
+				assert_eq!(params.dec(&sk, params.decompress_ciphertext(&compressed, &compression).unwrap()).unwrap(), plaintext);
+			}
+
+			// one bit fewer in the constant terms is already too much rounding
+			let too_far = CiphertextCompression { dv: dv - 1, ..compression };
+			assert!(params.compressed_failure_bound(&too_far).unwrap() > 0.0);
+		}
+	}
+
+	#[test]
+	fn test_lossy_vector() {
+		// rounding the vector at all gets multiplied by a uniformly random secret, which leaves
+		// nothing to decrypt
+		let params = LweParams::DEFAULT;
+		let compression = CiphertextCompression { du: 11, dv: 12 };
+		assert_eq!(params.compressed_failure_bound(&compression).unwrap(), 1.0);
+
+		let (sk, pk) = params.gen();
+		let plaintext: Plaintext = rand::thread_rng().gen();
+		let compressed = params.compress_ciphertext(&params.enc(pk, plaintext).unwrap(), &compression).unwrap();
+
+		assert_ne!(params.dec(&sk, params.decompress_ciphertext(&compressed, &compression).unwrap()).unwrap(), plaintext);
+	}
+
+	#[test]
+	fn test_strict_decompression() {
+		let params = LweParams::TOY;
+		let compression = CiphertextCompression { du: 9, dv: 3 };
+		let (_, pk) = params.gen();
+
+		let compressed = params.compress_ciphertext(&params.enc(pk, [0 ; 32]).unwrap(), &compression).unwrap();
+		assert_eq!(compressed.len(), (8 * 256 * 9) / 8 + (256 * 3) / 8);
+
+		assert_eq!(params.decompress_ciphertext(&compressed[1..], &compression), Err(PackingError::WrongLength { expected: compressed.len(), actual: compressed.len() - 1 }));
+	}
+
+	#[test]
+	fn test_invalid_compression() {
+		// too few or too many bits is an error everywhere, rather than a panic
+		let params = LweParams::TOY;
+		let max = params.coefficient_bits();
+		let (_, pk) = params.gen();
+		let ct = params.enc(pk, [0 ; 32]).unwrap();
+
+		for (compression, bits) in [(CiphertextCompression { du: 0, dv: 3 }, 0), (CiphertextCompression { du: max, dv: max + 1 }, max + 1), (CiphertextCompression { du: usize::MAX, dv: 3 }, usize::MAX)] {
+			let error = LweError::InvalidCompression { bits, max };
+
+			assert_eq!(params.compression_noise(&compression), Err(error.clone()));
+			assert_eq!(params.compressed_failure_bound(&compression), Err(error.clone()));
+			assert_eq!(params.compressed_ciphertext_len(&compression), Err(error.clone()));
+			assert_eq!(params.compress_ciphertext(&ct, &compression), Err(error));
+			assert_eq!(params.decompress_ciphertext(&[0 ; 16], &compression), Err(PackingError::InvalidCompression { bits, max }));
+		}
+
+		// and no number of bits overflows the rounding error
+		for d in [62, 63, 64, 1000, usize::MAX] {
+			assert_eq!(params.compression_error(d), 0);
+		}
+	}
+}
@@ -0,0 +1,134 @@
+//
+// Expanding a short public seed into the uniformly random matrix A, so that public keys
+// only need to carry the seed instead of all of A
+//
+
+use rand::{CryptoRng, RngCore};
+
+use crate::{secret::Scrub, sha512};
+
+use super::matrix::Matrix;
+
+/// The length, in bytes, of the seed A is expanded from
+pub const SEED_LEN: usize = 32;
+
+/// The seed A is expanded from
+pub type Seed = [u8 ; SEED_LEN];
+
+/// Separates the hashes done here from any other use of SHA-512 with the same seed
+const DOMAIN: &[u8] = b"rusty_crypto lwe A";
+
+/// An extendable-output function made from SHA-512 in counter mode, where block i of the output is
+/// SHA-512(domain || seed || i), with i as a big-endian u64.
+///
+/// This is also a `RngCore`, for anything that has to be drawn deterministically from a seed.
+pub(crate) struct Sha512Xof<'a> {
+	domain: &'a [u8],
+	seed: &'a [u8],
+	counter: u64,
+	block: sha512::Digest,
+	used: usize
+}
+
+impl<'a> Sha512Xof<'a> {
+	pub(crate) fn new(domain: &'a [u8], seed: &'a [u8]) -> Sha512Xof<'a> {
+		// starting out with the block used up, so the first read hashes block 0
+		Sha512Xof { domain, seed, counter: 0, block: [0 ; sha512::DIGEST_BYTE_COUNT], used: sha512::DIGEST_BYTE_COUNT }
+	}
+
+	fn next_byte(&mut self) -> u8 {
+		if self.used == self.block.len() {
+			let mut input = Vec::with_capacity(self.domain.len() + self.seed.len() + 8);
+			input.extend_from_slice(self.domain);
+			input.extend_from_slice(self.seed);
+			input.extend_from_slice(&self.counter.to_be_bytes());
+
+			self.block = sha512::hash(input);
+			self.counter += 1;
+			self.used = 0;
+		}
+
+		let byte = self.block[self.used];
+		self.used += 1;
+
+		byte
+	}
+}
+
+impl RngCore for Sha512Xof<'_> {
+	fn next_u32(&mut self) -> u32 {
+		u32::from_le_bytes(std::array::from_fn(|_| self.next_byte()))
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		u64::from_le_bytes(std::array::from_fn(|_| self.next_byte()))
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		for byte in dest.iter_mut() {
+			*byte = self.next_byte();
+		}
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
+
+impl CryptoRng for Sha512Xof<'_> { }
+
+impl Drop for Sha512Xof<'_> {
+	fn drop(&mut self) {
+		// the output may have been used for something secret
+		self.block.scrub();
+	}
+}
+
+/// A value uniform in [0, bound), for a bound of at most 2^31.
+///
+/// This takes the low ceil(log2 bound) bits of the next u32 from `rng`, and draws again if that
+/// is `bound` or more, so there is no bias towards small values. With a `Sha512Xof` that u32 is
+/// the next four bytes of output, read little-endian, so unlike `Rng::gen_range` the result is
+/// pinned down to the byte.
+pub(crate) fn uniform_below(bound: i64, rng: &mut dyn RngCore) -> i64 {
+	let bits = 64 - (bound - 1).leading_zeros();
+	let mask = (1u64 << bits) - 1;
+
+	loop {
+		let candidate = (rng.next_u32() as u64 & mask) as i64;
+
+		if candidate < bound {
+			return candidate;
+		}
+	}
+}
+
+/// Deterministically expands `seed` into a `rows` by `cols` matrix, uniform mod q.
+///
+/// Each entry is drawn with `uniform_below`, and entries are filled in column by column.
+pub(crate) fn expand_matrix(seed: &Seed, rows: usize, cols: usize, q: i64) -> Matrix {
+	let mut xof = Sha512Xof::new(DOMAIN, seed);
+
+	Matrix::from_fn(rows, cols, q, || uniform_below(q, &mut xof))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::expand_matrix;
+
+	#[test]
+	fn test_expansion() {
+		let a = expand_matrix(&[7 ; 32], 40, 30, 3329);
+
+		// the same seed always gives the same matrix, and a different one doesn't
+		assert_eq!(a, expand_matrix(&[7 ; 32], 40, 30, 3329));
+		assert_ne!(a, expand_matrix(&[8 ; 32], 40, 30, 3329));
+
+		// everything is in range, and roughly uniform
+		assert!(a.entries().iter().all(|x| (0..3329).contains(x)));
+
+		let mean = a.entries().iter().sum::<i64>() as f64 / a.entries().len() as f64;
+		assert!((mean - 1664.0).abs() < 100.0, "mean of {:?} is far from q / 2", mean);
+	}
+}
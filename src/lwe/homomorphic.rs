@@ -0,0 +1,324 @@
+//
+// Additively homomorphic operations on LWE ciphertexts. Adding two ciphertexts under the same key
+// gives a ciphertext of the XOR of their plaintexts, at the cost of adding their errors together,
+// so the error each ciphertext could be carrying is tracked along with it. With more than one bit
+// per coefficient, each group of bits is added mod 2^bits instead.
+//
+
+use std::fmt::Display;
+
+use super::{bits_to_values, check_len, decode_coefficients, encode_coefficients, encode_value, pt_to_matrix_rep, Ciphertext, LweError, LweParams, Plaintext};
+
+/// The largest chance of a decryption failure an operation will accept before refusing to go on
+pub const NOISE_BUDGET_THRESHOLD: f64 = 1.0 / (1u64 << 32) as f64;
+
+/// An operation would leave a ciphertext too noisy to be reliably decrypted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoiseBudgetExceeded {
+	/// The bound on the chance of a decryption failure the result would have had
+	pub failure_bound: f64
+}
+
+impl Display for NoiseBudgetExceeded {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Noise budget exceeded: decryption could fail with probability up to {:e}", self.failure_bound)
+	}
+}
+
+impl std::error::Error for NoiseBudgetExceeded { }
+
+/// Why a homomorphic operation couldn't be done
+#[derive(Clone, Debug, PartialEq)]
+pub enum HomomorphicError {
+	/// A ciphertext doesn't fit these parameters, most likely because it was made under others
+	Lwe(LweError),
+
+	/// The result would be too noisy to decrypt
+	NoiseBudgetExceeded(NoiseBudgetExceeded)
+}
+
+impl Display for HomomorphicError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			HomomorphicError::Lwe(error) => error.fmt(f),
+			HomomorphicError::NoiseBudgetExceeded(error) => error.fmt(f)
+		}
+	}
+}
+
+impl std::error::Error for HomomorphicError { }
+
+impl From<LweError> for HomomorphicError {
+	fn from(error: LweError) -> Self {
+		HomomorphicError::Lwe(error)
+	}
+}
+
+impl From<NoiseBudgetExceeded> for HomomorphicError {
+	fn from(error: NoiseBudgetExceeded) -> Self {
+		HomomorphicError::NoiseBudgetExceeded(error)
+	}
+}
+
+/// A ciphertext, along with what is known about the error in each of its coefficients
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackedCiphertext {
+	ciphertext: Ciphertext,
+
+	/// The largest the error in any coefficient could possibly be
+	max_noise: i64,
+
+	/// The largest fixed offset the operations themselves could have added to the error, from
+	/// rounding q / 2^bits down when q isn't a multiple of it
+	drift: i64,
+
+	/// A subgaussian variance proxy for the rest of the error
+	variance: f64
+}
+
+impl TrackedCiphertext {
+	/// The ciphertext itself
+	pub fn ciphertext(&self) -> &Ciphertext {
+		&self.ciphertext
+	}
+
+	/// Gives up the tracking, leaving just the ciphertext
+	pub fn into_ciphertext(self) -> Ciphertext {
+		self.ciphertext
+	}
+
+	/// The largest the error in any coefficient of this ciphertext could possibly be
+	pub fn max_noise(&self) -> i64 {
+		self.max_noise
+	}
+}
+
+impl LweParams {
+
+	/// The index of the constant term for each coefficient, in the last column of a ciphertext
+	fn constant_terms(&self) -> std::ops::Range<usize> {
+		let k = self.plaintext_coefficients();
+		(self.n * k)..((self.n + 1) * k)
+	}
+
+	/// How far off adding two encoded values can be from encoding their sum, which is 1 unless
+	/// q / 2^bits is a whole number
+	fn rounding_drift(&self) -> i64 {
+		(self.q % (1 << self.bits_per_coefficient) != 0) as i64
+	}
+
+	/// Starts tracking the noise in `ct`, which must be a fresh ciphertext from `enc`, or fails if
+	/// it is the wrong length for these parameters
+	pub fn track(&self, ct: Ciphertext) -> Result<TrackedCiphertext, LweError> {
+		check_len(&ct, self.ciphertext_len())?;
+
+		Ok(TrackedCiphertext { ciphertext: ct, max_noise: self.max_error(), drift: 0, variance: self.fresh_variance() })
+	}
+
+	/// An upper bound on the probability that `ct` decrypts incorrectly, in the same way as
+	/// `failure_bound` is for a fresh ciphertext
+	pub fn decryption_failure_bound(&self, ct: &TrackedCiphertext) -> f64 {
+		self.failure_bound_with(ct.max_noise, ct.drift, ct.variance)
+	}
+
+	/// Checks the result of an operation against the noise budget
+	fn within_budget(&self, ct: TrackedCiphertext) -> Result<TrackedCiphertext, NoiseBudgetExceeded> {
+		let failure_bound = self.decryption_failure_bound(&ct);
+
+		if failure_bound > NOISE_BUDGET_THRESHOLD {
+			Err(NoiseBudgetExceeded { failure_bound })
+		} else {
+			Ok(ct)
+		}
+	}
+
+	/// Adds two ciphertexts under the same key, giving a ciphertext of the XOR of their plaintexts,
+	/// or of their sum mod 2^bits in each coefficient with more than one bit per coefficient.
+	///
+	/// Fails if either ciphertext is the wrong length for these parameters, or if the sum would be
+	/// more likely to decrypt incorrectly than `NOISE_BUDGET_THRESHOLD`.
+	pub fn add(&self, a: &TrackedCiphertext, b: &TrackedCiphertext) -> Result<TrackedCiphertext, HomomorphicError> {
+		check_len(&a.ciphertext, self.ciphertext_len())?;
+		check_len(&b.ciphertext, self.ciphertext_len())?;
+
+		let sum: Vec<i64> = decode_coefficients(&a.ciphertext, self.q).iter()
+			.zip(decode_coefficients(&b.ciphertext, self.q))
+			.map(|(x, y)| (x + y) % self.q)
+			.collect();
+
+		let mut ciphertext = vec![0 ; self.ciphertext_len()];
+		encode_coefficients(&sum, &mut ciphertext);
+
+		// with one bit, two 1's add up to 2 floor(q/2), which is -1 rather than 0 when q is odd
+		let drift = self.rounding_drift();
+
+		Ok(self.within_budget(TrackedCiphertext {
+			ciphertext,
+			max_noise: a.max_noise + b.max_noise + drift,
+			drift: a.drift + b.drift + drift,
+			// both errors come from the same public key, so they aren't independent, but this
+			// holds however they are related
+			variance: (a.variance.sqrt() + b.variance.sqrt()).powi(2)
+		})?)
+	}
+
+	/// Adds a known plaintext to a ciphertext, in the same way as `add`, with no more error than
+	/// before apart from rounding.
+	///
+	/// Fails if the ciphertext is the wrong length for these parameters, or if the result would be
+	/// more likely to decrypt incorrectly than `NOISE_BUDGET_THRESHOLD`.
+	pub fn add_plaintext(&self, ct: &TrackedCiphertext, pt: &Plaintext) -> Result<TrackedCiphertext, HomomorphicError> {
+		check_len(&ct.ciphertext, self.ciphertext_len())?;
+
+		let mut coefficients = decode_coefficients(&ct.ciphertext, self.q);
+		let values = bits_to_values(&pt_to_matrix_rep(*pt), self.bits_per_coefficient);
+
+		for (index, value) in self.constant_terms().zip(values) {
+			coefficients[index] = (coefficients[index] + encode_value(value, self.q, self.bits_per_coefficient)) % self.q;
+		}
+
+		let mut ciphertext = vec![0 ; self.ciphertext_len()];
+		encode_coefficients(&coefficients, &mut ciphertext);
+
+		let drift = self.rounding_drift();
+
+		Ok(self.within_budget(TrackedCiphertext {
+			ciphertext,
+			max_noise: ct.max_noise + drift,
+			drift: ct.drift + drift,
+			variance: ct.variance
+		})?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::Rng;
+
+	use crate::lwe::{HomomorphicError, LweError, LweParams, Plaintext, PLAINTEXT_LEN};
+
+	fn xor(a: &Plaintext, b: &Plaintext) -> Plaintext {
+		std::array::from_fn(|i| a[i] ^ b[i])
+	}
+
+	fn random_plaintext() -> Plaintext {
+		let mut pt = [0 ; PLAINTEXT_LEN];
+		rand::thread_rng().fill(&mut pt);
+		pt
+	}
+
+	#[test]
+	fn test_add() {
+		// an even modulus and an odd one, both with plenty of room for noise
+		for params in [LweParams::new(100, 30, 1 << 20, 8), LweParams::new(100, 30, 1_000_003, 8)] {
+			let (sk, pk) = params.gen();
+
+			let (x, y) = (random_plaintext(), random_plaintext());
+			let a = params.track(params.enc(pk.clone(), x).unwrap()).unwrap();
+			let b = params.track(params.enc(pk.clone(), y).unwrap()).unwrap();
+
+			let sum = params.add(&a, &b).unwrap();
+			assert_eq!(params.dec(&sk, sum.ciphertext().clone()).unwrap(), xor(&x, &y));
+			assert!(sum.max_noise() >= a.max_noise() + b.max_noise());
+
+			// adding a ciphertext to itself always gives 0
+			let doubled = params.add(&a, &a).unwrap();
+			assert_eq!(params.dec(&sk, doubled.into_ciphertext()).unwrap(), [0 ; PLAINTEXT_LEN]);
+		}
+	}
+
+	#[test]
+	fn test_add_multiple_bits() {
+		// with 4 bits per coefficient, every nibble is added mod 16
+		let params = LweParams::new(100, 30, 1_000_003, 8).with_bits_per_coefficient(4);
+		let (sk, pk) = params.gen();
+
+		let nibble_sum = |x: &Plaintext, y: &Plaintext| -> Plaintext {
+			std::array::from_fn(|i| (((x[i] & 0x0f) + (y[i] & 0x0f)) & 0x0f) | (((x[i] >> 4) + (y[i] >> 4)) << 4))
+		};
+
+		let (x, y, z) = (random_plaintext(), random_plaintext(), random_plaintext());
+		let a = params.track(params.enc(pk.clone(), x).unwrap()).unwrap();
+		let b = params.track(params.enc(pk, y).unwrap()).unwrap();
+
+		let sum = params.add_plaintext(&params.add(&a, &b).unwrap(), &z).unwrap();
+		assert_eq!(params.dec(&sk, sum.into_ciphertext()).unwrap(), nibble_sum(&nibble_sum(&x, &y), &z));
+	}
+
+	#[test]
+	fn test_add_plaintext() {
+		for params in [LweParams::TOY, LweParams::DEFAULT, LweParams::new(100, 30, 1 << 20, 8)] {
+			let (sk, pk) = params.gen();
+
+			let (x, y) = (random_plaintext(), random_plaintext());
+			let ct = params.track(params.enc(pk, x).unwrap()).unwrap();
+
+			let sum = params.add_plaintext(&ct, &y).unwrap();
+			assert_eq!(params.dec(&sk, sum.into_ciphertext()).unwrap(), xor(&x, &y));
+		}
+	}
+
+	#[test]
+	fn test_tally() {
+		// the parity of a pile of encrypted ballots, without decrypting any of them
+		let params = LweParams::new(100, 30, 1 << 24, 8);
+		let (sk, pk) = params.gen();
+
+		let ballots: Vec<Plaintext> = (0..16).map(|_| random_plaintext()).collect();
+		let expected = ballots.iter().fold([0 ; PLAINTEXT_LEN], |acc, ballot| xor(&acc, ballot));
+
+		let tally = ballots.iter()
+			.map(|ballot| params.track(params.enc(pk.clone(), *ballot).unwrap()).unwrap())
+			.reduce(|acc, ct| params.add(&acc, &ct).unwrap())
+			.unwrap();
+
+		assert_eq!(params.dec(&sk, tally.into_ciphertext()).unwrap(), expected);
+	}
+
+	#[test]
+	fn test_noise_budget() {
+		let params = LweParams::DEFAULT;
+		let (_, pk) = params.gen();
+
+		// a fresh ciphertext can't fail at all, but the sum of two might
+		let ct = params.track(params.enc(pk, random_plaintext()).unwrap()).unwrap();
+		assert_eq!(params.decryption_failure_bound(&ct), 0.0);
+
+		match params.add(&ct, &ct) {
+			Err(HomomorphicError::NoiseBudgetExceeded(error)) => assert!(error.failure_bound > super::NOISE_BUDGET_THRESHOLD),
+			other => panic!("Expected the noise budget to run out, got {:?}", other)
+		}
+
+		// with a bigger modulus the noise keeps adding up until it runs out of room
+		let params = LweParams::new(100, 30, 1 << 16, 8);
+		let (_, pk) = params.gen();
+		let fresh = params.track(params.enc(pk, random_plaintext()).unwrap()).unwrap();
+
+		let mut ct = fresh.clone();
+		let mut additions = 0;
+		while let Ok(sum) = params.add(&ct, &fresh) {
+			assert!(params.decryption_failure_bound(&sum) >= params.decryption_failure_bound(&ct));
+			ct = sum;
+			additions += 1;
+		}
+
+		assert!(additions > 0);
+		assert!(params.decryption_failure_bound(&ct) <= super::NOISE_BUDGET_THRESHOLD);
+	}
+
+	#[test]
+	fn test_mismatched_params() {
+		// ciphertexts tracked under other parameters are turned away rather than panicking
+		let (small, big) = (LweParams::TOY, LweParams::new(100, 30, 1 << 20, 8));
+		let (_, small_pk) = small.gen();
+		let (_, big_pk) = big.gen();
+
+		let a = small.track(small.enc(small_pk, random_plaintext()).unwrap()).unwrap();
+		let b = big.track(big.enc(big_pk, random_plaintext()).unwrap()).unwrap();
+		let expected = HomomorphicError::Lwe(LweError::WrongLength { expected: big.ciphertext_len(), actual: small.ciphertext_len() });
+
+		assert_eq!(big.add(&a, &b), Err(expected.clone()));
+		assert_eq!(big.add(&b, &a), Err(expected.clone()));
+		assert_eq!(big.add_plaintext(&a, &random_plaintext()), Err(expected));
+	}
+}
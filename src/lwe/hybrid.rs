@@ -0,0 +1,235 @@
+//
+// Hybrid encryption of messages of any length: a fresh key is encapsulated with the LWE KEM, and
+// the message itself is encrypted with Speck under a key derived from it, the same way `lettuce`
+// does with Kyber. Everything is then authenticated under a second key derived from it, so that a
+// ciphertext that has been tampered with is turned away before anything is decrypted.
+//
+
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+
+use crate::secret::{eq_mask, Scrub, Secret};
+use crate::{sha3, sha512, speck};
+
+use super::{KemSecretKey, LweError, LweParams, PublicKey, SharedSecret};
+
+/// A type for the plaintext, an arbitrary string of bytes
+pub type Plaintext = Vec<u8>;
+
+/// The encapsulated key, followed by the message encrypted with Speck, followed by a `TAG_LEN`
+/// byte tag on both of them
+pub type Ciphertext = Vec<u8>;
+
+/// The length, in bytes, of the tag at the end of a ciphertext
+pub const TAG_LEN: usize = 32;
+
+/// Keeps the Speck key separate from anything else the shared secret might be used for
+const SPECK_KEY_DOMAIN: &[u8] = b"rusty_crypto lwe hybrid speck key";
+
+/// Keeps the MAC key separate from the Speck key, and anything else the shared secret might be used for
+const MAC_KEY_DOMAIN: &[u8] = b"rusty_crypto lwe hybrid mac key";
+
+/// A 32-byte key for a shared secret, one for each domain
+fn derive_key(shared: &SharedSecret, domain: &[u8]) -> Secret<[u8 ; 32]> {
+	// allocated at its full size up front, so that growing it never leaves a copy of the shared
+	// secret behind, and `sha512::hash` wipes it once it's done
+	let mut input = Vec::with_capacity(domain.len() + shared.expose().len());
+	input.extend_from_slice(domain);
+	input.extend_from_slice(shared.expose());
+
+	let mut digest = sha512::hash(input);
+
+	let mut key = Secret::new([0 ; 32]);
+	key.expose_mut().copy_from_slice(&digest[0..32]);
+	digest.scrub();
+
+	key
+}
+
+/// The tag on the rest of a ciphertext, SHA3-256 of the MAC key followed by the ciphertext, which
+/// unlike SHA-512 can't be extended to a tag on a longer message without the key
+fn tag(mac_key: &Secret<[u8 ; 32]>, ciphertext: &[u8]) -> [u8 ; TAG_LEN] {
+	let mut input = Vec::with_capacity(mac_key.expose().len() + ciphertext.len());
+	input.extend_from_slice(mac_key.expose());
+	input.extend_from_slice(ciphertext);
+
+	let tag = sha3::sha3_256(&input);
+	input.scrub();
+
+	tag
+}
+
+impl LweParams {
+
+	/// Encrypts a message of any length under a KEM public key from `keygen`
+	pub fn hybrid_enc(&self, pk: PublicKey, plaintext: Plaintext) -> Result<Ciphertext, LweError> {
+		self.hybrid_enc_with_rng(pk, plaintext, &mut StdRng::from_entropy())
+	}
+
+	/// Encrypts a message of any length, drawing the encapsulated key from `rng`
+	pub fn hybrid_enc_with_rng(&self, pk: PublicKey, plaintext: Plaintext, rng: &mut (impl RngCore + CryptoRng)) -> Result<Ciphertext, LweError> {
+		let (mut ct, shared) = self.encaps_with_rng(&pk, rng)?;
+		ct.extend(speck::enc_vec(&derive_key(&shared, SPECK_KEY_DOMAIN), plaintext));
+
+		let tag = tag(&derive_key(&shared, MAC_KEY_DOMAIN), &ct);
+		ct.extend_from_slice(&tag);
+
+		Ok(ct)
+	}
+
+	/// Decrypts a message encrypted with `hybrid_enc`, using the KEM secret key.
+	///
+	/// This fails if the ciphertext is too short to hold the encapsulated key, a Speck block and
+	/// the tag, and with `LweError::Invalid` if the tag is wrong, which is what happens with the
+	/// wrong key or a ciphertext that has been tampered with, or if what it authenticates isn't
+	/// a message encrypted with Speck.
+	pub fn hybrid_dec(&self, sk: &KemSecretKey, ciphertext: Ciphertext) -> Result<Plaintext, LweError> {
+		let minimum = self.ciphertext_len() + speck::BLOCK_SIZE + TAG_LEN;
+		if ciphertext.len() < minimum {
+			return Err(LweError::TooShort { minimum, actual: ciphertext.len() });
+		}
+
+		let (authenticated, received_tag) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+		let (ct_kem, inner_ciphertext) = authenticated.split_at(self.ciphertext_len());
+		let shared: SharedSecret = self.decaps(sk, &ct_kem.to_vec())?;
+
+		// checked without stopping at the first byte that differs, and before decrypting anything
+		if eq_mask(&tag(&derive_key(&shared, MAC_KEY_DOMAIN), authenticated), received_tag) == 0 {
+			return Err(LweError::Invalid);
+		}
+
+		// anyone can encapsulate a key and tag whatever they like, so this still has to be checked
+		speck::try_dec_vec(&derive_key(&shared, SPECK_KEY_DOMAIN), inner_ciphertext.to_vec()).ok_or(LweError::Invalid)
+	}
+}
+
+/// Generates a key pair for hybrid encryption with the default parameters, which is just a KEM
+/// key pair
+pub fn gen() -> (KemSecretKey, PublicKey) {
+	LweParams::DEFAULT.keygen()
+}
+
+/// Generates a key pair for hybrid encryption with the default parameters, drawing everything
+/// from `rng`
+pub fn gen_with_rng(rng: &mut (impl RngCore + CryptoRng)) -> (KemSecretKey, PublicKey) {
+	LweParams::DEFAULT.keygen_with_rng(rng)
+}
+
+/// Encrypts a message of any length with the default parameters
+pub fn enc(pk: PublicKey, plaintext: Plaintext) -> Result<Ciphertext, LweError> {
+	LweParams::DEFAULT.hybrid_enc(pk, plaintext)
+}
+
+/// Encrypts a message of any length with the default parameters, drawing the encapsulated key
+/// from `rng`
+pub fn enc_with_rng(pk: PublicKey, plaintext: Plaintext, rng: &mut (impl RngCore + CryptoRng)) -> Result<Ciphertext, LweError> {
+	LweParams::DEFAULT.hybrid_enc_with_rng(pk, plaintext, rng)
+}
+
+/// Decrypts a message with the default parameters
+pub fn dec(sk: &KemSecretKey, ciphertext: Ciphertext) -> Result<Plaintext, LweError> {
+	LweParams::DEFAULT.hybrid_dec(sk, ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	use crate::lwe::{LweError, LweParams};
+	use crate::speck;
+
+	#[test]
+	fn test_symmetry() {
+		for length in [0, 1, 15, 16, 17, 1000, 1 << 16] {
+			let mut plaintext = vec![0u8 ; length];
+			rand::thread_rng().fill(&mut plaintext[..]);
+
+			let (sk, pk) = super::gen();
+			let ciphertext = super::enc(pk, plaintext.clone()).unwrap();
+
+			assert!(ciphertext.len() > LweParams::DEFAULT.ciphertext_len() + length);
+			assert_eq!(super::dec(&sk, ciphertext).unwrap(), plaintext);
+		}
+	}
+
+	#[test]
+	fn test_other_params() {
+		let plaintext = b"any parameter set with a KEM works the same way".to_vec();
+
+		for params in [LweParams::TOY, LweParams::TOY.with_seeded_matrix(), LweParams::new(100, 30, 1 << 20, 8).with_bits_per_coefficient(4)] {
+			let (sk, pk) = params.keygen();
+			assert_eq!(params.hybrid_dec(&sk, params.hybrid_enc(pk, plaintext.clone()).unwrap()).unwrap(), plaintext);
+		}
+	}
+
+	#[test]
+	fn test_too_short() {
+		let params = LweParams::TOY;
+		let (sk, pk) = params.keygen();
+		let ciphertext = params.hybrid_enc(pk, b"short".to_vec()).unwrap();
+
+		// the encapsulated key alone, with no Speck blocks or tag after it, is an error rather than a panic
+		let minimum = params.ciphertext_len() + speck::BLOCK_SIZE + super::TAG_LEN;
+		for length in [0, params.ciphertext_len(), minimum - 1] {
+			assert_eq!(params.hybrid_dec(&sk, ciphertext[0..length].to_vec()), Err(LweError::TooShort { minimum, actual: length }));
+		}
+	}
+
+	#[test]
+	fn test_tampering() {
+		let params = LweParams::TOY;
+		let (sk, pk) = params.keygen();
+		let ciphertext = params.hybrid_enc(pk, b"nobody should be able to change this".to_vec()).unwrap();
+
+		// a flipped bit anywhere, whether in the encapsulated key, the Speck blocks or the tag
+		for index in [0, params.ciphertext_len() - 1, params.ciphertext_len(), ciphertext.len() - super::TAG_LEN - 1, ciphertext.len() - 1] {
+			let mut tampered = ciphertext.clone();
+			tampered[index] ^= 1;
+			assert_eq!(params.hybrid_dec(&sk, tampered), Err(LweError::Invalid));
+		}
+
+		// blocks cut off the end, with the tag kept in place
+		let mut truncated = ciphertext[..(ciphertext.len() - super::TAG_LEN - speck::BLOCK_SIZE)].to_vec();
+		truncated.extend_from_slice(&ciphertext[(ciphertext.len() - super::TAG_LEN)..]);
+		assert_eq!(params.hybrid_dec(&sk, truncated), Err(LweError::Invalid));
+
+		// somebody else's key
+		let (other_sk, _) = params.keygen();
+		assert_eq!(params.hybrid_dec(&other_sk, ciphertext), Err(LweError::Invalid));
+	}
+
+	#[test]
+	fn test_authenticated_garbage() {
+		// anyone can encapsulate a key to the public key and tag what they like, so a Speck part
+		// that isn't whole blocks, or doesn't unpad, has to be turned away even with a good tag
+		let params = LweParams::TOY;
+		let (sk, pk) = params.keygen();
+
+		for inner in [vec![0x42u8 ; speck::BLOCK_SIZE + 3], vec![0u8 ; 2 * speck::BLOCK_SIZE]] {
+			let (mut ciphertext, shared) = params.encaps(&pk).unwrap();
+			ciphertext.extend(inner);
+
+			let tag = super::tag(&super::derive_key(&shared, super::MAC_KEY_DOMAIN), &ciphertext);
+			ciphertext.extend_from_slice(&tag);
+
+			assert_eq!(params.hybrid_dec(&sk, ciphertext), Err(LweError::Invalid));
+		}
+	}
+
+	#[test]
+	fn test_seeded_reproducibility() {
+		let plaintext = b"the same seed gives the same bytes".to_vec();
+
+		let (first_sk, first_pk) = super::gen_with_rng(&mut StdRng::seed_from_u64(45));
+		let (second_sk, second_pk) = super::gen_with_rng(&mut StdRng::seed_from_u64(45));
+
+		assert_eq!(first_pk, second_pk);
+		assert_eq!(first_sk, second_sk);
+
+		let first_ct = super::enc_with_rng(first_pk, plaintext.clone(), &mut StdRng::seed_from_u64(46)).unwrap();
+		let second_ct = super::enc_with_rng(second_pk, plaintext.clone(), &mut StdRng::seed_from_u64(46)).unwrap();
+
+		assert_eq!(first_ct, second_ct);
+		assert_eq!(super::dec(&first_sk, first_ct).unwrap(), plaintext);
+	}
+}
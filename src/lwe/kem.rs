@@ -0,0 +1,301 @@
+//
+// A key encapsulation mechanism built on LWE with the Fujisaki-Okamoto transform, so that a
+// secret key can be used over and over without ciphertexts leaking anything about it
+//
+
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+
+use crate::secret::{eq_mask, Scrub, Secret};
+use crate::sha512;
+
+use super::expand::Sha512Xof;
+use super::{check_len, Ciphertext, LweError, LweParams, Plaintext, PublicKey, SecretKey, KEY_SEED_LEN, PLAINTEXT_LEN};
+
+/// The length, in bytes, of an encapsulated shared secret
+pub const SHARED_SECRET_LEN: usize = 32;
+
+/// The length, in bytes, of the hashes and the rejection secret stored in a KEM secret key
+const HASH_LEN: usize = 32;
+
+/// A KEM secret key: the LWE secret key, the public key, a hash of the public key and a random
+/// secret z for implicit rejection, one after the other
+pub type KemSecretKey = Secret<Vec<u8>>;
+
+/// The secret both sides end up with
+pub type SharedSecret = Secret<[u8 ; SHARED_SECRET_LEN]>;
+
+// Every hash here gets its own domain, so none of them can be mistaken for another
+const H_DOMAIN: &[u8] = b"rusty_crypto lwe kem H";
+const G_DOMAIN: &[u8] = b"rusty_crypto lwe kem G";
+const KDF_DOMAIN: &[u8] = b"rusty_crypto lwe kem KDF";
+const COINS_DOMAIN: &[u8] = b"rusty_crypto lwe kem coins";
+const KEYGEN_DOMAIN_V2: &[u8] = b"rusty_crypto lwe kem keygen v2";
+
+/// SHA-512 of the domain followed by all of `parts`
+fn hash(domain: &[u8], parts: &[&[u8]]) -> sha512::Digest {
+	// allocated at its full size up front, so that growing it never leaves a copy of a secret
+	// part behind, and `sha512::hash` wipes it once it's done
+	let mut input = Vec::with_capacity(domain.len() + parts.iter().map(|part| part.len()).sum::<usize>());
+	input.extend_from_slice(domain);
+	for part in parts {
+		input.extend_from_slice(part);
+	}
+
+	sha512::hash(input)
+}
+
+impl LweParams {
+
+	/// The length, in bytes, of a KEM secret key
+	pub const fn kem_secret_key_len(&self) -> usize {
+		self.secret_key_len() + self.public_key_len() + 2 * HASH_LEN
+	}
+
+	/// Encrypts `m` with every random choice drawn from `coins`, so the same message and coins
+	/// always give the same ciphertext. `pk` has to have been checked to be the right length.
+	fn enc_derandomized(&self, pk: &PublicKey, m: &Plaintext, coins: &[u8]) -> Ciphertext {
+		self.enc_with_rng(pk.clone(), *m, &mut Sha512Xof::new(COINS_DOMAIN, coins))
+			.expect("The public key was checked to be the right length")
+	}
+
+	/// The shared secret, from the pre-key (or z, on rejection) and the ciphertext's hash
+	fn kdf(key: &[u8], ct_hash: &[u8]) -> SharedSecret {
+		let mut digest = hash(KDF_DOMAIN, &[key, ct_hash]);
+
+		let mut shared = [0 ; SHARED_SECRET_LEN];
+		shared.copy_from_slice(&digest[0..SHARED_SECRET_LEN]);
+		digest.scrub();
+
+		Secret::new(shared)
+	}
+
+	/// Generates a KEM key pair for this parameter set
+	pub fn keygen(&self) -> (KemSecretKey, PublicKey) {
+		self.keygen_with_rng(&mut StdRng::from_entropy())
+	}
+
+	/// Generates a KEM key pair, drawing everything from `rng`
+	pub fn keygen_with_rng(&self, rng: &mut (impl RngCore + CryptoRng)) -> (KemSecretKey, PublicKey) {
+		let (sk, pk) = self.gen_with_rng(rng);
+
+		let mut kem_sk = KemSecretKey::new(vec![0 ; self.kem_secret_key_len()]);
+		let bytes = kem_sk.expose_mut();
+
+		let (sk_part, rest) = bytes.split_at_mut(self.secret_key_len());
+		let (pk_part, rest) = rest.split_at_mut(self.public_key_len());
+		let (pk_hash, z) = rest.split_at_mut(HASH_LEN);
+
+		sk_part.copy_from_slice(sk.expose());
+		pk_part.copy_from_slice(&pk);
+		pk_hash.copy_from_slice(&hash(H_DOMAIN, &[&pk])[0..HASH_LEN]);
+		rng.fill_bytes(z);
+
+		(kem_sk, pk)
+	}
+
+	/// Regenerates the same KEM key pair every time from a 32-byte seed.
+	///
+	/// Version 2 of the derivation reads everything from SHA-512 in counter mode, block i being
+	/// SHA-512("rusty_crypto lwe kem keygen v2" || seed || i) with i a big-endian u64: the LWE
+	/// key pair in the same way as `gen_from_seed`, then the 32 bytes of z. Version 1 went through
+	/// `Rng::gen_range`, and has been dropped.
+	pub fn keygen_from_seed(&self, seed: [u8 ; KEY_SEED_LEN]) -> (KemSecretKey, PublicKey) {
+		let mut seed = seed;
+		let keys = self.keygen_with_rng(&mut Sha512Xof::new(KEYGEN_DOMAIN_V2, &seed));
+		seed.scrub();
+
+		keys
+	}
+
+	/// Makes up a fresh shared secret and encapsulates it under `pk`, or fails if `pk` is the
+	/// wrong length for these parameters
+	pub fn encaps(&self, pk: &PublicKey) -> Result<(Ciphertext, SharedSecret), LweError> {
+		self.encaps_with_rng(pk, &mut StdRng::from_entropy())
+	}
+
+	/// Makes up a fresh shared secret from `rng` and encapsulates it under `pk`
+	pub fn encaps_with_rng(&self, pk: &PublicKey, rng: &mut (impl RngCore + CryptoRng)) -> Result<(Ciphertext, SharedSecret), LweError> {
+		check_len(pk, self.public_key_len())?;
+
+		let mut m: Plaintext = [0 ; PLAINTEXT_LEN];
+		rng.fill_bytes(&mut m);
+
+		// the pre-key and the encryption coins both come from the message and the public key
+		let mut g = hash(G_DOMAIN, &[&m, &hash(H_DOMAIN, &[pk])[0..HASH_LEN]]);
+		let ct = self.enc_derandomized(pk, &m, &g[HASH_LEN..(2 * HASH_LEN)]);
+		let shared = LweParams::kdf(&g[0..HASH_LEN], &hash(H_DOMAIN, &[&ct])[0..HASH_LEN]);
+
+		m.scrub();
+		g.scrub();
+
+		Ok((ct, shared))
+	}
+
+	/// Recovers the shared secret encapsulated in `ct`.
+	///
+	/// The message is decrypted and encrypted again, and if that doesn't give back exactly `ct`,
+	/// the result is a pseudorandom value derived from the secret z instead. Either way this takes
+	/// the same steps, so a tampered ciphertext tells an attacker nothing. The only thing that is
+	/// an error is `ct` or `sk` being the wrong length, which anyone can see anyway.
+	pub fn decaps(&self, sk: &KemSecretKey, ct: &Ciphertext) -> Result<SharedSecret, LweError> {
+		check_len(ct, self.ciphertext_len())?;
+		check_len(sk.expose(), self.kem_secret_key_len())?;
+
+		let bytes = sk.expose();
+		let (sk_part, rest) = bytes.split_at(self.secret_key_len());
+		let (pk_part, rest) = rest.split_at(self.public_key_len());
+		let (pk_hash, z) = rest.split_at(HASH_LEN);
+
+		let lwe_sk = SecretKey::new(sk_part.to_vec());
+		let pk = pk_part.to_vec();
+
+		let mut m = self.dec(&lwe_sk, ct.clone())?;
+		let mut g = hash(G_DOMAIN, &[&m, pk_hash]);
+		let reencrypted = self.enc_derandomized(&pk, &m, &g[HASH_LEN..(2 * HASH_LEN)]);
+
+		let ct_hash = hash(H_DOMAIN, &[ct]);
+		let accepted = LweParams::kdf(&g[0..HASH_LEN], &ct_hash[0..HASH_LEN]);
+		let rejected = LweParams::kdf(z, &ct_hash[0..HASH_LEN]);
+
+		// pick one without branching on which
+		let mask = eq_mask(&reencrypted, ct);
+		let mut shared = [0 ; SHARED_SECRET_LEN];
+		for (i, byte) in shared.iter_mut().enumerate() {
+			*byte = (accepted.expose()[i] & mask) | (rejected.expose()[i] & !mask);
+		}
+
+		m.scrub();
+		g.scrub();
+
+		Ok(Secret::new(shared))
+	}
+}
+
+/// Generates a KEM key pair with the default parameters
+pub fn keygen() -> (KemSecretKey, PublicKey) {
+	LweParams::DEFAULT.keygen()
+}
+
+/// Regenerates the same KEM key pair every time from a 32-byte seed, with the default parameters
+pub fn keygen_from_seed(seed: [u8 ; KEY_SEED_LEN]) -> (KemSecretKey, PublicKey) {
+	LweParams::DEFAULT.keygen_from_seed(seed)
+}
+
+/// Encapsulates a fresh shared secret with the default parameters
+pub fn encaps(pk: &PublicKey) -> Result<(Ciphertext, SharedSecret), LweError> {
+	LweParams::DEFAULT.encaps(pk)
+}
+
+/// Recovers an encapsulated shared secret with the default parameters
+pub fn decaps(sk: &KemSecretKey, ct: &Ciphertext) -> Result<SharedSecret, LweError> {
+	LweParams::DEFAULT.decaps(sk, ct)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use crate::lwe::{LweError, LweParams, SecretKey};
+
+	use super::{hash, H_DOMAIN, HASH_LEN};
+
+	#[test]
+	fn test_kem_round_trip() {
+		for params in [LweParams::TOY, LweParams::TOY.with_seeded_matrix(), LweParams::DEFAULT, LweParams::new(100, 30, 1 << 20, 8).with_bits_per_coefficient(8)] {
+			let (sk, pk) = params.keygen();
+			assert_eq!(sk.expose().len(), params.kem_secret_key_len());
+
+			let (ct, shared) = params.encaps(&pk).unwrap();
+			assert_eq!(params.decaps(&sk, &ct).unwrap(), shared);
+
+			// a second encapsulation agrees on something else entirely
+			let (other_ct, other_shared) = params.encaps(&pk).unwrap();
+			assert_ne!(ct, other_ct);
+			assert_ne!(shared, other_shared);
+		}
+	}
+
+	#[test]
+	fn test_encaps_deterministic() {
+		let params = LweParams::TOY;
+		let (_, pk) = params.keygen_with_rng(&mut StdRng::seed_from_u64(0x38));
+
+		let (first_ct, first_shared) = params.encaps_with_rng(&pk, &mut StdRng::seed_from_u64(0x39)).unwrap();
+		let (second_ct, second_shared) = params.encaps_with_rng(&pk, &mut StdRng::seed_from_u64(0x39)).unwrap();
+
+		assert_eq!(first_ct, second_ct);
+		assert_eq!(first_shared, second_shared);
+	}
+
+	#[test]
+	fn test_keygen_from_seed() {
+		let (sk, pk) = super::keygen_from_seed([0x46 ; 32]);
+		assert_eq!(super::keygen_from_seed([0x46 ; 32]), (sk.clone(), pk.clone()));
+
+		// a different domain from plain LWE keys, so the same seed gives unrelated keys
+		assert_ne!(crate::lwe::gen_from_seed([0x46 ; 32]).1, pk);
+
+		let (ct, shared) = super::encaps(&pk).unwrap();
+		assert_eq!(super::decaps(&sk, &ct).unwrap(), shared);
+	}
+
+	#[test]
+	fn test_implicit_rejection() {
+		let params = LweParams::TOY;
+		let (sk, pk) = params.keygen();
+		let (ct, shared) = params.encaps(&pk).unwrap();
+
+		// flipping one bit of one coefficient still decrypts to the same message, but it
+		// doesn't re-encrypt to the same ciphertext, so it is rejected
+		let mut tampered = ct.clone();
+		tampered[8] ^= 1;
+
+		let rejected = params.decaps(&sk, &tampered).unwrap();
+		assert_ne!(rejected, shared);
+
+		// rejection is deterministic, so it can't be told apart from a real shared secret
+		assert_eq!(params.decaps(&sk, &tampered).unwrap(), rejected);
+
+		// a non-canonical encoding of the same coefficients is rejected too
+		let mut noncanonical = ct.clone();
+		let coefficient = i64::from_le_bytes(ct[0..8].try_into().unwrap()) + params.q;
+		noncanonical[0..8].copy_from_slice(&coefficient.to_le_bytes());
+		assert_ne!(params.decaps(&sk, &noncanonical).unwrap(), shared);
+
+		// and so is a ciphertext for somebody else's key
+		let (other_sk, _) = params.keygen();
+		assert_ne!(params.decaps(&other_sk, &ct).unwrap(), shared);
+	}
+
+	#[test]
+	fn test_wrong_lengths() {
+		let params = LweParams::TOY;
+		let (sk, pk) = params.keygen();
+		let (ct, _) = params.encaps(&pk).unwrap();
+
+		// a malformed ciphertext or key is an error, rather than a panic
+		let short = ct[1..].to_vec();
+		assert_eq!(params.decaps(&sk, &short), Err(LweError::WrongLength { expected: params.ciphertext_len(), actual: ct.len() - 1 }));
+
+		let long = [ct.clone(), vec![0]].concat();
+		assert!(matches!(params.decaps(&sk, &long), Err(LweError::WrongLength { .. })));
+		assert!(matches!(params.encaps(&pk[8..].to_vec()), Err(LweError::WrongLength { .. })));
+
+		let lwe_sk = SecretKey::new(sk.expose()[8..params.secret_key_len()].to_vec());
+		assert!(matches!(params.dec(&lwe_sk, ct), Err(LweError::WrongLength { .. })));
+	}
+
+	#[test]
+	fn test_secret_key_layout() {
+		let params = LweParams::TOY;
+		let (sk, pk) = params.keygen();
+		let bytes = sk.expose();
+
+		let pk_start = params.secret_key_len();
+		let hash_start = pk_start + params.public_key_len();
+
+		assert_eq!(bytes[pk_start..hash_start], pk[..]);
+		assert_eq!(bytes[hash_start..(hash_start + HASH_LEN)], hash(H_DOMAIN, &[&pk])[0..HASH_LEN]);
+	}
+}
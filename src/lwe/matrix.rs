@@ -0,0 +1,190 @@
+//
+// Heap-allocated matrices over the integers mod q, whose dimensions and modulus are only
+// known at runtime
+//
+
+use std::ops::{Add, Index, IndexMut, Mul};
+
+use crate::secret::Scrub;
+
+/// A dense matrix over the integers mod q, stored column by column in a single vector, the same
+/// layout `matrix_kit` used. Every entry is kept in [0, q), and anything written through
+/// `entries_mut` or by index must be too.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Matrix {
+	rows: usize,
+	cols: usize,
+	modulus: i64,
+	entries: Vec<i64>
+}
+
+impl Matrix {
+	/// A `rows` by `cols` matrix of zeroes
+	pub(crate) fn zero(rows: usize, cols: usize, modulus: i64) -> Matrix {
+		Matrix { rows, cols, modulus, entries: vec![0 ; rows * cols] }
+	}
+
+	/// A `rows` by `cols` matrix whose entries are filled in column by column by `f`, and
+	/// then reduced mod q
+	pub(crate) fn from_fn(rows: usize, cols: usize, modulus: i64, mut f: impl FnMut() -> i64) -> Matrix {
+		Matrix { rows, cols, modulus, entries: (0..(rows * cols)).map(|_| f().rem_euclid(modulus)).collect() }
+	}
+
+	/// A `rows` by `cols` matrix with the given entries, listed column by column
+	pub(crate) fn from_entries(rows: usize, cols: usize, modulus: i64, mut entries: Vec<i64>) -> Matrix {
+		assert_eq!(entries.len(), rows * cols, "Expected {:?} entries for a {:?}x{:?} matrix", rows * cols, rows, cols);
+
+		for x in entries.iter_mut() {
+			*x = x.rem_euclid(modulus);
+		}
+
+		Matrix { rows, cols, modulus, entries }
+	}
+
+	pub(crate) fn rows(&self) -> usize {
+		self.rows
+	}
+
+	pub(crate) fn cols(&self) -> usize {
+		self.cols
+	}
+
+	pub(crate) fn modulus(&self) -> i64 {
+		self.modulus
+	}
+
+	/// All entries, column by column
+	pub(crate) fn entries(&self) -> &[i64] {
+		&self.entries
+	}
+
+	/// All entries, column by column, which must be kept reduced mod q
+	pub(crate) fn entries_mut(&mut self) -> &mut [i64] {
+		&mut self.entries
+	}
+
+	pub(crate) fn column(&self, c: usize) -> &[i64] {
+		&self.entries[(c * self.rows)..((c + 1) * self.rows)]
+	}
+
+	/// The dot product of row `r` of this matrix with column `c` of `other`
+	pub(crate) fn row_col_dot(&self, r: usize, other: &Matrix, c: usize) -> i64 {
+		assert_eq!(self.cols, other.rows);
+		assert_eq!(self.modulus, other.modulus);
+
+		let mut sum = 0;
+		for (i, y) in other.column(c).iter().enumerate() {
+			sum = (sum + self[(r, i)] * y) % self.modulus;
+		}
+
+		sum
+	}
+
+	/// Places `right` next to this matrix, so the result is `[self | right]`
+	pub(crate) fn augment(&self, right: &Matrix) -> Matrix {
+		assert_eq!(self.rows, right.rows);
+		assert_eq!(self.modulus, right.modulus);
+
+		let mut entries = Vec::with_capacity(self.rows * (self.cols + right.cols));
+		entries.extend_from_slice(&self.entries);
+		entries.extend_from_slice(&right.entries);
+
+		Matrix { rows: self.rows, cols: self.cols + right.cols, modulus: self.modulus, entries }
+	}
+
+	/// The columns `start..end` of this matrix
+	pub(crate) fn columns(&self, start: usize, end: usize) -> Matrix {
+		Matrix {
+			rows: self.rows,
+			cols: end - start,
+			modulus: self.modulus,
+			entries: self.entries[(start * self.rows)..(end * self.rows)].to_vec()
+		}
+	}
+}
+
+impl Index<(usize, usize)> for Matrix {
+	type Output = i64;
+
+	fn index(&self, (r, c): (usize, usize)) -> &i64 {
+		&self.entries[c * self.rows + r]
+	}
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+	fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut i64 {
+		&mut self.entries[c * self.rows + r]
+	}
+}
+
+impl Add for &Matrix {
+	type Output = Matrix;
+
+	fn add(self, rhs: &Matrix) -> Matrix {
+		assert_eq!((self.rows, self.cols), (rhs.rows, rhs.cols));
+		assert_eq!(self.modulus, rhs.modulus);
+
+		Matrix {
+			rows: self.rows,
+			cols: self.cols,
+			modulus: self.modulus,
+			entries: self.entries.iter().zip(rhs.entries.iter()).map(|(x, y)| (x + y) % self.modulus).collect()
+		}
+	}
+}
+
+impl Mul for &Matrix {
+	type Output = Matrix;
+
+	fn mul(self, rhs: &Matrix) -> Matrix {
+		assert_eq!(self.cols, rhs.rows, "Can't multiply a {:?}x{:?} matrix by a {:?}x{:?} matrix", self.rows, self.cols, rhs.rows, rhs.cols);
+		assert_eq!(self.modulus, rhs.modulus);
+
+		let mut product = Matrix::zero(self.rows, rhs.cols, self.modulus);
+
+		for c in 0..rhs.cols {
+			for (i, y) in rhs.column(c).iter().enumerate() {
+				for r in 0..self.rows {
+					product[(r, c)] = (product[(r, c)] + self[(r, i)] * y) % self.modulus;
+				}
+			}
+		}
+
+		product
+	}
+}
+
+impl Scrub for Matrix {
+	fn scrub(&mut self) {
+		self.entries.scrub()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Matrix;
+
+	#[test]
+	fn test_mul() {
+		// [1 2 3]
+		// [4 5 6]
+		let a = Matrix::from_entries(2, 3, 7, vec![1, 4, 2, 5, 3, 6]);
+		let b = Matrix::from_entries(3, 1, 7, vec![1, 0, 2]);
+
+		// [1 + 6, 4 + 12] mod 7
+		assert_eq!(&a * &b, Matrix::from_entries(2, 1, 7, vec![0, 2]));
+		assert_eq!(a.row_col_dot(1, &b, 0), 2);
+		assert_eq!(&b + &b, Matrix::from_entries(3, 1, 7, vec![2, 0, 4]));
+	}
+
+	#[test]
+	fn test_augment() {
+		let a = Matrix::from_entries(2, 1, 7, vec![1, 2]);
+		let b = Matrix::from_entries(2, 2, 7, vec![3, 5, 4, -1]);
+		let ab = a.augment(&b);
+
+		assert_eq!(ab.entries(), [1, 2, 3, 5, 4, 6]);
+		assert_eq!(ab.columns(0, 1), a);
+		assert_eq!(ab.columns(1, 3), b);
+	}
+}
@@ -0,0 +1,714 @@
+//
+// The basic Learning with Errors over integer lattices
+//
+
+pub(crate) mod analysis;
+mod attack;
+mod compress;
+pub(crate) mod expand;
+mod homomorphic;
+pub mod hybrid;
+mod kem;
+mod matrix;
+mod packing;
+mod params;
+mod sampler;
+mod threshold;
+
+use expand::{expand_matrix, uniform_below, Sha512Xof};
+use matrix::Matrix;
+pub use analysis::NoiseDistribution;
+pub use attack::EmbeddingAttack;
+pub use compress::CiphertextCompression;
+pub use expand::{Seed, SEED_LEN};
+pub use homomorphic::{HomomorphicError, NoiseBudgetExceeded, TrackedCiphertext, NOISE_BUDGET_THRESHOLD};
+pub use kem::{decaps, encaps, keygen, keygen_from_seed, KemSecretKey, SharedSecret, SHARED_SECRET_LEN};
+pub use packing::PackingError;
+pub use params::LweParams;
+pub use sampler::ErrorDistribution;
+pub use threshold::{KeyShare, PartialDecryption, SMUDGING_SECURITY_BITS};
+
+use crate::secret::{Scrub, Secret};
+use crate::utility::BigMappable;
+
+use std::fmt::Display;
+
+use rand::rngs::StdRng;
+use rand::CryptoRng;
+#[cfg(test)]
+use rand::Rng;
+use rand::RngCore;
+use rand::SeedableRng;
+
+macro_rules! get_bit {
+	($x: expr, $i: expr) => {
+		($x >> $i) & 1
+	};
+}
+
+
+// This is little endian!!!
+macro_rules! byte_to_bits {
+	($b: expr) => {
+		// TODO: Learn how Rust macros work to make this wayyyy more elegant
+		[
+			get_bit!($b, 0), get_bit!($b, 1),
+			get_bit!($b, 2), get_bit!($b, 3),
+			get_bit!($b, 4), get_bit!($b, 5),
+			get_bit!($b, 6), get_bit!($b, 7),
+		]
+	};
+}
+
+// little endian!
+macro_rules! bits_to_byte {
+	($bits: expr) => {
+		($bits[0] << 0) + ($bits[1] << 1) + 
+		($bits[2] << 2) + ($bits[3] << 3) + 
+		($bits[4] << 4) + ($bits[5] << 5) + 
+		($bits[6] << 6) + ($bits[7] << 7)
+	};
+}
+
+/// Generates a random error for a matrix in the intergers mod q, drawn from the error
+/// distribution of `params`
+fn error_gen(error: &mut [i64], params: &LweParams, rng: &mut dyn RngCore) {
+	params.error.sample_into(error, rng);
+
+	for e in error.iter_mut() {
+		*e = e.rem_euclid(params.q);
+	}
+}
+
+/// Generates a key pair for encrypting K bits at a time, returning the N x K secret S, 
+/// the M x (N + K) public key [A | AS + E], and the seed A was expanded from if it was
+fn gen_mat(params: &LweParams, k: usize, rng: &mut dyn RngCore) -> (Matrix, Matrix, Option<Seed>) {
+	let LweParams { m, n, q, .. } = *params;
+
+	// generate the secret, S
+	let secret = Matrix::from_fn(n, k, q, || uniform_below(q, rng));
+
+	// generate the public key A, or the seed to expand it from
+	let (a, seed) = if params.seeded_matrix {
+		let mut seed = [0 ; SEED_LEN];
+		rng.fill_bytes(&mut seed);
+
+		(expand_matrix(&seed, m, n, q), Some(seed))
+	} else {
+		(Matrix::from_fn(m, n, q, || uniform_below(q, rng)), None)
+	};
+	
+	// Compute AS + E
+	let mut e = Matrix::zero(m, k, q);
+	error_gen(e.entries_mut(), params, rng);
+
+	let b = &(&a * &secret) + &e;
+
+	// A goes on the left side of the public key, and AS + E on the right-hand side
+	(secret, a.augment(&b), seed)
+
+}
+
+/// Encrypts the K values of `m`, each `bits` bits long, under an M x (N + K) public key, giving
+/// a K x (N + 1) ciphertext
+fn enc_mat(pubkey: &Matrix, m: &[i64], bits: usize, rng: &mut dyn RngCore) -> Matrix {
+	let q = pubkey.modulus();
+	let k = m.len();
+	let n = pubkey.cols() - k;
+
+	// generate selection matrix
+	let t = Matrix::from_fn(k, pubkey.rows(), q, || uniform_below(2, rng));
+	
+	// we need to generate the rows of new summed equations
+	let a = pubkey.columns(0, n);
+	let b = pubkey.columns(n, n + k);
+
+	// K equations, each for a bit, each with N coefficients.
+	let summed_eqs = &t * &a;
+
+	// great! Now we add offsets to the constant terms as needed.
+	let mut constants = Matrix::zero(k, 1, q);
+
+	for i in 0..k {
+		constants[(i, 0)] = (t.row_col_dot(i, &b, i) + encode_value(m[i], q, bits)) % q;
+	}
+
+	summed_eqs.augment(&constants)
+}
+
+fn dec_mat(seckey: &Matrix, cipher: &Matrix, bits: usize) -> Vec<i64> {
+	let q = seckey.modulus();
+	let n = seckey.rows();
+
+	// we ONLY need to compute the diagonal of the product matrix.
+	(0..cipher.rows()).map(|i| {
+		let mut dot = 0;
+		for j in 0..n {
+			dot = (dot + cipher[(i, j)] * seckey[(j, i)]) % q;
+		}
+
+		decode_value((cipher[(i, n)] - dot).rem_euclid(q), q, bits)
+	}).collect()
+}
+
+/// Scales a value of `bits` bits up to floor(v q / 2^bits), so that a 1 bit is floor(q / 2)
+fn encode_value(v: i64, q: i64, bits: usize) -> i64 {
+	(v * q) >> bits
+}
+
+/// Rounds what's left of a ciphertext coefficient once the secret is taken away to the nearest
+/// multiple of q / 2^bits, giving back the value that was encoded
+fn decode_value(d: i64, q: i64, bits: usize) -> i64 {
+	(((d << (bits + 1)) + q) / (2 * q)) % (1 << bits)
+}
+
+/// Groups bits, least significant first, into values of `bits` bits each
+fn bits_to_values(plain_bits: &[i64], bits: usize) -> Vec<i64> {
+	plain_bits.chunks_exact(bits)
+		.map(|chunk| chunk.iter().enumerate().fold(0, |value, (i, bit)| value | (bit << i)))
+		.collect()
+}
+
+/// Splits values of `bits` bits each back up into bits, least significant first
+fn values_to_bits(values: &[i64], bits: usize) -> Vec<i64> {
+	values.iter().flat_map(|value| (0..bits).map(move |i| (value >> i) & 1)).collect()
+}
+
+#[test]
+fn test_lwe() {
+	// These are the same tests as before, but the one-bit versions
+	for _ in 1..=256 {
+		let (seckey, pubkey, _) = gen_mat(&LweParams::DEFAULT, BIT_LENGTH, &mut StdRng::from_entropy());
+
+		// the plaintext!
+		let mut b = [0 ; 256];
+
+		for bit in b.iter_mut() {
+			*bit = rand::thread_rng().gen_range(0..2);
+		}
+	
+		let ciphertext = enc_mat(&pubkey, &b, 1, &mut StdRng::from_entropy());
+		let decrypted = dec_mat(&seckey, &ciphertext, 1);
+
+		assert_eq!(b.to_vec(), decrypted);
+	}
+}
+
+// -- Now we get to actually exporting these algorithms into a usable form
+
+// [q ; M * (N + K)] pubkey
+// [q ; N * K] privkey
+
+/*
+ * Notes to myself:
+ * 
+ * We will represent the keys as byte arrays, 8 bytes for each i64, which will be reduced 
+ * mod q later. Each i64 is little-endian, no matter what machine we're on, so the coefficients
+ * [1, 2, 3] would be 
+ * [0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+ * 	0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+ * 	0x3, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0]
+ * 
+ * So, the number of bytes here is 8 * M * (N + K)!
+ */
+
+const BIT_LENGTH: usize = 256;
+
+/// The length, in bytes, of the public key with the default parameters
+pub const PUBKEY_LEN: usize = LweParams::DEFAULT.public_key_len();
+
+/// The length, in bytes, of the secret key with the default parameters
+pub const SECKEY_LEN: usize = LweParams::DEFAULT.secret_key_len();
+
+/// The length, in bytes, of the plaintext
+pub const PLAINTEXT_LEN: usize = BIT_LENGTH / 8;
+
+/// The length, in bytes, of the ciphertext with the default parameters
+pub const CIPHERTEXT_LEN: usize = LweParams::DEFAULT.ciphertext_len();
+
+/// The public key type for standard learning with errors, `public_key_len()` bytes long
+pub type PublicKey = Vec<u8>;
+
+/// The private key type for learning with errors, `secret_key_len()` bytes long
+pub type SecretKey = Secret<Vec<u8>>;
+
+/// The plaintext type, just 256 bits!
+pub type Plaintext = [u8 ; PLAINTEXT_LEN];
+
+/// The ciphertext type, `ciphertext_len()` bytes long
+pub type Ciphertext = Vec<u8>;
+
+/// Why a key or ciphertext was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LweError {
+	/// The input is not as long as these parameters call for
+	WrongLength { expected: usize, actual: usize },
+
+	/// The input is shorter than the least these parameters call for
+	TooShort { minimum: usize, actual: usize },
+
+	/// The smudging noise allowed for this many participants is too small to hide the error
+	/// in a ciphertext, so a partial decryption would leak its share
+	WeakSmudging { bound: i64, needed: i64 },
+
+	/// Threshold decryption needs a prime modulus
+	NotPrime { q: i64 },
+
+	/// A key can only be split into k shares with 1 <= t <= k < q
+	InvalidThreshold { t: usize, k: usize },
+
+	/// Fewer parties are taking part than it takes to decrypt
+	TooFewParticipants { threshold: usize, actual: usize },
+
+	/// A participant's index is 0, or not below q, so it can't be the index of a share
+	InvalidParticipant { index: u64 },
+
+	/// The same share is taking part more than once
+	DuplicateParticipant { index: u64 },
+
+	/// A share was used, or a partial decryption made, by a party that isn't taking part
+	NotParticipating { index: u64 },
+
+	/// The partial decryptions were made for different sets of participants
+	MismatchedParticipants,
+
+	/// One of the participants has no partial decryption
+	MissingPartial { index: u64 },
+
+	/// A partial decryption has the wrong number of values for these parameters, or one that isn't in [0, q)
+	InvalidPartial { index: u64 },
+
+	/// An encoding has a field that no valid encoding could have
+	Malformed,
+
+	/// A ciphertext failed authentication, or didn't decrypt to a message
+	Invalid,
+
+	/// Ciphertexts can only be compressed to between 1 and `max` bits per coefficient
+	InvalidCompression { bits: usize, max: usize }
+}
+
+impl Display for LweError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LweError::WrongLength { expected, actual } => write!(f, "Expected {:?} bytes, got {:?}", expected, actual),
+			LweError::TooShort { minimum, actual } => write!(f, "Expected at least {:?} bytes, got {:?}", minimum, actual),
+			LweError::WeakSmudging { bound, needed } => write!(f, "Smudging noise up to {:?} can't hide the ciphertext error, which needs at least {:?}", bound, needed),
+			LweError::NotPrime { q } => write!(f, "Threshold decryption needs a prime modulus, not {:?}", q),
+			LweError::InvalidThreshold { t, k } => write!(f, "Need 1 <= t <= k < q, but t = {:?} and k = {:?}", t, k),
+			LweError::TooFewParticipants { threshold, actual } => write!(f, "Need at least {:?} participants, but only have {:?}", threshold, actual),
+			LweError::InvalidParticipant { index } => write!(f, "{:?} can't be the index of a share", index),
+			LweError::DuplicateParticipant { index } => write!(f, "Share {:?} is taking part more than once", index),
+			LweError::NotParticipating { index } => write!(f, "Share {:?} isn't one of the participants", index),
+			LweError::MismatchedParticipants => write!(f, "Partial decryptions were made for different participants"),
+			LweError::MissingPartial { index } => write!(f, "Missing a partial decryption from share {:?}", index),
+			LweError::InvalidPartial { index } => write!(f, "The partial decryption from share {:?} doesn't fit these parameters", index),
+			LweError::Malformed => write!(f, "Malformed encoding"),
+			LweError::Invalid => write!(f, "Invalid ciphertext"),
+			LweError::InvalidCompression { bits, max } => write!(f, "Can't compress to {:?} bits, it has to be from 1 to {:?}", bits, max)
+		}
+	}
+}
+
+impl std::error::Error for LweError { }
+
+/// Checks that a key or ciphertext is `expected` bytes long, since these often come from
+/// someone else, who shouldn't be able to crash us by sending the wrong amount
+fn check_len(bytes: &[u8], expected: usize) -> Result<(), LweError> {
+	if bytes.len() == expected {
+		Ok(())
+	} else {
+		Err(LweError::WrongLength { expected, actual: bytes.len() })
+	}
+}
+
+/// Reads little-endian i64 coefficients from `bytes`
+fn decode_coefficients(bytes: &[u8], q: i64) -> Vec<i64> {
+	bytes.chunks_exact(8).map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap()).rem_euclid(q)).collect()
+}
+
+/// Writes coefficients out as little-endian i64's
+fn encode_coefficients(coefficients: &[i64], into: &mut [u8]) {
+	for (coefficient, chunk) in coefficients.iter().zip(into.chunks_exact_mut(8)) {
+		chunk.copy_from_slice(&coefficient.to_le_bytes());
+	}
+}
+
+fn pk_to_matrix_rep(params: &LweParams, pubkey: &[u8]) -> Result<Matrix, LweError> {
+	check_len(pubkey, params.public_key_len())?;
+
+	if params.seeded_matrix {
+		// A is regenerated from the seed, and only B follows it
+		let seed: Seed = pubkey[0..SEED_LEN].try_into().unwrap();
+		let a = expand_matrix(&seed, params.m, params.n, params.q);
+		let b = Matrix::from_entries(params.m, params.plaintext_coefficients(), params.q, decode_coefficients(&pubkey[SEED_LEN..], params.q));
+
+		Ok(a.augment(&b))
+	} else {
+		Ok(Matrix::from_entries(params.m, params.n + params.plaintext_coefficients(), params.q, decode_coefficients(pubkey, params.q)))
+	}
+}
+
+fn matrix_rep_to_pk(params: &LweParams, matrix: &Matrix, seed: Option<Seed>) -> PublicKey {
+	match seed {
+		Some(seed) => {
+			let b = matrix.columns(params.n, matrix.cols());
+
+			let mut pubkey = vec![0 ; SEED_LEN + 8 * b.entries().len()];
+			pubkey[0..SEED_LEN].copy_from_slice(&seed);
+			encode_coefficients(b.entries(), &mut pubkey[SEED_LEN..]);
+			pubkey
+		},
+		None => {
+			let mut pubkey = vec![0 ; 8 * matrix.entries().len()];
+			encode_coefficients(matrix.entries(), &mut pubkey);
+			pubkey
+		}
+	}
+}
+
+fn sk_to_matrix_rep(params: &LweParams, secretkey: &SecretKey) -> Result<Matrix, LweError> {
+	check_len(secretkey.expose(), params.secret_key_len())?;
+
+	// the decoded coefficients are moved into the matrix, so that no other copies of the key are left lying around
+	Ok(Matrix::from_entries(params.n, params.plaintext_coefficients(), params.q, decode_coefficients(secretkey.expose(), params.q)))
+}
+
+fn matrix_rep_to_sk(matrix: &Matrix) -> SecretKey {
+	let mut secretkey = SecretKey::new(vec![0 ; 8 * matrix.entries().len()]);
+	encode_coefficients(matrix.entries(), secretkey.expose_mut());
+	secretkey
+}
+
+fn pt_to_matrix_rep(plaintext: Plaintext) -> [i64 ; BIT_LENGTH] {
+
+	let slice: [[i64 ; 8] ; BIT_LENGTH / 8] = plaintext.big_map(|byte| 
+		byte_to_bits!(byte).map(|b| b as i64)
+	);
+
+	let mut as_array = [0 ; BIT_LENGTH];
+
+	for i in 0..BIT_LENGTH {
+		as_array[i] = slice[i / 8][i % 8];
+	}
+
+	as_array
+}
+
+fn matrix_rep_to_pt(matrix: &[i64]) -> Plaintext {
+
+	let mut pt = [0 ; BIT_LENGTH / 8];
+
+	for i in 0..(BIT_LENGTH / 8) {
+		pt[i] = bits_to_byte!(matrix[(i * 8)..(i * 8 + 8)]) as u8
+	}
+
+	pt
+
+}
+
+fn ct_to_matrix_rep(params: &LweParams, ciphertext: &[u8]) -> Result<Matrix, LweError> {
+	check_len(ciphertext, params.ciphertext_len())?;
+
+	Ok(Matrix::from_entries(params.plaintext_coefficients(), params.n + 1, params.q, decode_coefficients(ciphertext, params.q)))
+}
+
+fn matrix_rep_to_ct(matrix: &Matrix) -> Ciphertext {
+	let mut ciphertext = vec![0 ; 8 * matrix.entries().len()];
+	encode_coefficients(matrix.entries(), &mut ciphertext);
+	ciphertext
+}
+
+#[test]
+fn test_conversions() {
+	println!("Testing conversions");
+	println!("Plaintext bits: {:?}", BIT_LENGTH);
+	println!("{}", LweParams::DEFAULT);
+
+	for i in 1..=256 {
+
+		println!("Test {:?}", i);
+
+		// alternating between storing A and expanding it from a seed
+		let params = if i % 2 == 0 { LweParams::DEFAULT } else { LweParams::DEFAULT.with_seeded_matrix() };
+		
+		let (sk_mat, pk_mat, seed) = gen_mat(&params, BIT_LENGTH, &mut StdRng::from_entropy());
+
+		let mut pt_mat = [0 ; BIT_LENGTH];
+
+		for bit in pt_mat.iter_mut() {
+			*bit = rand::thread_rng().gen_range(0..2);
+		}
+
+		// we are going to be generating a random ciphertext, instead of trying to encrypt soemthing!
+		// we aren't testing encryption, just conversion.
+		let ct_mat = Matrix::from_fn(BIT_LENGTH, params.n + 1, params.q, || rand::thread_rng().gen_range(0..params.q));
+
+		assert_eq!(sk_mat, sk_to_matrix_rep(&params, &matrix_rep_to_sk(&sk_mat)).unwrap());
+		assert_eq!(pk_mat, pk_to_matrix_rep(&params, &matrix_rep_to_pk(&params, &pk_mat, seed)).unwrap());
+		assert_eq!(pt_mat, pt_to_matrix_rep(matrix_rep_to_pt(&pt_mat)));
+		assert_eq!(ct_mat, ct_to_matrix_rep(&params, &matrix_rep_to_ct(&ct_mat)).unwrap());
+
+	}
+}
+
+// MARK: Interface
+
+/// The length, in bytes, of a seed that keys can be regenerated from
+pub const KEY_SEED_LEN: usize = 32;
+
+/// The domain for version 2 of deriving keys from a seed. Any change to the derivation gets a new
+/// version with its own domain. Version 1 went through `Rng::gen_range`, so its keys could change
+/// with the `rand` crate, and it has been dropped rather than kept.
+const KEYGEN_DOMAIN_V2: &[u8] = b"rusty_crypto lwe keygen v2";
+
+impl LweParams {
+
+	/// Regenerates the same key pair every time from a 32-byte seed, which is all that needs to
+	/// be backed up (or Shamir-shared with `secsharing::sharing::distribute`).
+	///
+	/// Version 2 of the derivation reads everything from SHA-512 in counter mode, block i being
+	/// SHA-512("rusty_crypto lwe keygen v2" || seed || i) with i a big-endian u64, in this order:
+	///
+	/// - the N x K secret S, column by column, each entry drawn with `uniform_below(q)`
+	/// - the 32-byte seed A is expanded from, or else the M x N matrix A, in the same way as S
+	/// - the M x K errors, column by column, as `ErrorDistribution::sample_into` draws them
+	///
+	/// where `uniform_below` reads four bytes at a time as a little-endian u32. The keys also
+	/// depend on the parameters, so the seed has to be kept along with them.
+	pub fn gen_from_seed(&self, seed: [u8 ; KEY_SEED_LEN]) -> (SecretKey, PublicKey) {
+		let mut seed = seed;
+		let keys = self.gen_with_rng(&mut Sha512Xof::new(KEYGEN_DOMAIN_V2, &seed));
+		seed.scrub();
+
+		keys
+	}
+
+	/// Generates a key pair for this parameter set
+	pub fn gen(&self) -> (SecretKey, PublicKey) {
+		self.gen_with_rng(&mut StdRng::from_entropy())
+	}
+
+	/// Generates a key pair, drawing the secret, the public matrix and the errors from `rng`
+	pub fn gen_with_rng(&self, rng: &mut (impl RngCore + CryptoRng)) -> (SecretKey, PublicKey) {
+		let (mut sk_mat, pk_mat, seed) = gen_mat(self, self.plaintext_coefficients(), rng);
+		let sk = matrix_rep_to_sk(&sk_mat);
+		sk_mat.scrub();
+
+		(sk, matrix_rep_to_pk(self, &pk_mat, seed))
+	}
+
+	/// Encrypts a plaintext under a public key for this parameter set, or fails if the public key
+	/// is the wrong length for it
+	pub fn enc(&self, pk: PublicKey, pt: Plaintext) -> Result<Ciphertext, LweError> {
+		self.enc_with_rng(pk, pt, &mut StdRng::from_entropy())
+	}
+
+	/// Encrypts a plaintext, drawing the row selections from `rng`
+	pub fn enc_with_rng(&self, pk: PublicKey, pt: Plaintext, rng: &mut (impl RngCore + CryptoRng)) -> Result<Ciphertext, LweError> {
+		let pk_mat = pk_to_matrix_rep(self, &pk)?;
+		let m = bits_to_values(&pt_to_matrix_rep(pt), self.bits_per_coefficient);
+
+		Ok(matrix_rep_to_ct(&enc_mat(&pk_mat, &m, self.bits_per_coefficient, rng)))
+	}
+
+	/// Decrypts a ciphertext with a secret key for this parameter set, or fails if either of them
+	/// is the wrong length for it
+	pub fn dec(&self, sk: &SecretKey, ct: Ciphertext) -> Result<Plaintext, LweError> {
+		let ct_mat = ct_to_matrix_rep(self, &ct)?;
+		let mut sk_mat = sk_to_matrix_rep(self, sk)?;
+
+		let m = dec_mat(&sk_mat, &ct_mat, self.bits_per_coefficient);
+		let pt = matrix_rep_to_pt(&values_to_bits(&m, self.bits_per_coefficient));
+		sk_mat.scrub();
+
+		Ok(pt)
+	}
+}
+
+/// Generates a key pair with the default parameters
+pub fn gen() -> (SecretKey, PublicKey) {
+	LweParams::DEFAULT.gen()
+}
+
+/// Regenerates the same key pair every time from a 32-byte seed, with the default parameters
+pub fn gen_from_seed(seed: [u8 ; KEY_SEED_LEN]) -> (SecretKey, PublicKey) {
+	LweParams::DEFAULT.gen_from_seed(seed)
+}
+
+/// Generates a key pair with the default parameters, drawing the secret, the public matrix and 
+/// the errors from `rng`
+pub fn gen_with_rng(rng: &mut (impl RngCore + CryptoRng)) -> (SecretKey, PublicKey) {
+	LweParams::DEFAULT.gen_with_rng(rng)
+}
+
+/// Encrypts a plaintext with the default parameters
+pub fn enc(pk: PublicKey, pt: Plaintext) -> Result<Ciphertext, LweError> {
+	LweParams::DEFAULT.enc(pk, pt)
+}
+
+/// Encrypts a plaintext with the default parameters, drawing the row selections from `rng`
+pub fn enc_with_rng(pk: PublicKey, pt: Plaintext, rng: &mut (impl RngCore + CryptoRng)) -> Result<Ciphertext, LweError> {
+	LweParams::DEFAULT.enc_with_rng(pk, pt, rng)
+}
+
+/// Decrypts a ciphertext with the default parameters
+pub fn dec(sk: &SecretKey, ct: Ciphertext) -> Result<Plaintext, LweError> {
+	LweParams::DEFAULT.dec(sk, ct)
+}
+
+#[test]
+fn test_correctness() {
+	println!("Testing correctness");
+	println!("Plaintext bits: {:?}", BIT_LENGTH);
+	println!("{}", LweParams::DEFAULT);
+
+	for i in 1..=256 {
+
+		println!("Test {:?}", i);
+
+		let (secret_key, public_key) = gen();
+
+		let plaintext: Plaintext = rand::thread_rng().gen::<[u8; 32]>();
+		
+		let encrypted = enc(public_key, plaintext).unwrap();
+		let recovered = dec(&secret_key, encrypted).unwrap();
+
+		assert_eq!(plaintext, recovered);
+
+	}
+}
+
+#[test]
+fn test_small_stack() {
+	// keys, ciphertexts and matrices all live on the heap, so this works on a far smaller stack
+	// than the 2 MiB threads get by default
+	let handle = std::thread::Builder::new().stack_size(256 * 1024).spawn(|| {
+		let (secret_key, public_key) = gen();
+
+		let plaintext: Plaintext = rand::thread_rng().gen();
+		assert_eq!(dec(&secret_key, enc(public_key, plaintext).unwrap()).unwrap(), plaintext);
+	}).unwrap();
+
+	handle.join().unwrap();
+}
+
+#[test]
+fn test_presets_round_trip() {
+	for params in [LweParams::TOY, LweParams::DEFAULT, LweParams::new(60, 20, 12289, 40), LweParams::TOY.with_seeded_matrix(),
+		LweParams::new(64, 16, 3329, 0).with_error(ErrorDistribution::CenteredBinomial { eta: 3 }),
+		LweParams::new(64, 16, 12289, 0).with_error(ErrorDistribution::DiscreteGaussian { sigma: 3.2 })] {
+		let (secret_key, public_key) = params.gen();
+
+		assert_eq!(public_key.len(), params.public_key_len());
+		assert_eq!(secret_key.expose().len(), params.secret_key_len());
+
+		let plaintext: Plaintext = rand::thread_rng().gen();
+		let encrypted = params.enc(public_key, plaintext).unwrap();
+
+		assert_eq!(encrypted.len(), params.ciphertext_len());
+		assert_eq!(params.dec(&secret_key, encrypted).unwrap(), plaintext);
+	}
+}
+
+#[test]
+fn test_bits_per_coefficient_round_trip() {
+	// with as many bits as still leave room for a single error
+	for (params, max_bits) in [(LweParams::TOY, 4), (LweParams::DEFAULT, 4), (LweParams::new(60, 20, 12289, 40), 4), (LweParams::new(100, 30, 1 << 20, 8).with_seeded_matrix(), 8)] {
+		for bits in [1, 2, 4, 8].into_iter().filter(|bits| *bits <= max_bits) {
+			let multi = params.with_bits_per_coefficient(bits);
+
+			// only where decryption is all but certain to work
+			if multi.failure_bound() > 1e-9 {
+				continue;
+			}
+
+			let (secret_key, public_key) = multi.gen();
+			assert_eq!(public_key.len(), multi.public_key_len());
+
+			for _ in 0..4 {
+				let plaintext: Plaintext = rand::thread_rng().gen();
+				let encrypted = multi.enc(public_key.clone(), plaintext).unwrap();
+
+				assert_eq!(encrypted.len(), multi.ciphertext_len());
+				assert_eq!(multi.dec(&secret_key, encrypted).unwrap(), plaintext);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_value_encoding() {
+	// one bit is the same as it has always been, floor(q/2)
+	assert_eq!(encode_value(1, 3329, 1), 1664);
+	assert_eq!(decode_value(832, 3329, 1), 0);
+	assert_eq!(decode_value(833, 3329, 1), 1);
+	assert_eq!(decode_value(2495, 3329, 1), 1);
+	assert_eq!(decode_value(3328, 3329, 1), 0);
+
+	for bits in [1, 2, 4, 8] {
+		for v in 0..(1 << bits) {
+			assert_eq!(decode_value(encode_value(v, 12289, bits), 12289, bits), v);
+		}
+	}
+
+	let plain_bits: Vec<i64> = (0..16).map(|i| (i % 3 == 0) as i64).collect();
+	assert_eq!(bits_to_values(&plain_bits, 4), [0b1001, 0b0100, 0b0010, 0b1001]);
+	assert_eq!(values_to_bits(&bits_to_values(&plain_bits, 4), 4), plain_bits);
+}
+
+#[test]
+fn test_seeded_reproducibility() {
+	let params = LweParams::new(20, 8, 89, 1);
+	let (first_sk, first_pk, _) = gen_mat(&params, 8, &mut StdRng::seed_from_u64(0x1e));
+	let (second_sk, second_pk, _) = gen_mat(&params, 8, &mut StdRng::seed_from_u64(0x1e));
+
+	assert_eq!(first_sk, second_sk);
+	assert_eq!(first_pk, second_pk);
+
+	let b: [i64 ; 8] = std::array::from_fn(|i| (i % 3 == 0) as i64);
+
+	let first_ct = enc_mat(&first_pk, &b, 1, &mut StdRng::seed_from_u64(0x1f));
+	let second_ct = enc_mat(&second_pk, &b, 1, &mut StdRng::seed_from_u64(0x1f));
+
+	assert_eq!(first_ct, second_ct);
+	assert_eq!(dec_mat(&first_sk, &first_ct, 1), b);
+}
+
+#[test]
+fn test_gen_from_seed() {
+	let (first_sk, first_pk) = gen_from_seed([0x46 ; KEY_SEED_LEN]);
+	let (second_sk, second_pk) = gen_from_seed([0x46 ; KEY_SEED_LEN]);
+
+	assert_eq!(first_sk, second_sk);
+	assert_eq!(first_pk, second_pk);
+	assert_ne!(gen_from_seed([0x47 ; KEY_SEED_LEN]).1, first_pk);
+
+	let plaintext: Plaintext = rand::thread_rng().gen();
+	assert_eq!(dec(&first_sk, enc(first_pk.clone(), plaintext).unwrap()).unwrap(), plaintext);
+
+	// version 2 of the derivation has to give these exact keys in every release
+	let fingerprint = |bytes: &[u8]| crate::sha512::hash(bytes.to_vec())[0..8].iter().map(|b| format!("{:02x}", b)).collect::<String>();
+	assert_eq!(fingerprint(first_sk.expose()), "ca13b7c49f561c53");
+	assert_eq!(fingerprint(&first_pk), "4943f0b650601110");
+
+	let (seeded_sk, seeded_pk) = LweParams::BITS_80.gen_from_seed([0x46 ; KEY_SEED_LEN]);
+	assert_eq!(fingerprint(seeded_sk.expose()), "a5d9938dc2436b31");
+	assert_eq!(fingerprint(&seeded_pk), "49647468b963f864");
+}
+
+#[test]
+fn test_byte_layout() {
+	// keys and ciphertexts are little-endian i64's, on every machine
+	let coefficients = [1, 0x102, 3328];
+	let mut bytes = [0xff ; 24];
+	encode_coefficients(&coefficients, &mut bytes);
+
+	assert_eq!(bytes, [
+		0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x02, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x0d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+	]);
+
+	assert_eq!(decode_coefficients(&bytes, 3329), coefficients);
+}
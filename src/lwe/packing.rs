@@ -0,0 +1,273 @@
+//
+// Compact encodings of LWE keys and ciphertexts, where every coefficient mod q takes up
+// only ceil(log2 q) bits instead of a whole i64
+//
+
+use std::fmt::Display;
+
+use crate::secret::{Scrub, Secret};
+
+use super::{decode_coefficients, encode_coefficients, Ciphertext, LweParams, PublicKey, SecretKey, SEED_LEN};
+
+/// Why a packed key or ciphertext was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PackingError {
+	/// The encoding is not as long as these parameters call for
+	WrongLength { expected: usize, actual: usize },
+
+	/// The coefficient at `index` is not in [0, q)
+	OutOfRange { index: usize, value: i64 },
+
+	/// The bits filling up the last byte are not all zero
+	NonzeroPadding,
+
+	/// Ciphertexts can only be compressed to between 1 and `max` bits per coefficient
+	InvalidCompression { bits: usize, max: usize }
+}
+
+impl Display for PackingError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			PackingError::WrongLength { expected, actual } => write!(f, "Expected {:?} bytes, got {:?}", expected, actual),
+			PackingError::OutOfRange { index, value } => write!(f, "Coefficient {:?} is out of range: {:?}", index, value),
+			PackingError::NonzeroPadding => write!(f, "Padding bits are not zero"),
+			PackingError::InvalidCompression { bits, max } => write!(f, "Can't compress to {:?} bits, it has to be from 1 to {:?}", bits, max)
+		}
+	}
+}
+
+impl std::error::Error for PackingError { }
+
+/// The number of bytes `count` coefficients of `bits` bits each take up
+pub(crate) const fn packed_len(count: usize, bits: usize) -> usize {
+	(count * bits).div_ceil(8)
+}
+
+/// Packs coefficients in [0, 2^bits) one after the other, least significant bit first, so
+/// coefficient i is bits i * bits through (i + 1) * bits - 1 of the little-endian bit string.
+pub(crate) fn pack_coefficients(coefficients: &[i64], bits: usize) -> Vec<u8> {
+	let mut packed = vec![0u8 ; packed_len(coefficients.len(), bits)];
+
+	let mut buffer: u64 = 0;
+	let mut buffered = 0;
+	let mut out = 0;
+
+	for c in coefficients {
+		buffer |= (*c as u64) << buffered;
+		buffered += bits;
+
+		while buffered >= 8 {
+			packed[out] = buffer as u8;
+			out += 1;
+			buffer >>= 8;
+			buffered -= 8;
+		}
+	}
+
+	if buffered > 0 {
+		packed[out] = buffer as u8;
+	}
+
+	buffer.scrub();
+
+	packed
+}
+
+/// Reads `count` coefficients packed by `pack_coefficients`, rejecting anything that isn't
+/// exactly what packing coefficients in [0, q) would have produced
+pub(crate) fn unpack_coefficients(packed: &[u8], count: usize, bits: usize, q: i64) -> Result<Vec<i64>, PackingError> {
+	let expected = packed_len(count, bits);
+	if packed.len() != expected {
+		return Err(PackingError::WrongLength { expected, actual: packed.len() });
+	}
+
+	let mask = (1u64 << bits) - 1;
+	let mut coefficients = Vec::with_capacity(count);
+
+	let mut buffer: u64 = 0;
+	let mut buffered = 0;
+	let mut bytes = packed.iter();
+
+	while coefficients.len() < count {
+		while buffered < bits {
+			buffer |= (*bytes.next().unwrap() as u64) << buffered;
+			buffered += 8;
+		}
+
+		let value = (buffer & mask) as i64;
+		buffer >>= bits;
+		buffered -= bits;
+
+		if value >= q {
+			coefficients.scrub();
+			return Err(PackingError::OutOfRange { index: coefficients.len(), value });
+		}
+
+		coefficients.push(value);
+	}
+
+	let padding = buffer;
+	buffer.scrub();
+
+	if padding != 0 {
+		coefficients.scrub();
+		return Err(PackingError::NonzeroPadding);
+	}
+
+	Ok(coefficients)
+}
+
+impl LweParams {
+
+	/// The number of bits each coefficient takes up in the packed encodings, ceil(log2 q)
+	pub const fn coefficient_bits(&self) -> usize {
+		(64 - (self.q - 1).leading_zeros()) as usize
+	}
+
+	/// The number of bytes at the start of a public key that are a seed rather than coefficients
+	const fn public_key_seed_len(&self) -> usize {
+		if self.seeded_matrix { SEED_LEN } else { 0 }
+	}
+
+	/// The length, in bytes, of a packed public key, including the seed if there is one
+	pub const fn packed_public_key_len(&self) -> usize {
+		let seed_len = self.public_key_seed_len();
+		seed_len + packed_len((self.public_key_len() - seed_len) / 8, self.coefficient_bits())
+	}
+
+	/// The length, in bytes, of a packed secret key
+	pub const fn packed_secret_key_len(&self) -> usize {
+		packed_len(self.secret_key_len() / 8, self.coefficient_bits())
+	}
+
+	/// The length, in bytes, of a packed ciphertext
+	pub const fn packed_ciphertext_len(&self) -> usize {
+		packed_len(self.ciphertext_len() / 8, self.coefficient_bits())
+	}
+
+	/// Packs a public key so that each coefficient takes up `coefficient_bits()` bits. A seed
+	/// is copied over as it is.
+	pub fn pack_public_key(&self, pk: &PublicKey) -> Vec<u8> {
+		assert_eq!(pk.len(), self.public_key_len(), "Public key has the wrong length for these parameters");
+
+		let seed_len = self.public_key_seed_len();
+		let mut packed = pk[0..seed_len].to_vec();
+		packed.extend(pack_coefficients(&decode_coefficients(&pk[seed_len..], self.q), self.coefficient_bits()));
+
+		packed
+	}
+
+	/// Reads a packed public key back, checking that every coefficient is in [0, q)
+	pub fn unpack_public_key(&self, packed: &[u8]) -> Result<PublicKey, PackingError> {
+		let expected = self.packed_public_key_len();
+		if packed.len() != expected {
+			return Err(PackingError::WrongLength { expected, actual: packed.len() });
+		}
+
+		let seed_len = self.public_key_seed_len();
+		let coefficients = unpack_coefficients(&packed[seed_len..], (self.public_key_len() - seed_len) / 8, self.coefficient_bits(), self.q)?;
+
+		let mut pk = vec![0 ; self.public_key_len()];
+		pk[0..seed_len].copy_from_slice(&packed[0..seed_len]);
+		encode_coefficients(&coefficients, &mut pk[seed_len..]);
+
+		Ok(pk)
+	}
+
+	/// Packs a secret key so that each coefficient takes up `coefficient_bits()` bits
+	pub fn pack_secret_key(&self, sk: &SecretKey) -> Secret<Vec<u8>> {
+		assert_eq!(sk.expose().len(), self.secret_key_len(), "Secret key has the wrong length for these parameters");
+
+		let coefficients = Secret::new(decode_coefficients(sk.expose(), self.q));
+		Secret::new(pack_coefficients(coefficients.expose(), self.coefficient_bits()))
+	}
+
+	/// Reads a packed secret key back, checking that every coefficient is in [0, q)
+	pub fn unpack_secret_key(&self, packed: &Secret<Vec<u8>>) -> Result<SecretKey, PackingError> {
+		let coefficients = Secret::new(unpack_coefficients(packed.expose(), self.secret_key_len() / 8, self.coefficient_bits(), self.q)?);
+
+		let mut sk = SecretKey::new(vec![0 ; self.secret_key_len()]);
+		encode_coefficients(coefficients.expose(), sk.expose_mut());
+
+		Ok(sk)
+	}
+
+	/// Packs a ciphertext so that each coefficient takes up `coefficient_bits()` bits
+	pub fn pack_ciphertext(&self, ct: &Ciphertext) -> Vec<u8> {
+		assert_eq!(ct.len(), self.ciphertext_len(), "Ciphertext has the wrong length for these parameters");
+		pack_coefficients(&decode_coefficients(ct, self.q), self.coefficient_bits())
+	}
+
+	/// Reads a packed ciphertext back, checking that every coefficient is in [0, q)
+	pub fn unpack_ciphertext(&self, packed: &[u8]) -> Result<Ciphertext, PackingError> {
+		let coefficients = unpack_coefficients(packed, self.ciphertext_len() / 8, self.coefficient_bits(), self.q)?;
+
+		let mut ct = vec![0 ; self.ciphertext_len()];
+		encode_coefficients(&coefficients, &mut ct);
+
+		Ok(ct)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::Rng;
+
+	use crate::lwe::{LweParams, Plaintext};
+
+	use super::{pack_coefficients, unpack_coefficients, PackingError};
+
+	#[test]
+	fn test_coefficient_bits() {
+		assert_eq!(LweParams::DEFAULT.coefficient_bits(), 12);
+		assert_eq!(LweParams::TOY.coefficient_bits(), 9);
+		assert_eq!(LweParams::new(8, 8, 4096, 1).coefficient_bits(), 12);
+		assert_eq!(LweParams::new(8, 8, 4097, 1).coefficient_bits(), 13);
+	}
+
+	#[test]
+	fn test_bit_layout() {
+		// 12-bit coefficients: two of them fill exactly three bytes
+		let packed = pack_coefficients(&[0xABC, 0x123], 12);
+		assert_eq!(packed, [0xBC, 0x3A, 0x12]);
+		assert_eq!(unpack_coefficients(&packed, 2, 12, 3329), Ok(vec![0xABC, 0x123]));
+
+		// one 12-bit coefficient leaves four padding bits
+		assert_eq!(unpack_coefficients(&[0xBC, 0x0A], 1, 12, 3329), Ok(vec![0xABC]));
+		assert_eq!(unpack_coefficients(&[0xBC, 0x1A], 1, 12, 3329), Err(PackingError::NonzeroPadding));
+	}
+
+	#[test]
+	fn test_strict_decoding() {
+		// 3329 fits in 12 bits, but it isn't a valid coefficient
+		let packed = pack_coefficients(&[5, 3329], 12);
+		assert_eq!(unpack_coefficients(&packed, 2, 12, 3329), Err(PackingError::OutOfRange { index: 1, value: 3329 }));
+
+		assert_eq!(unpack_coefficients(&packed, 1, 12, 3329), Err(PackingError::WrongLength { expected: 2, actual: 3 }));
+	}
+
+	#[test]
+	fn test_packed_round_trip() {
+		for params in [LweParams::TOY, LweParams::DEFAULT, LweParams::TOY.with_seeded_matrix()] {
+			let (sk, pk) = params.gen();
+
+			let packed_pk = params.pack_public_key(&pk);
+			let packed_sk = params.pack_secret_key(&sk);
+
+			assert_eq!(packed_pk.len(), params.packed_public_key_len());
+			assert_eq!(packed_sk.expose().len(), params.packed_secret_key_len());
+			assert_eq!(params.unpack_public_key(&packed_pk), Ok(pk.clone()));
+			assert_eq!(params.unpack_secret_key(&packed_sk).unwrap(), sk);
+
+			let plaintext: Plaintext = rand::thread_rng().gen();
+			let ct = params.enc(pk, plaintext).unwrap();
+			let packed_ct = params.pack_ciphertext(&ct);
+
+			assert_eq!(packed_ct.len(), params.packed_ciphertext_len());
+			assert_eq!(params.dec(&sk, params.unpack_ciphertext(&packed_ct).unwrap()).unwrap(), plaintext);
+		}
+
+		// 12 bits instead of 64
+		assert!(LweParams::DEFAULT.packed_public_key_len() * 5 < LweParams::DEFAULT.public_key_len());
+	}
+}
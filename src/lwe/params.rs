@@ -0,0 +1,371 @@
+//
+// Parameter sets for LWE, and rough estimates of how well they do
+//
+
+use std::fmt::Display;
+
+use super::{ErrorDistribution, BIT_LENGTH, SEED_LEN};
+
+/// The dimensions, modulus and error range of an instance of LWE.
+///
+/// A public key is M noisy equations in N unknowns, with one right-hand side per plaintext bit,
+/// and each error is drawn independently from `error`. The coefficients of the equations, the
+/// matrix A, are either stored in the public key or expanded from a short seed.
+///
+/// The fields can only be set through `new` and the `with_` methods, which check that they work
+/// together, and are read with the methods of the same names.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LweParams {
+	/// The number of equations in the public key, the rows of A
+	pub(super) m: usize,
+
+	/// The length of each secret, the columns of A
+	pub(super) n: usize,
+
+	/// The modulus all the arithmetic is done in
+	pub(super) q: i64,
+
+	/// The distribution every error term is drawn from
+	pub(super) error: ErrorDistribution,
+
+	/// Whether public keys hold a seed that A is expanded from, instead of A itself
+	pub(super) seeded_matrix: bool,
+
+	/// How many plaintext bits are packed into each coefficient of a ciphertext, which is 1, 2,
+	/// 4 or 8. More bits make keys and ciphertexts smaller, but leave less room for error.
+	pub(super) bits_per_coefficient: usize
+}
+
+impl LweParams {
+
+	/// Tiny parameters for trying things out. There is no security here whatsoever!
+	pub const TOY: LweParams = LweParams { m: 32, n: 8, q: 257, error: ErrorDistribution::Uniform { bound: 1 }, seeded_matrix: false, bits_per_coefficient: 1 };
+
+	/// The parameters this module has always used, which are kept as the default so that
+	/// existing keys stay readable. These are NOT secure, see `estimated_security_bits`.
+	pub const DEFAULT: LweParams = LweParams { m: 100, n: 30, q: 3329, error: ErrorDistribution::Uniform { bound: 8 }, seeded_matrix: false, bits_per_coefficient: 1 };
+
+	/// Parameters estimated to give roughly 80 bits of security, with A expanded from a seed
+	pub const BITS_80: LweParams = LweParams { m: 450, n: 320, q: 3329, error: ErrorDistribution::Uniform { bound: 2 }, seeded_matrix: true, bits_per_coefficient: 1 };
+
+	/// Parameters estimated to give roughly 128 bits of security, with A expanded from a seed
+	pub const BITS_128: LweParams = LweParams { m: 640, n: 450, q: 3329, error: ErrorDistribution::Uniform { bound: 2 }, seeded_matrix: true, bits_per_coefficient: 1 };
+
+	/// A custom parameter set, which stores A in full in public keys and draws errors uniformly
+	/// from [-error, error].
+	///
+	/// q must be small enough that the product of two entries fits in an i64, and the errors
+	/// must be strictly smaller than q / 4, or nothing could ever be decrypted. Each ciphertext
+	/// coefficient holds one bit.
+	pub fn new(m: usize, n: usize, q: i64, error: i64) -> LweParams {
+		assert!(m > 0 && n > 0, "LWE needs at least one equation and one unknown");
+		assert!((4..(1 << 31)).contains(&q), "The modulus {:?} should be in [4, 2^31)", q);
+
+		LweParams { m, n, q, error: ErrorDistribution::Uniform { bound: 0 }, seeded_matrix: false, bits_per_coefficient: 1 }
+			.with_error(ErrorDistribution::Uniform { bound: error })
+	}
+
+	/// The same parameters, but with errors drawn from `error`, which has the same restriction
+	/// as in `new`
+	pub fn with_error(self, error: ErrorDistribution) -> LweParams {
+		error.validate();
+		assert!(error.max_abs() <= self.decoding_margin(), "The errors of {} are too big for the modulus {:?}", error, self.q);
+
+		LweParams { error, ..self }
+	}
+
+	/// The same parameters, but with `bits` plaintext bits in each ciphertext coefficient, which
+	/// has to be 1, 2, 4 or 8. The message is scaled by q / 2^bits instead of q / 2, so a single
+	/// error still has to fit in the smaller decoding margin that leaves, and `failure_bound`
+	/// tells how likely the errors are to add up to more than it.
+	pub fn with_bits_per_coefficient(self, bits: usize) -> LweParams {
+		assert!([1, 2, 4, 8].contains(&bits), "Can't put {:?} bits in each coefficient, only 1, 2, 4 or 8", bits);
+
+		let params = LweParams { bits_per_coefficient: bits, ..self };
+		assert!(params.error.max_abs() <= params.decoding_margin(), "The errors of {} are too big for {:?} bits per coefficient mod {:?}", params.error, bits, params.q);
+
+		params
+	}
+
+	/// The same parameters, but with A expanded from a seed
+	pub const fn with_seeded_matrix(self) -> LweParams {
+		LweParams { seeded_matrix: true, ..self }
+	}
+
+	// MARK: Fields
+
+	/// The number of equations in the public key, the rows of A
+	pub const fn m(&self) -> usize {
+		self.m
+	}
+
+	/// The length of each secret, the columns of A
+	pub const fn n(&self) -> usize {
+		self.n
+	}
+
+	/// The modulus all the arithmetic is done in
+	pub const fn q(&self) -> i64 {
+		self.q
+	}
+
+	/// The distribution every error term is drawn from
+	pub const fn error(&self) -> ErrorDistribution {
+		self.error
+	}
+
+	/// Whether public keys hold a seed that A is expanded from, instead of A itself
+	pub const fn seeded_matrix(&self) -> bool {
+		self.seeded_matrix
+	}
+
+	/// How many plaintext bits are packed into each coefficient of a ciphertext
+	pub const fn bits_per_coefficient(&self) -> usize {
+		self.bits_per_coefficient
+	}
+
+	// MARK: Sizes
+
+	/// The number of ciphertext coefficients a plaintext is spread over, one for every
+	/// `bits_per_coefficient` bits
+	pub const fn plaintext_coefficients(&self) -> usize {
+		BIT_LENGTH / self.bits_per_coefficient
+	}
+
+	/// The length, in bytes, of a public key
+	pub const fn public_key_len(&self) -> usize {
+		if self.seeded_matrix {
+			SEED_LEN + 8 * self.m * self.plaintext_coefficients()
+		} else {
+			8 * self.m * (self.n + self.plaintext_coefficients())
+		}
+	}
+
+	/// The length, in bytes, of a secret key
+	pub const fn secret_key_len(&self) -> usize {
+		8 * self.n * self.plaintext_coefficients()
+	}
+
+	/// The length, in bytes, of a ciphertext
+	pub const fn ciphertext_len(&self) -> usize {
+		8 * self.plaintext_coefficients() * (self.n + 1)
+	}
+
+	// MARK: Correctness
+
+	/// The largest accumulated error a ciphertext coefficient can take and still decrypt correctly
+	pub fn decoding_margin(&self) -> i64 {
+		// the value v is encoded as floor(v q / 2^b), and decodes correctly as long as the error
+		// keeps it rounding to v, in [ceil((2v - 1) q / 2^(b + 1)), ceil((2v + 1) q / 2^(b + 1)))
+		let q = self.q;
+		let b = self.bits_per_coefficient;
+		let ceil = |x: i64| -(-x).div_euclid(1 << (b + 1));
+
+		(0..(1i64 << b)).map(|v| {
+			let encoded = (v * q) >> b;
+			(encoded - ceil((2 * v - 1) * q)).min(ceil((2 * v + 1) * q) - 1 - encoded)
+		}).min().unwrap()
+	}
+
+	/// The largest error that can ever build up in a ciphertext coefficient, when all M equations are
+	/// selected and every error is as big as it can be
+	pub fn max_error(&self) -> i64 {
+		self.m as i64 * self.error.max_abs()
+	}
+
+	/// An upper bound on the probability that a 256-bit plaintext decrypts incorrectly.
+	///
+	/// This is 0 if even the worst possible error fits within the decoding margin. Otherwise, each
+	/// coefficient's error is a sum of at most M independent subgaussian terms, so the subgaussian
+	/// tail bound (Hoeffding's inequality, for uniform errors) gives the chance that one coefficient
+	/// goes wrong, and a union bound covers all of them.
+	pub fn failure_bound(&self) -> f64 {
+		self.failure_bound_with(self.max_error(), 0, self.fresh_variance())
+	}
+
+	/// A subgaussian variance proxy for the error in a coefficient of a fresh ciphertext
+	pub(super) fn fresh_variance(&self) -> f64 {
+		self.m as f64 * self.error.subgaussian_variance()
+	}
+
+	/// The same bound as `failure_bound`, for an error that is never more than `max_error`, and
+	/// is subgaussian with variance proxy `variance` once a fixed offset of up to `drift` is
+	/// taken out
+	pub(super) fn failure_bound_with(&self, max_error: i64, drift: i64, variance: f64) -> f64 {
+		let margin = self.decoding_margin();
+
+		if max_error <= margin {
+			return 0.0;
+		}
+
+		let t = (margin + 1 - drift) as f64;
+		if t <= 0.0 {
+			return 1.0;
+		}
+
+		let per_coefficient = 2.0 * (-t * t / (2.0 * variance)).exp();
+
+		(self.plaintext_coefficients() as f64 * per_coefficient).min(1.0)
+	}
+
+	// MARK: Hardness
+
+	/// The standard deviation of a single error term
+	fn error_std_dev(&self) -> f64 {
+		self.error.variance().sqrt()
+	}
+
+	/// The smallest BKZ block size that finds the error with the primal (uSVP) attack,
+	/// using the 2016 estimate of Alkim, Ducas, Pöppelmann and Schwabe, or `None` if no
+	/// block size does with at most M samples.
+	pub fn primal_block_size(&self) -> Option<usize> {
+		let n = self.n as f64;
+		let q = self.q as f64;
+		let sigma = self.error_std_dev();
+
+		if sigma == 0.0 {
+			// without any error, this is just linear algebra
+			return Some(0);
+		}
+
+		for beta in 2..=(self.m + 1) {
+			let b = beta as f64;
+
+			// the root Hermite factor BKZ-beta achieves. The formula only holds up from about
+			// beta = 50, and anything below that is cheap anyway, so small block sizes are
+			// treated as doing as well as BKZ-50.
+			let bd = b.max(50.0);
+			let delta = ((std::f64::consts::PI * bd).powf(1.0 / bd) * bd / (2.0 * std::f64::consts::PI * std::f64::consts::E)).powf(1.0 / (2.0 * (bd - 1.0)));
+
+			// the attacker may use any number of the samples, so we try them all
+			for samples in (self.n + 1)..=self.m {
+				let d = (samples + 1) as f64;
+
+				if b > d {
+					continue;
+				}
+
+				// the error vector is found when its projection is shorter than the
+				// Gram-Schmidt vector BKZ leaves at index d - beta
+				if sigma * b.sqrt() <= delta.powf(2.0 * b - d - 1.0) * q.powf((samples as f64 - n) / d) {
+					return Some(beta);
+				}
+			}
+		}
+
+		None
+	}
+
+	/// A rough estimate of the security in bits, as the classical core-SVP cost 0.292 * beta of
+	/// the primal attack. This ignores the dual attack, hybrid attacks and every other refinement,
+	/// so it is only good for comparing parameter sets, not for choosing real ones.
+	pub fn estimated_security_bits(&self) -> f64 {
+		match self.primal_block_size() {
+			Some(beta) => 0.292 * beta as f64,
+			None => f64::INFINITY
+		}
+	}
+}
+
+impl Default for LweParams {
+	fn default() -> Self {
+		LweParams::DEFAULT
+	}
+}
+
+impl Display for LweParams {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "LWE with M={:?}, N={:?}, q={:?}, {} errors", self.m, self.n, self.q, self.error)?;
+		if self.seeded_matrix {
+			writeln!(f, "  A is expanded from a {:?}-byte seed", SEED_LEN)?;
+		}
+		if self.bits_per_coefficient > 1 {
+			writeln!(f, "  {:?} plaintext bits per coefficient", self.bits_per_coefficient)?;
+		}
+		writeln!(f, "  public key: {:?} bytes ({:?} packed)", self.public_key_len(), self.packed_public_key_len())?;
+		writeln!(f, "  secret key: {:?} bytes ({:?} packed)", self.secret_key_len(), self.packed_secret_key_len())?;
+		writeln!(f, "  ciphertext: {:?} bytes ({:?} packed)", self.ciphertext_len(), self.packed_ciphertext_len())?;
+		writeln!(f, "  worst-case error: {:?} (margin {:?})", self.max_error(), self.decoding_margin())?;
+		writeln!(f, "  decryption failure probability <= {:e}", self.failure_bound())?;
+		write!(f, "  estimated security: ~{:.0} bits", self.estimated_security_bits())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ErrorDistribution, LweParams};
+
+	#[test]
+	fn test_presets() {
+		for params in [LweParams::TOY, LweParams::DEFAULT, LweParams::BITS_80, LweParams::BITS_128] {
+			println!("{}", params);
+			assert!(params.failure_bound() < 1e-20);
+		}
+
+		assert_eq!(LweParams::DEFAULT.public_key_len(), super::super::PUBKEY_LEN);
+
+		// with A expanded from a seed, only B = AS + E is left
+		let full_a = LweParams::new(640, 450, 3329, 2);
+		assert!(LweParams::BITS_128.public_key_len() * 2 < full_a.public_key_len());
+		assert_eq!(LweParams::TOY.failure_bound(), 0.0);
+		assert!(LweParams::TOY.estimated_security_bits() < 40.0);
+		assert!(LweParams::DEFAULT.estimated_security_bits() < 40.0);
+		assert!(LweParams::BITS_80.estimated_security_bits() >= 80.0);
+		assert!(LweParams::BITS_128.estimated_security_bits() >= 128.0);
+	}
+
+	#[test]
+	fn test_decoding_margin() {
+		assert_eq!(LweParams::DEFAULT.decoding_margin(), 831);
+		assert_eq!(LweParams::new(10, 10, 8, 1).decoding_margin(), 1);
+	}
+
+	#[test]
+	fn test_bits_per_coefficient() {
+		// with as many bits as still leave room for a single error
+		for (params, max_bits) in [(LweParams::TOY, 4), (LweParams::DEFAULT, 4), (LweParams::BITS_80, 8), (LweParams::new(100, 30, 1 << 20, 8), 8)] {
+			let mut previous = params;
+
+			for bits in [2, 4, 8].into_iter().filter(|bits| *bits <= max_bits) {
+				let multi = params.with_bits_per_coefficient(bits);
+				assert_eq!(multi.bits_per_coefficient(), bits);
+				println!("{}", multi);
+
+				assert_eq!(multi.plaintext_coefficients() * bits, 256);
+				assert_eq!(multi.ciphertext_len() * bits, params.ciphertext_len());
+				assert!(multi.secret_key_len() < previous.secret_key_len());
+
+				// packing more in leaves less room for error
+				assert!(multi.decoding_margin() < previous.decoding_margin());
+				assert!(multi.failure_bound() >= previous.failure_bound());
+
+				previous = multi;
+			}
+		}
+
+		// q / 2^bits is about 2^12 here, so even 8 bits can't fail
+		let roomy = LweParams::new(100, 30, 1 << 20, 8).with_bits_per_coefficient(8);
+		assert_eq!(roomy.decoding_margin(), (1 << 11) - 1);
+		assert_eq!(roomy.failure_bound(), 0.0);
+
+		// but the default parameters only have room for a couple of bits
+		assert!(LweParams::DEFAULT.with_bits_per_coefficient(2).failure_bound() > 0.0);
+		assert!(std::panic::catch_unwind(|| LweParams::DEFAULT.with_bits_per_coefficient(8)).is_err());
+	}
+
+	#[test]
+	fn test_error_distributions() {
+		// uniform errors in [-2, 2] and CBD with eta = 2 both stop at the same place, but the
+		// binomial ones are smaller on average, so they fail less often
+		let uniform = LweParams::new(1000, 20, 3329, 2);
+		let binomial = uniform.with_error(ErrorDistribution::CenteredBinomial { eta: 2 });
+
+		assert_eq!(uniform.max_error(), binomial.max_error());
+		assert!(binomial.failure_bound() < uniform.failure_bound());
+		assert!(binomial.estimated_security_bits() <= uniform.estimated_security_bits());
+
+		let gaussian = uniform.with_error(ErrorDistribution::DiscreteGaussian { sigma: 1.0 });
+		assert_eq!(gaussian.max_error(), 12000);
+		println!("{}", gaussian);
+	}
+}
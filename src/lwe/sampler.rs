@@ -0,0 +1,261 @@
+//
+// The distributions LWE errors are drawn from
+//
+
+use std::fmt::Display;
+
+use rand::RngCore;
+
+use super::expand::uniform_below;
+
+/// How many standard deviations out the discrete Gaussian is cut off. The mass beyond that is
+/// below 2^-100, far smaller than the precision of the table anyway.
+const GAUSSIAN_TAIL_CUT: f64 = 12.0;
+
+/// A distribution of small integers that errors are drawn from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorDistribution {
+	/// Uniform over [-bound, bound], which is simple, but doesn't match any hardness proof
+	Uniform { bound: i64 },
+
+	/// The difference of two sums of eta random bits, as in Kyber. This is centered at zero with
+	/// variance eta / 2, and never goes past eta.
+	CenteredBinomial { eta: u32 },
+
+	/// The discrete Gaussian with parameter sigma, so P(x) is proportional to exp(-x^2 / 2 sigma^2),
+	/// cut off at 12 sigma
+	DiscreteGaussian { sigma: f64 }
+}
+
+impl ErrorDistribution {
+
+	/// The largest absolute value a sample can have
+	pub fn max_abs(&self) -> i64 {
+		match *self {
+			ErrorDistribution::Uniform { bound } => bound,
+			ErrorDistribution::CenteredBinomial { eta } => eta as i64,
+			ErrorDistribution::DiscreteGaussian { sigma } => (GAUSSIAN_TAIL_CUT * sigma).ceil() as i64
+		}
+	}
+
+	/// The variance of a single sample
+	pub fn variance(&self) -> f64 {
+		match *self {
+			// uniform on 2 * bound + 1 consecutive integers
+			ErrorDistribution::Uniform { bound } => {
+				let width = (2 * bound + 1) as f64;
+				(width * width - 1.0) / 12.0
+			},
+			ErrorDistribution::CenteredBinomial { eta } => eta as f64 / 2.0,
+			ErrorDistribution::DiscreteGaussian { sigma } => {
+				// for small sigma this differs noticeably from sigma^2, so it is summed up exactly
+				let weights = gaussian_weights(sigma);
+				let total: f64 = weights.iter().enumerate().map(|(k, w)| if k == 0 { *w } else { 2.0 * w }).sum();
+				weights.iter().enumerate().map(|(k, w)| 2.0 * w * (k * k) as f64).sum::<f64>() / total
+			}
+		}
+	}
+
+	/// A variance proxy s^2 for which a sample x has E[exp(tx)] <= exp(s^2 t^2 / 2) for every t,
+	/// so that tail bounds for sums of samples can be worked out as if they were Gaussian
+	pub fn subgaussian_variance(&self) -> f64 {
+		match *self {
+			// Hoeffding's lemma, for anything in an interval of width 2 * bound
+			ErrorDistribution::Uniform { bound } => (bound * bound) as f64,
+
+			// 2 eta independent bits, each in an interval of width 1
+			ErrorDistribution::CenteredBinomial { eta } => eta as f64 / 2.0,
+
+			// the discrete Gaussian is as subgaussian as the continuous one it comes from
+			ErrorDistribution::DiscreteGaussian { sigma } => sigma * sigma
+		}
+	}
+
+	/// The probability of every value from -max_abs to max_abs, in that order
+	pub fn probabilities(&self) -> Vec<f64> {
+		match *self {
+			ErrorDistribution::Uniform { bound } => vec![1.0 / (2 * bound + 1) as f64 ; (2 * bound + 1) as usize],
+
+			ErrorDistribution::CenteredBinomial { eta } => {
+				// P(x) = C(2 eta, eta + x) / 2^(2 eta), building up the binomial coefficients one at a time
+				let trials = 2 * eta as usize;
+				let mut coefficient = 1.0;
+				let mut probabilities = Vec::with_capacity(trials + 1);
+
+				for k in 0..=trials {
+					probabilities.push(coefficient / 2f64.powi(trials as i32));
+					coefficient = coefficient * (trials - k) as f64 / (k + 1) as f64;
+				}
+
+				probabilities
+			},
+
+			ErrorDistribution::DiscreteGaussian { sigma } => {
+				let weights = gaussian_weights(sigma);
+				let total: f64 = weights.iter().enumerate().map(|(k, w)| if k == 0 { *w } else { 2.0 * w }).sum();
+
+				weights.iter().rev().chain(weights.iter().skip(1)).map(|w| w / total).collect()
+			}
+		}
+	}
+
+	/// Fills `out` with independent samples.
+	///
+	/// Every sample is read straight off the output of `rng`, so that keys derived from a seed
+	/// don't depend on how the `rand` crate happens to map random bits to ranges: a uniform
+	/// sample is `uniform_below(2 bound + 1) - bound`, and the others each take the next u64.
+	pub fn sample_into(&self, out: &mut [i64], rng: &mut dyn RngCore) {
+		match *self {
+			ErrorDistribution::Uniform { bound } => {
+				for e in out.iter_mut() {
+					*e = uniform_below(2 * bound + 1, rng) - bound;
+				}
+			},
+
+			ErrorDistribution::CenteredBinomial { eta } => {
+				let mask = (1u64 << eta) - 1;
+
+				for e in out.iter_mut() {
+					// the low eta bits are added, and the next eta subtracted
+					let bits = rng.next_u64();
+					*e = (bits & mask).count_ones() as i64 - ((bits >> eta) & mask).count_ones() as i64;
+				}
+			},
+
+			ErrorDistribution::DiscreteGaussian { sigma } => {
+				let table = gaussian_cdt(sigma);
+
+				for e in out.iter_mut() {
+					*e = sample_cdt(&table, rng);
+				}
+			}
+		}
+	}
+
+	/// Panics if this can't be sampled from
+	pub(crate) fn validate(&self) {
+		match *self {
+			ErrorDistribution::Uniform { bound } => assert!(bound >= 0, "The uniform bound must not be negative"),
+			ErrorDistribution::CenteredBinomial { eta } => assert!(eta <= 32, "eta can be at most 32"),
+			ErrorDistribution::DiscreteGaussian { sigma } => assert!(sigma > 0.0 && sigma.is_finite(), "sigma must be positive")
+		}
+	}
+}
+
+impl Display for ErrorDistribution {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match *self {
+			ErrorDistribution::Uniform { bound } => write!(f, "uniform in [-{:?}, {:?}]", bound, bound),
+			ErrorDistribution::CenteredBinomial { eta } => write!(f, "centered binomial with eta={:?}", eta),
+			ErrorDistribution::DiscreteGaussian { sigma } => write!(f, "discrete Gaussian with sigma={:?}", sigma)
+		}
+	}
+}
+
+// MARK: Discrete Gaussian
+
+/// exp(-k^2 / 2 sigma^2) for k = 0, 1, ..., up to the tail cut
+fn gaussian_weights(sigma: f64) -> Vec<f64> {
+	let tail = (GAUSSIAN_TAIL_CUT * sigma).ceil() as usize;
+	(0..=tail).map(|k| (-((k * k) as f64) / (2.0 * sigma * sigma)).exp()).collect()
+}
+
+/// The cumulative distribution table of |x|, where entry k is P(|x| <= k), scaled up to 2^63
+fn gaussian_cdt(sigma: f64) -> Vec<u64> {
+	let weights = gaussian_weights(sigma);
+
+	// every nonzero |x| covers both x and -x
+	let total: f64 = weights.iter().enumerate().map(|(k, w)| if k == 0 { *w } else { 2.0 * w }).sum();
+
+	let scale = (1u64 << 63) as f64;
+	let mut cumulative = 0.0;
+
+	let mut table: Vec<u64> = weights.iter().enumerate().map(|(k, w)| {
+		cumulative += if k == 0 { *w } else { 2.0 * w };
+		((cumulative / total) * scale).min(scale - 1.0) as u64
+	}).collect();
+
+	// whatever rounding left over goes to the last entry, so every draw lands somewhere
+	*table.last_mut().unwrap() = 1 << 63;
+
+	table
+}
+
+/// Samples from a CDT by comparing a random 63-bit value against every entry. The whole table
+/// is always read and nothing branches on the secret, so the time this takes doesn't depend on
+/// the sample.
+fn sample_cdt(table: &[u64], rng: &mut dyn RngCore) -> i64 {
+	let r = rng.next_u64();
+	let value = r >> 1;
+	let sign = (r & 1) as i64;
+
+	let mut magnitude = 0;
+	for entry in table {
+		// |x| is the number of entries at or below the random value
+		magnitude += (*entry <= value) as i64;
+	}
+
+	// (1 - 2 * sign) is either 1 or -1
+	magnitude * (1 - 2 * sign)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use super::ErrorDistribution;
+
+	const SAMPLES: usize = 200_000;
+
+	/// Checks the empirical mean and variance against the exact ones, and that nothing is out of range
+	fn check_moments(distribution: ErrorDistribution) {
+		let mut samples = vec![0 ; SAMPLES];
+		distribution.sample_into(&mut samples, &mut StdRng::seed_from_u64(0x5a));
+
+		let n = SAMPLES as f64;
+		let mean = samples.iter().sum::<i64>() as f64 / n;
+		let variance = samples.iter().map(|x| (*x as f64 - mean).powi(2)).sum::<f64>() / (n - 1.0);
+		let expected = distribution.variance();
+
+		// the mean should be within a few standard errors of zero
+		assert!(mean.abs() < 5.0 * (expected / n).sqrt(), "{}: mean of {:?}", distribution, mean);
+		assert!((variance - expected).abs() < 0.03 * expected, "{}: variance of {:?}, expected {:?}", distribution, variance, expected);
+		assert!(samples.iter().all(|x| x.abs() <= distribution.max_abs()), "{}: sample out of range", distribution);
+
+		// the exact probabilities add up to 1 and agree with the variance
+		let probabilities = distribution.probabilities();
+		assert_eq!(probabilities.len() as i64, 2 * distribution.max_abs() + 1);
+		assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+
+		let exact: f64 = probabilities.iter().enumerate().map(|(i, p)| p * ((i as i64 - distribution.max_abs()) as f64).powi(2)).sum();
+		assert!((exact - expected).abs() < 1e-9 * expected.max(1.0), "{}: exact variance of {:?}", distribution, exact);
+
+		// symmetric distributions have no skew, so the third central moment should be tiny
+		let third = samples.iter().map(|x| (*x as f64 - mean).powi(3)).sum::<f64>() / n;
+		assert!(third.abs() < 0.1 * expected.powf(1.5), "{}: third moment of {:?}", distribution, third);
+	}
+
+	#[test]
+	fn test_uniform() {
+		check_moments(ErrorDistribution::Uniform { bound: 2 });
+		check_moments(ErrorDistribution::Uniform { bound: 8 });
+	}
+
+	#[test]
+	fn test_centered_binomial() {
+		for eta in [1, 2, 3, 5] {
+			check_moments(ErrorDistribution::CenteredBinomial { eta });
+		}
+	}
+
+	#[test]
+	fn test_discrete_gaussian() {
+		for sigma in [0.8, 1.5, 3.2, 10.0] {
+			check_moments(ErrorDistribution::DiscreteGaussian { sigma });
+		}
+
+		// a wide enough discrete Gaussian has very nearly the variance of the continuous one
+		let variance = ErrorDistribution::DiscreteGaussian { sigma: 3.2 }.variance();
+		assert!((variance - 3.2 * 3.2).abs() < 1e-6);
+	}
+}
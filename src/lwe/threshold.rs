@@ -0,0 +1,544 @@
+//
+// Threshold decryption, where the secret key is split up with Shamir's secret sharing so that
+// any t out of k parties can decrypt together, but fewer than t learn nothing about the key
+//
+
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+
+use crate::secret::{Scrub, Secret};
+use crate::secsharing::sharing::{create_curve_mod_with_rng, lagrange_coefficients_mod};
+
+use super::{check_len, ct_to_matrix_rep, decode_value, matrix_rep_to_pt, matrix_rep_to_sk, sk_to_matrix_rep, values_to_bits, Ciphertext, ErrorDistribution, LweError, LweParams, Plaintext, SecretKey};
+
+/// How many bits of statistical security the smudging noise has to give: a partial decryption
+/// can be told apart from one of a ciphertext with no error at all with an advantage of at most
+/// 2^-SMUDGING_SECURITY_BITS. Anything much more than this needs q far beyond the 2^31 the
+/// parameters allow, so it is only a modest margin, not the 40 or more bits a real system wants.
+pub const SMUDGING_SECURITY_BITS: u32 = 12;
+
+/// One party's share of a secret key
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyShare {
+	/// Which share this is, from 1 to k
+	index: u64,
+
+	/// How many shares it takes to decrypt
+	threshold: usize,
+
+	/// The share of every coefficient of the secret key, encoded the same way as a secret key
+	share: SecretKey
+}
+
+impl KeyShare {
+	/// Which share this is, from 1 to k
+	pub fn index(&self) -> u64 {
+		self.index
+	}
+
+	/// How many shares it takes to decrypt
+	pub fn threshold(&self) -> usize {
+		self.threshold
+	}
+
+	/// Encodes this share as its index and threshold, each a little-endian u64, followed by the
+	/// share itself, encoded the same way as a secret key
+	pub fn to_bytes(&self) -> Secret<Vec<u8>> {
+		let mut bytes = Secret::new(vec![0 ; 16 + self.share.expose().len()]);
+		let encoded = bytes.expose_mut();
+
+		encoded[0..8].copy_from_slice(&self.index.to_le_bytes());
+		encoded[8..16].copy_from_slice(&(self.threshold as u64).to_le_bytes());
+		encoded[16..].copy_from_slice(self.share.expose());
+
+		bytes
+	}
+
+	/// Reads a share back from `to_bytes`, checking that it could have come from splitting a key
+	/// with `params`: the index and threshold are in [1, q), and every coefficient is in [0, q)
+	pub fn from_bytes(params: &LweParams, bytes: &Secret<Vec<u8>>) -> Result<KeyShare, LweError> {
+		let bytes = bytes.expose();
+		check_len(bytes, 16 + params.secret_key_len())?;
+
+		let index = read_u64(&bytes[0..8]);
+		if index == 0 || index >= params.q as u64 {
+			return Err(LweError::InvalidParticipant { index });
+		}
+
+		let threshold = read_u64(&bytes[8..16]);
+		if threshold == 0 || threshold >= params.q as u64 {
+			return Err(LweError::Malformed);
+		}
+
+		if !bytes[16..].chunks_exact(8).all(|chunk| (0..params.q).contains(&i64::from_le_bytes(chunk.try_into().unwrap()))) {
+			return Err(LweError::Malformed);
+		}
+
+		Ok(KeyShare { index, threshold: threshold as usize, share: SecretKey::new(bytes[16..].to_vec()) })
+	}
+}
+
+/// One party's contribution to decrypting a ciphertext, made for one particular set of
+/// participants
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialDecryption {
+	/// The index of the share this came from
+	index: u64,
+
+	/// The indices of everybody taking part, in order
+	participants: Vec<u64>,
+
+	/// This party's part of the inner product of each coefficient with the secret, with smudging noise
+	values: Vec<i64>
+}
+
+impl PartialDecryption {
+	/// The index of the share this came from
+	pub fn index(&self) -> u64 {
+		self.index
+	}
+
+	/// The indices of everybody taking part, in order
+	pub fn participants(&self) -> &[u64] {
+		&self.participants
+	}
+
+	/// Encodes this partial decryption as its index and the number of participants, then each
+	/// participant, then each value, all little-endian 64-bit integers
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(16 + 8 * (self.participants.len() + self.values.len()));
+
+		bytes.extend_from_slice(&self.index.to_le_bytes());
+		bytes.extend_from_slice(&(self.participants.len() as u64).to_le_bytes());
+		for participant in &self.participants {
+			bytes.extend_from_slice(&participant.to_le_bytes());
+		}
+		for value in &self.values {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+
+		bytes
+	}
+
+	/// Reads a partial decryption back from `to_bytes`, checking that it could have been made with
+	/// `params`: the participants are all different, valid indices, and include this one, and there
+	/// is one value in [0, q) for each plaintext coefficient
+	pub fn from_bytes(params: &LweParams, bytes: &[u8]) -> Result<PartialDecryption, LweError> {
+		if bytes.len() < 16 {
+			return Err(LweError::TooShort { minimum: 16, actual: bytes.len() });
+		}
+
+		let index = read_u64(&bytes[0..8]);
+		let count = read_u64(&bytes[8..16]);
+
+		// the count is checked against the length before anything is allocated for it
+		let participants_len = usize::try_from(count).ok().and_then(|count| count.checked_mul(8)).ok_or(LweError::Malformed)?;
+		let expected = participants_len.checked_add(16 + 8 * params.plaintext_coefficients()).ok_or(LweError::Malformed)?;
+		check_len(bytes, expected)?;
+
+		let (participants, values) = bytes[16..].split_at(participants_len);
+
+		let partial = PartialDecryption {
+			index,
+			participants: participants.chunks_exact(8).map(read_u64).collect(),
+			values: values.chunks_exact(8).map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap())).collect()
+		};
+
+		params.check_partial(&partial)?;
+
+		Ok(partial)
+	}
+}
+
+/// Reads a little-endian u64 from exactly 8 bytes
+fn read_u64(bytes: &[u8]) -> u64 {
+	u64::from_le_bytes(bytes.try_into().unwrap())
+}
+
+/// Whether q is prime, by trial division, which is quick enough for any q that fits the parameters
+pub(super) fn is_prime(q: i64) -> bool {
+	q >= 2 && (2..).take_while(|d| d * d <= q).all(|d| q % d != 0)
+}
+
+impl LweParams {
+
+	/// Checks that `participants` are at least `threshold` different indices, each of which could
+	/// be the index of a share, so that their Lagrange coefficients mod q exist
+	fn check_participants(&self, participants: &[u64], threshold: usize) -> Result<(), LweError> {
+		if participants.len() < threshold.max(1) {
+			return Err(LweError::TooFewParticipants { threshold: threshold.max(1), actual: participants.len() });
+		}
+
+		for (i, index) in participants.iter().enumerate() {
+			if *index == 0 || *index >= self.q as u64 {
+				return Err(LweError::InvalidParticipant { index: *index });
+			}
+
+			if participants[..i].contains(index) {
+				return Err(LweError::DuplicateParticipant { index: *index });
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Checks that a partial decryption could have been made with these parameters
+	fn check_partial(&self, partial: &PartialDecryption) -> Result<(), LweError> {
+		self.check_participants(&partial.participants, 1)?;
+
+		if !partial.participants.contains(&partial.index) {
+			return Err(LweError::NotParticipating { index: partial.index });
+		}
+
+		if partial.values.len() != self.plaintext_coefficients() || !partial.values.iter().all(|value| (0..self.q).contains(value)) {
+			return Err(LweError::InvalidPartial { index: partial.index });
+		}
+
+		Ok(())
+	}
+
+	/// The largest smudging noise each of `participants` parties can add to a partial decryption,
+	/// while the combined plaintext still always decrypts correctly.
+	///
+	/// The smudging noise is what keeps partial decryptions from leaking the shares, but it only
+	/// does that when it is at least `smudging_needed`, which takes a much bigger q relative to
+	/// the errors than any of the presets have.
+	pub fn smudging_bound(&self, participants: usize) -> i64 {
+		((self.decoding_margin() - self.max_error()) / participants as i64).max(0)
+	}
+
+	/// The smallest smudging noise that hides the error in a ciphertext to within
+	/// `SMUDGING_SECURITY_BITS` bits.
+	///
+	/// Noise drawn uniformly from [-B, B] moves the distribution of a coefficient by |e| / (2B + 1)
+	/// when an error e is added to it. Half of the advantage allowed goes to the chance that some
+	/// coefficient's error is bigger than the subgaussian tail bound, as in `failure_bound`, and
+	/// the other half to every coefficient's error up to that bound.
+	pub fn smudging_needed(&self) -> i64 {
+		let coefficients = self.plaintext_coefficients() as f64;
+		let advantage = 2f64.powi(-(SMUDGING_SECURITY_BITS as i32) - 1);
+
+		let tail = (2.0 * self.fresh_variance() * (2.0 * coefficients / advantage).ln()).sqrt();
+		let error = tail.min(self.max_error() as f64);
+
+		(coefficients * error / (2.0 * advantage)).ceil() as i64
+	}
+
+	/// Splits `sk` into k shares, any t of which are enough to decrypt.
+	///
+	/// q has to be a prime bigger than k, so that the shares can be interpolated, and t has to be
+	/// between 1 and k.
+	pub fn split_key(&self, sk: &SecretKey, t: usize, k: usize) -> Result<Vec<KeyShare>, LweError> {
+		self.split_key_with_rng(sk, t, k, &mut StdRng::from_entropy())
+	}
+
+	/// Splits `sk` into k shares, any t of which are enough to decrypt, drawing the sharing
+	/// polynomials from `rng`
+	pub fn split_key_with_rng(&self, sk: &SecretKey, t: usize, k: usize, rng: &mut (impl RngCore + CryptoRng)) -> Result<Vec<KeyShare>, LweError> {
+		if !is_prime(self.q) {
+			return Err(LweError::NotPrime { q: self.q });
+		}
+
+		if t == 0 || t > k || k as i64 >= self.q {
+			return Err(LweError::InvalidThreshold { t, k });
+		}
+
+		let mut sk_mat = sk_to_matrix_rep(self, sk)?;
+
+		// every share is a matrix the same shape as the key, and coefficient by coefficient they
+		// are points on a curve through that coefficient of the key
+		let mut share_mats = vec![sk_mat.clone() ; k];
+		for (j, coefficient) in sk_mat.entries().iter().enumerate() {
+			for (share_mat, (_, y)) in share_mats.iter_mut().zip(create_curve_mod_with_rng(t, k, *coefficient, self.q, rng)) {
+				share_mat.entries_mut()[j] = y;
+			}
+		}
+
+		sk_mat.scrub();
+
+		Ok(share_mats.into_iter().enumerate().map(|(i, mut share_mat)| {
+			let share = matrix_rep_to_sk(&share_mat);
+			share_mat.scrub();
+
+			KeyShare { index: i as u64 + 1, threshold: t, share }
+		}).collect())
+	}
+
+	/// Makes this party's contribution to decrypting `ct`, together with the rest of `participants`.
+	///
+	/// `participants` are the indices of the shares taking part, which must all be different,
+	/// include this one and be at least the threshold, and every partial decryption has to be made
+	/// for the same ones. This fails rather than give away the share if `smudging_bound` for that
+	/// many participants is less than `smudging_needed`.
+	pub fn partial_dec(&self, share: &KeyShare, participants: &[u64], ct: &Ciphertext) -> Result<PartialDecryption, LweError> {
+		self.partial_dec_with_rng(share, participants, ct, &mut StdRng::from_entropy())
+	}
+
+	/// The same as `partial_dec`, drawing the smudging noise from `rng`
+	pub fn partial_dec_with_rng(&self, share: &KeyShare, participants: &[u64], ct: &Ciphertext, rng: &mut (impl RngCore + CryptoRng)) -> Result<PartialDecryption, LweError> {
+		if !is_prime(self.q) {
+			return Err(LweError::NotPrime { q: self.q });
+		}
+
+		self.check_participants(participants, share.threshold)?;
+
+		let position = participants.iter().position(|x| *x == share.index)
+			.ok_or(LweError::NotParticipating { index: share.index })?;
+
+		let bound = self.smudging_bound(participants.len());
+		let needed = self.smudging_needed();
+		if bound < needed {
+			return Err(LweError::WeakSmudging { bound, needed });
+		}
+
+		// each party scales its own share by its Lagrange coefficient, so that the smudging noise
+		// is only ever added up, never multiplied by a coefficient that could be as big as q
+		let lambda = lagrange_coefficients_mod(participants, self.q)[position];
+
+		let ct_mat = ct_to_matrix_rep(self, ct)?;
+		let mut share_mat = sk_to_matrix_rep(self, &share.share)?;
+		let a = ct_mat.columns(0, self.n);
+
+		let mut noise = vec![0 ; self.plaintext_coefficients()];
+		ErrorDistribution::Uniform { bound }.sample_into(&mut noise, rng);
+
+		let values = (0..self.plaintext_coefficients())
+			.map(|r| (lambda * a.row_col_dot(r, &share_mat, r) + noise[r]).rem_euclid(self.q))
+			.collect();
+
+		share_mat.scrub();
+		noise.scrub();
+
+		Ok(PartialDecryption { index: share.index, participants: participants.to_vec(), values })
+	}
+
+	/// Combines the partial decryptions of `ct` from every one of the participants they were made
+	/// for, failing if any is missing, repeated, made for other participants, or doesn't fit
+	/// these parameters
+	pub fn combine(&self, ct: &Ciphertext, partials: &[PartialDecryption]) -> Result<Plaintext, LweError> {
+		let participants = &partials.first().ok_or(LweError::TooFewParticipants { threshold: 1, actual: 0 })?.participants;
+
+		for (i, partial) in partials.iter().enumerate() {
+			if partial.participants != *participants {
+				return Err(LweError::MismatchedParticipants);
+			}
+
+			self.check_partial(partial)?;
+
+			if partials[..i].iter().any(|other| other.index == partial.index) {
+				return Err(LweError::DuplicateParticipant { index: partial.index });
+			}
+		}
+
+		// every partial is from a different participant, so this only leaves out missing ones
+		if let Some(index) = participants.iter().find(|x| !partials.iter().any(|partial| partial.index == **x)) {
+			return Err(LweError::MissingPartial { index: *index });
+		}
+
+		let ct_mat = ct_to_matrix_rep(self, ct)?;
+
+		let values: Vec<i64> = (0..self.plaintext_coefficients()).map(|r| {
+			let dot = partials.iter().fold(0, |sum, partial| (sum + partial.values[r]) % self.q);
+			decode_value((ct_mat[(r, self.n)] - dot).rem_euclid(self.q), self.q, self.bits_per_coefficient)
+		}).collect();
+
+		Ok(matrix_rep_to_pt(&values_to_bits(&values, self.bits_per_coefficient)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::seq::IteratorRandom;
+	use rand::Rng;
+
+	use crate::lwe::{ct_to_matrix_rep, sk_to_matrix_rep, LweError, LweParams, PLAINTEXT_LEN};
+	use crate::secsharing::sharing::lagrange_coefficients_mod;
+
+	use super::{is_prime, KeyShare, PartialDecryption};
+
+	/// Small errors and the biggest prime q allowed, which leaves room for enough smudging noise
+	fn smudged() -> LweParams {
+		LweParams::new(32, 16, 2_147_483_647, 1)
+	}
+
+	#[test]
+	fn test_threshold_decryption() {
+		for (params, t, k) in [(smudged(), 2, 3), (smudged(), 3, 5), (smudged(), 4, 4), (smudged().with_bits_per_coefficient(4), 2, 3)] {
+			let (sk, pk) = params.gen();
+			let shares = params.split_key(&sk, t, k).unwrap();
+			assert_eq!(shares.len(), k);
+
+			for _ in 0..4 {
+				let mut pt = [0 ; PLAINTEXT_LEN];
+				rand::thread_rng().fill(&mut pt);
+				let ct = params.enc(pk.clone(), pt).unwrap();
+
+				// any t of the shares will do, and so will more than t
+				let count = rand::thread_rng().gen_range(t..=k);
+				let chosen = shares.iter().choose_multiple(&mut rand::thread_rng(), count);
+				let participants: Vec<u64> = chosen.iter().map(|share| share.index()).collect();
+
+				let partials: Vec<_> = chosen.iter().map(|share| params.partial_dec(share, &participants, &ct).unwrap()).collect();
+				assert_eq!(params.combine(&ct, &partials).unwrap(), pt);
+			}
+		}
+	}
+
+	#[test]
+	fn test_smudging_noise() {
+		let params = smudged();
+		let (sk, pk) = params.gen();
+		let shares = params.split_key(&sk, 2, 3).unwrap();
+		let ct = params.enc(pk, [0x5A ; PLAINTEXT_LEN]).unwrap();
+
+		let participants = [1, 2];
+		let partial = params.partial_dec(&shares[0], &participants, &ct).unwrap();
+
+		// take out exactly what the partial decryption would be with no noise at all
+		let lambda = lagrange_coefficients_mod(&participants, params.q)[0];
+		let ct_mat = ct_to_matrix_rep(&params, &ct).unwrap();
+		let share_mat = sk_to_matrix_rep(&params, &shares[0].share).unwrap();
+		let a = ct_mat.columns(0, params.n);
+
+		let noise: Vec<i64> = partial.values.iter().enumerate().map(|(r, value)| {
+			let noise = (value - lambda * a.row_col_dot(r, &share_mat, r)).rem_euclid(params.q);
+			if noise > params.q / 2 { noise - params.q } else { noise }
+		}).collect();
+
+		// the noise stays within the bound, but goes well past what it takes to hide the error
+		let bound = params.smudging_bound(participants.len());
+		assert!(bound >= params.smudging_needed());
+		assert!(noise.iter().all(|x| x.abs() <= bound));
+		assert!(noise.iter().any(|x| x.abs() > bound / 2));
+		assert!(noise.iter().any(|x| x.abs() > params.smudging_needed()));
+
+		// and it is fresh every time
+		assert_ne!(params.partial_dec(&shares[0], &participants, &ct).unwrap(), partial);
+	}
+
+	#[test]
+	fn test_weak_smudging() {
+		for params in [LweParams::TOY, LweParams::DEFAULT, LweParams::new(100, 30, 1_000_003, 8)] {
+			let (sk, pk) = params.gen();
+			let shares = params.split_key(&sk, 2, 3).unwrap();
+			let ct = params.enc(pk, [0 ; PLAINTEXT_LEN]).unwrap();
+
+			let result = params.partial_dec(&shares[0], &[1, 2], &ct);
+			assert_eq!(result, Err(LweError::WeakSmudging { bound: params.smudging_bound(2), needed: params.smudging_needed() }));
+		}
+
+		// more participants leave less room for each one's noise
+		assert!(smudged().smudging_bound(5) >= smudged().smudging_needed());
+		assert!(smudged().smudging_bound(50) < smudged().smudging_needed());
+	}
+
+	#[test]
+	fn test_invalid_split() {
+		let (sk, _) = LweParams::new(32, 16, 1 << 20, 1).gen();
+		assert_eq!(LweParams::new(32, 16, 1 << 20, 1).split_key(&sk, 2, 3), Err(LweError::NotPrime { q: 1 << 20 }));
+
+		let params = smudged();
+		let (sk, _) = params.gen();
+		assert_eq!(params.split_key(&sk, 4, 3), Err(LweError::InvalidThreshold { t: 4, k: 3 }));
+		assert_eq!(params.split_key(&sk, 0, 3), Err(LweError::InvalidThreshold { t: 0, k: 3 }));
+	}
+
+	#[test]
+	fn test_invalid_participants() {
+		let params = smudged();
+		let (sk, pk) = params.gen();
+		let shares = params.split_key(&sk, 3, 5).unwrap();
+		let ct = params.enc(pk, [0x3C ; PLAINTEXT_LEN]).unwrap();
+
+		let partial_dec = |participants: &[u64]| params.partial_dec(&shares[0], participants, &ct);
+		assert_eq!(partial_dec(&[1, 2]), Err(LweError::TooFewParticipants { threshold: 3, actual: 2 }));
+		assert_eq!(partial_dec(&[1, 2, 2]), Err(LweError::DuplicateParticipant { index: 2 }));
+		assert_eq!(partial_dec(&[2, 3, 4]), Err(LweError::NotParticipating { index: 1 }));
+		assert_eq!(partial_dec(&[1, 0, 3]), Err(LweError::InvalidParticipant { index: 0 }));
+		assert_eq!(partial_dec(&[1, 2, params.q as u64 + 2]), Err(LweError::InvalidParticipant { index: params.q as u64 + 2 }));
+	}
+
+	#[test]
+	fn test_mismatched_partials() {
+		let params = smudged();
+		let (sk, pk) = params.gen();
+		let shares = params.split_key(&sk, 2, 3).unwrap();
+		let ct = params.enc(pk, [0xA5 ; PLAINTEXT_LEN]).unwrap();
+
+		let first = params.partial_dec(&shares[0], &[1, 2], &ct).unwrap();
+		let second = params.partial_dec(&shares[1], &[1, 2], &ct).unwrap();
+		let other = params.partial_dec(&shares[1], &[2, 3], &ct).unwrap();
+
+		assert_eq!(params.combine(&ct, &[]), Err(LweError::TooFewParticipants { threshold: 1, actual: 0 }));
+		assert_eq!(params.combine(&ct, &[first.clone(), other]), Err(LweError::MismatchedParticipants));
+		assert_eq!(params.combine(&ct, &[first.clone()]), Err(LweError::MissingPartial { index: 2 }));
+		assert_eq!(params.combine(&ct, &[first.clone(), first.clone()]), Err(LweError::DuplicateParticipant { index: 1 }));
+
+		// partials that don't fit the parameters, which could only have come from someone else
+		let mut short = second.clone();
+		short.values.pop();
+		assert_eq!(params.combine(&ct, &[first.clone(), short]), Err(LweError::InvalidPartial { index: 2 }));
+
+		let mut out_of_range = second.clone();
+		out_of_range.values[0] = i64::MAX;
+		assert_eq!(params.combine(&ct, &[first.clone(), out_of_range]), Err(LweError::InvalidPartial { index: 2 }));
+
+		let mut stranger = second.clone();
+		stranger.index = 3;
+		assert_eq!(params.combine(&ct, &[first.clone(), stranger]), Err(LweError::NotParticipating { index: 3 }));
+
+		assert_eq!(params.combine(&ct, &[second, first]), Ok([0xA5 ; PLAINTEXT_LEN]));
+	}
+
+	#[test]
+	fn test_bytes() {
+		let params = smudged();
+		let (sk, pk) = params.gen();
+		let shares = params.split_key(&sk, 2, 3).unwrap();
+		let ct = params.enc(pk, [0x96 ; PLAINTEXT_LEN]).unwrap();
+
+		let share_bytes = shares[1].to_bytes();
+		assert_eq!(KeyShare::from_bytes(&params, &share_bytes), Ok(shares[1].clone()));
+
+		let partials: Vec<_> = shares[..2].iter()
+			.map(|share| params.partial_dec(share, &[1, 2], &ct).unwrap().to_bytes())
+			.map(|bytes| PartialDecryption::from_bytes(&params, &bytes).unwrap())
+			.collect();
+		assert_eq!(params.combine(&ct, &partials), Ok([0x96 ; PLAINTEXT_LEN]));
+
+		// key shares with a bad index, threshold or coefficient
+		let corrupt_share = |at: usize, with: u64| {
+			let mut bytes = share_bytes.clone();
+			bytes.expose_mut()[at..(at + 8)].copy_from_slice(&with.to_le_bytes());
+			KeyShare::from_bytes(&params, &bytes)
+		};
+		assert_eq!(corrupt_share(0, 0), Err(LweError::InvalidParticipant { index: 0 }));
+		assert_eq!(corrupt_share(8, 0), Err(LweError::Malformed));
+		assert_eq!(corrupt_share(16, params.q as u64), Err(LweError::Malformed));
+		assert_eq!(KeyShare::from_bytes(&LweParams::TOY, &share_bytes), Err(LweError::WrongLength { expected: 16 + LweParams::TOY.secret_key_len(), actual: share_bytes.expose().len() }));
+
+		// partial decryptions that are cut short, run long, or claim too many participants
+		let partial_bytes = partials[0].to_bytes();
+		let expected = partial_bytes.len();
+		assert_eq!(PartialDecryption::from_bytes(&params, &partial_bytes[..8]), Err(LweError::TooShort { minimum: 16, actual: 8 }));
+		assert_eq!(PartialDecryption::from_bytes(&params, &partial_bytes[..(expected - 1)]), Err(LweError::WrongLength { expected, actual: expected - 1 }));
+		assert_eq!(PartialDecryption::from_bytes(&params, &[partial_bytes.as_slice(), &[0]].concat()), Err(LweError::WrongLength { expected, actual: expected + 1 }));
+
+		let corrupt_partial = |at: usize, with: u64| {
+			let mut bytes = partial_bytes.clone();
+			bytes[at..(at + 8)].copy_from_slice(&with.to_le_bytes());
+			PartialDecryption::from_bytes(&params, &bytes)
+		};
+		assert_eq!(corrupt_partial(8, u64::MAX), Err(LweError::Malformed));
+		assert_eq!(corrupt_partial(8, 3), Err(LweError::WrongLength { expected: expected + 8, actual: expected }));
+		assert_eq!(corrupt_partial(0, 3), Err(LweError::NotParticipating { index: 3 }));
+		assert_eq!(corrupt_partial(24, 1), Err(LweError::DuplicateParticipant { index: 1 }));
+		assert_eq!(corrupt_partial(32, u64::MAX), Err(LweError::InvalidPartial { index: 1 }));
+	}
+
+	#[test]
+	fn test_is_prime() {
+		assert!(is_prime(257) && is_prime(3329) && is_prime(12289) && is_prime(1_000_003));
+		assert!(!is_prime(1) && !is_prime(1 << 20) && !is_prime(3329 * 257));
+	}
+}
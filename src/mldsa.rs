@@ -0,0 +1,947 @@
+//
+// ML-DSA, the module-lattice digital signature algorithm of FIPS 204, in all three of its
+// parameter sets. Signing is hedged by default, mixing fresh randomness in with the key and
+// message, and can also be made deterministic.
+//
+
+use std::fmt::Display;
+
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+
+use crate::ntt::dilithium::{canonical, inv_ntt, ntt, ntt_dot, pointwise_mul, reduce32, Q};
+use crate::ntt::N;
+use crate::secret::{Scrub, Secret};
+use crate::sha3::Shake;
+
+/// The length, in bytes, of the key generation seed xi, of rho and K, and of the randomness
+/// mixed into a signature
+pub const SEED_LEN: usize = 32;
+
+/// The length, in bytes, of tr, mu, and rho'
+const HASH_LEN: usize = 64;
+
+/// The longest context string a signature can be bound to
+pub const MAX_CONTEXT_LEN: usize = 255;
+
+/// The bits dropped from t in the public key
+const D: usize = 13;
+
+/// The bits kept of each coefficient of t1
+const T1_BITS: usize = 10;
+
+/// The length, in bytes, of a polynomial of t1
+const T1_POLY_LEN: usize = 32 * T1_BITS;
+
+/// The length, in bytes, of a polynomial of t0
+const T0_POLY_LEN: usize = 32 * D;
+
+/// A public key, rho followed by t1
+pub type PublicKey = Vec<u8>;
+
+/// A private key: rho, K, the hash tr of the public key, s1, s2, and t0
+pub type PrivateKey = Secret<Vec<u8>>;
+
+/// A signature: the commitment hash c~, the response z, and the hints
+pub type Signature = Vec<u8>;
+
+/// Why a message couldn't be signed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MlDsaError {
+	/// The private key is not as long as this parameter set calls for
+	WrongLength { expected: usize, actual: usize },
+
+	/// The context string is longer than `MAX_CONTEXT_LEN` bytes
+	ContextTooLong { len: usize }
+}
+
+impl Display for MlDsaError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			MlDsaError::WrongLength { expected, actual } => write!(f, "Expected {:?} bytes, got {:?}", expected, actual),
+			MlDsaError::ContextTooLong { len } => write!(f, "Context string is {:?} bytes, but can be at most {:?}", len, MAX_CONTEXT_LEN)
+		}
+	}
+}
+
+impl std::error::Error for MlDsaError { }
+
+/// One of the parameter sets of FIPS 204
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MlDsaParams {
+	/// The number of rows of A
+	pub k: usize,
+
+	/// The number of columns of A
+	pub l: usize,
+
+	/// The bound on the coefficients of the secret vectors s1 and s2
+	pub eta: i32,
+
+	/// The number of nonzero coefficients of the challenge c
+	pub tau: usize,
+
+	/// The bound on the coefficients of the mask y
+	pub gamma1: i32,
+
+	/// Half of the rounding step for the high bits of w
+	pub gamma2: i32,
+
+	/// The most hints a signature can have
+	pub omega: usize,
+
+	/// The collision strength of c~, in bits
+	pub lambda: usize
+}
+
+/// The number of bits it takes to write x
+const fn bit_len(x: i32) -> usize {
+	(32 - x.leading_zeros()) as usize
+}
+
+impl MlDsaParams {
+
+	pub const ML_DSA_44: MlDsaParams = MlDsaParams { k: 4, l: 4, eta: 2, tau: 39, gamma1: 1 << 17, gamma2: (Q - 1) / 88, omega: 80, lambda: 128 };
+
+	pub const ML_DSA_65: MlDsaParams = MlDsaParams { k: 6, l: 5, eta: 4, tau: 49, gamma1: 1 << 19, gamma2: (Q - 1) / 32, omega: 55, lambda: 192 };
+
+	pub const ML_DSA_87: MlDsaParams = MlDsaParams { k: 8, l: 7, eta: 2, tau: 60, gamma1: 1 << 19, gamma2: (Q - 1) / 32, omega: 75, lambda: 256 };
+
+	/// The largest coefficient c s1 or c s2 can have
+	const fn beta(&self) -> i32 {
+		self.tau as i32 * self.eta
+	}
+
+	/// The bits per coefficient of s1 and s2
+	const fn eta_bits(&self) -> usize {
+		bit_len(2 * self.eta)
+	}
+
+	/// The bits per coefficient of z
+	const fn gamma1_bits(&self) -> usize {
+		1 + bit_len(self.gamma1 - 1)
+	}
+
+	/// The bits per coefficient of w1
+	const fn w1_bits(&self) -> usize {
+		bit_len((Q - 1) / (2 * self.gamma2) - 1)
+	}
+
+	/// The length, in bytes, of c~
+	const fn c_tilde_len(&self) -> usize {
+		self.lambda / 4
+	}
+
+	/// The length, in bytes, of a public key
+	pub const fn public_key_len(&self) -> usize {
+		SEED_LEN + T1_POLY_LEN * self.k
+	}
+
+	/// The length, in bytes, of a private key
+	pub const fn private_key_len(&self) -> usize {
+		2 * SEED_LEN + HASH_LEN + 32 * self.eta_bits() * (self.k + self.l) + T0_POLY_LEN * self.k
+	}
+
+	/// The length, in bytes, of a signature
+	pub const fn signature_len(&self) -> usize {
+		self.c_tilde_len() + 32 * self.gamma1_bits() * self.l + self.omega + self.k
+	}
+}
+
+// MARK: Encoding
+
+/// Packs 256 values of `bits` bits each, least significant bit first (SimpleBitPack)
+fn pack(values: &[i32 ; N], bits: usize, out: &mut [u8]) {
+	let mut buffer: u64 = 0;
+	let mut buffered = 0;
+	let mut bytes = out.iter_mut();
+
+	for v in values {
+		buffer |= (*v as u64) << buffered;
+		buffered += bits;
+
+		while buffered >= 8 {
+			*bytes.next().unwrap() = buffer as u8;
+			buffer >>= 8;
+			buffered -= 8;
+		}
+	}
+
+	buffer.scrub();
+}
+
+/// Unpacks 256 values of `bits` bits each (SimpleBitUnpack)
+fn unpack(bytes: &[u8], bits: usize) -> [i32 ; N] {
+	let mask = (1 << bits) - 1;
+	let mut values = [0 ; N];
+
+	let mut buffer: u64 = 0;
+	let mut buffered = 0;
+	let mut bytes = bytes.iter();
+
+	for v in values.iter_mut() {
+		while buffered < bits {
+			buffer |= (*bytes.next().unwrap() as u64) << buffered;
+			buffered += 8;
+		}
+
+		*v = (buffer & mask) as i32;
+		buffer >>= bits;
+		buffered -= bits;
+	}
+
+	buffer.scrub();
+
+	values
+}
+
+/// Packs 256 values in [-(2^bits - b - 1), b] as b minus each of them (BitPack)
+fn bit_pack(values: &[i32 ; N], b: i32, bits: usize, out: &mut [u8]) {
+	let mut shifted = values.map(|x| b - x);
+	pack(&shifted, bits, out);
+	shifted.scrub();
+}
+
+/// Unpacks 256 values packed with `bit_pack` (BitUnpack)
+fn bit_unpack(bytes: &[u8], b: i32, bits: usize) -> [i32 ; N] {
+	unpack(bytes, bits).map(|x| b - x)
+}
+
+impl MlDsaParams {
+
+	/// Packs the positions of the hints, then how many hints there are up to the end of each
+	/// polynomial (HintBitPack)
+	fn hint_bit_pack(&self, hints: &[[bool ; N]], out: &mut [u8]) {
+		let mut index = 0;
+
+		for (i, h) in hints.iter().enumerate() {
+			for (j, _) in h.iter().enumerate().filter(|(_, hint)| **hint) {
+				out[index] = j as u8;
+				index += 1;
+			}
+
+			out[self.omega + i] = index as u8;
+		}
+	}
+
+	/// Unpacks the hints, or gives None if they aren't packed exactly the way `hint_bit_pack`
+	/// packs them, so that every set of hints has only one encoding (HintBitUnpack)
+	fn hint_bit_unpack(&self, bytes: &[u8]) -> Option<Vec<[bool ; N]>> {
+		let mut hints = vec![[false ; N] ; self.k];
+		let mut index = 0;
+
+		for (i, h) in hints.iter_mut().enumerate() {
+			let end = bytes[self.omega + i] as usize;
+			if end < index || end > self.omega {
+				return None;
+			}
+
+			// the positions in each polynomial have to be in increasing order
+			let first = index;
+			while index < end {
+				if index > first && bytes[index - 1] >= bytes[index] {
+					return None;
+				}
+
+				h[bytes[index] as usize] = true;
+				index += 1;
+			}
+		}
+
+		// and whatever is left over has to be 0
+		if bytes[index..self.omega].iter().any(|b| *b != 0) {
+			return None;
+		}
+
+		Some(hints)
+	}
+
+	/// Packs the high bits of every polynomial of w (w1Encode)
+	fn w1_encode(&self, w1: &[[i32 ; N]]) -> Vec<u8> {
+		let poly_len = 32 * self.w1_bits();
+		let mut bytes = vec![0 ; poly_len * w1.len()];
+
+		for (w, chunk) in w1.iter().zip(bytes.chunks_exact_mut(poly_len)) {
+			pack(w, self.w1_bits(), chunk);
+		}
+
+		bytes
+	}
+}
+
+// MARK: Rounding
+
+/// The representative of a mod q in [-(q - 1) / 2, (q - 1) / 2], without branching on a
+fn centered(a: i32) -> i32 {
+	let a = canonical(a);
+	a - ((((Q - 1) / 2 - a) >> 31) & Q)
+}
+
+/// Whether any coefficient, taken mod +-q, is at least `bound` in absolute value
+fn exceeds(poly: &[i32 ; N], bound: i32) -> bool {
+	poly.iter().any(|x| centered(*x).abs() >= bound)
+}
+
+/// Splits r in [0, q) into r1 2^d + r0, with r0 in (-2^(d - 1), 2^(d - 1)] (Power2Round)
+fn power2round(r: i32) -> (i32, i32) {
+	let r1 = (r + (1 << (D - 1)) - 1) >> D;
+	(r1, r - (r1 << D))
+}
+
+impl MlDsaParams {
+
+	/// Splits r in [0, q) into r1 2 gamma2 + r0, with r0 in (-gamma2, gamma2], except that the
+	/// top of the range wraps around to r1 = 0 (Decompose). This divides by 2 gamma2 with a
+	/// multiplication, so that the time it takes doesn't depend on r.
+	fn decompose(&self, r: i32) -> (i32, i32) {
+		let mut r1 = (r + 127) >> 7;
+
+		if self.gamma2 == (Q - 1) / 32 {
+			r1 = (r1 * 1025 + (1 << 21)) >> 22;
+			r1 &= 15;
+		} else {
+			r1 = (r1 * 11275 + (1 << 23)) >> 24;
+			r1 ^= ((43 - r1) >> 31) & r1;
+		}
+
+		let r0 = r - r1 * 2 * self.gamma2;
+		(r1, r0 - ((((Q - 1) / 2 - r0) >> 31) & Q))
+	}
+
+	fn high_bits(&self, r: i32) -> i32 {
+		self.decompose(r).0
+	}
+
+	fn low_bits(&self, r: i32) -> i32 {
+		self.decompose(r).1
+	}
+
+	/// The high bits of r, moved one step in the direction the low bits point when there is a hint
+	/// (UseHint)
+	fn use_hint(&self, hint: bool, r: i32) -> i32 {
+		let m = (Q - 1) / (2 * self.gamma2);
+		let (r1, r0) = self.decompose(r);
+
+		match (hint, r0 > 0) {
+			(false, _) => r1,
+			(true, true) => (r1 + 1) % m,
+			(true, false) => (r1 - 1).rem_euclid(m)
+		}
+	}
+}
+
+// MARK: Sampling
+
+/// H from FIPS 204: SHAKE256 of every piece of `input` one after another, as many bytes as `out` holds
+fn h(input: &[&[u8]], out: &mut [u8]) {
+	let mut joined = input.concat();
+	Shake::shake256(&joined).squeeze(out);
+	joined.scrub();
+}
+
+/// The transform of a polynomial with coefficients smaller than q in absolute value
+fn to_ntt(poly: &[i32 ; N]) -> [i32 ; N] {
+	let mut transformed = *poly;
+	ntt(&mut transformed);
+	transformed
+}
+
+/// The polynomial whose transform is `transformed`, with every coefficient in [0, q)
+fn from_ntt(transformed: &[i32 ; N]) -> [i32 ; N] {
+	let mut poly = *transformed;
+	inv_ntt(&mut poly);
+	poly.map(canonical)
+}
+
+/// A uniformly random polynomial in the NTT domain, from 23-bit pieces of SHAKE128 of the seed
+/// and both indices, skipping any that are q or more (RejNTTPoly)
+fn rej_ntt_poly(rho: &[u8], s: usize, r: usize) -> [i32 ; N] {
+	let mut xof = Shake::shake128(&[rho, &[s as u8, r as u8]].concat());
+	let mut a = [0 ; N];
+	let mut filled = 0;
+	let mut c = [0u8 ; 3];
+
+	while filled < N {
+		xof.squeeze(&mut c);
+		let d = c[0] as i32 | ((c[1] as i32) << 8) | ((c[2] as i32 & 0x7f) << 16);
+
+		if d < Q {
+			a[filled] = d;
+			filled += 1;
+		}
+	}
+
+	a
+}
+
+/// The K x L matrix A, row by row, where entry (r, s) is RejNTTPoly(rho || s || r) (ExpandA)
+fn expand_a(rho: &[u8], k: usize, l: usize) -> Vec<[i32 ; N]> {
+	(0..(k * l)).map(|index| rej_ntt_poly(rho, index % l, index / l)).collect()
+}
+
+impl MlDsaParams {
+
+	/// A coefficient in [-eta, eta] from half a byte, or None if it has to be skipped to keep
+	/// them uniform (CoeffFromHalfByte)
+	fn coefficient_from_half_byte(&self, b: u8) -> Option<i32> {
+		match self.eta {
+			2 if b < 15 => Some(2 - (b % 5) as i32),
+			4 if b < 9 => Some(4 - b as i32),
+			_ => None
+		}
+	}
+
+	/// A polynomial with coefficients uniform in [-eta, eta], from half-bytes of
+	/// SHAKE256(rho' || index) (RejBoundedPoly)
+	fn rej_bounded_poly(&self, rho_prime: &[u8], index: usize) -> [i32 ; N] {
+		let mut input = [rho_prime, &(index as u16).to_le_bytes()].concat();
+		let mut xof = Shake::shake256(&input);
+		let mut a = [0 ; N];
+		let mut filled = 0;
+		let mut z = [0u8 ; 1];
+
+		while filled < N {
+			xof.squeeze(&mut z);
+
+			for half in [z[0] & 0x0f, z[0] >> 4] {
+				if let (true, Some(c)) = (filled < N, self.coefficient_from_half_byte(half)) {
+					a[filled] = c;
+					filled += 1;
+				}
+			}
+		}
+
+		input.scrub();
+		z.scrub();
+
+		a
+	}
+
+	/// The mask y for attempt kappa, with coefficients in (-gamma1, gamma1] (ExpandMask)
+	fn expand_mask(&self, rho_prime: &[u8], kappa: usize) -> Vec<[i32 ; N]> {
+		let bits = self.gamma1_bits();
+		let mut bytes = vec![0 ; 32 * bits];
+
+		let y = (0..self.l).map(|r| {
+			h(&[rho_prime, &((kappa + r) as u16).to_le_bytes()], &mut bytes);
+			bit_unpack(&bytes, self.gamma1, bits)
+		}).collect();
+
+		bytes.scrub();
+
+		y
+	}
+
+	/// The challenge polynomial, with tau coefficients of +-1 and the rest 0, from SHAKE256 of c~
+	/// (SampleInBall)
+	fn sample_in_ball(&self, c_tilde: &[u8]) -> [i32 ; N] {
+		let mut xof = Shake::shake256(c_tilde);
+
+		let mut sign_bytes = [0u8 ; 8];
+		xof.squeeze(&mut sign_bytes);
+		let signs = u64::from_le_bytes(sign_bytes);
+
+		let mut c = [0 ; N];
+		let mut j = [0u8 ; 1];
+
+		for i in (N - self.tau)..N {
+			xof.squeeze(&mut j);
+			while j[0] as usize > i {
+				xof.squeeze(&mut j);
+			}
+
+			let j = j[0] as usize;
+			c[i] = c[j];
+			c[j] = 1 - 2 * ((signs >> (i + self.tau - N)) & 1) as i32;
+		}
+
+		c
+	}
+}
+
+// MARK: ML-DSA
+
+/// The message as it actually gets signed, with the context string in front of it
+fn format_message(message: &[u8], ctx: &[u8]) -> Result<Vec<u8>, MlDsaError> {
+	if ctx.len() > MAX_CONTEXT_LEN {
+		return Err(MlDsaError::ContextTooLong { len: ctx.len() });
+	}
+
+	Ok([&[0, ctx.len() as u8], ctx, message].concat())
+}
+
+impl MlDsaParams {
+
+	/// Derives a key pair from the seed xi (ML-DSA.KeyGen_internal). This is only for
+	/// known-answer tests and for deriving keys from a seed that has been kept secret.
+	pub fn keygen_internal(&self, xi: &[u8 ; SEED_LEN]) -> (PublicKey, PrivateKey) {
+		let (k, l) = (self.k, self.l);
+		let eta_poly_len = 32 * self.eta_bits();
+
+		// the dimensions go into the hash, so the parameter sets never share keys
+		let mut seeds = [0 ; 2 * SEED_LEN + HASH_LEN];
+		h(&[xi, &[k as u8, l as u8]], &mut seeds);
+		let (rho, rest) = seeds.split_at(SEED_LEN);
+		let (rho_prime, key) = rest.split_at(HASH_LEN);
+
+		let a = expand_a(rho, k, l);
+		let mut s1: Vec<[i32 ; N]> = (0..l).map(|r| self.rej_bounded_poly(rho_prime, r)).collect();
+		let mut s2: Vec<[i32 ; N]> = (0..k).map(|r| self.rej_bounded_poly(rho_prime, l + r)).collect();
+		let mut s1_hat: Vec<[i32 ; N]> = s1.iter().map(to_ntt).collect();
+
+		// t = As1 + s2, split into the high bits t1 that go in the public key, and the low bits t0
+		let mut pk = vec![0 ; self.public_key_len()];
+		pk[0..SEED_LEN].copy_from_slice(rho);
+
+		let mut t0 = vec![[0 ; N] ; k];
+		for (i, chunk) in pk[SEED_LEN..].chunks_exact_mut(T1_POLY_LEN).enumerate() {
+			let mut t = from_ntt(&ntt_dot(a[(i * l)..((i + 1) * l)].iter(), &s1_hat));
+			let mut t1 = [0 ; N];
+
+			for j in 0..N {
+				(t1[j], t0[i][j]) = power2round(canonical(t[j] + s2[i][j]));
+			}
+
+			pack(&t1, T1_BITS, chunk);
+			t.scrub();
+		}
+
+		let mut sk = PrivateKey::new(vec![0 ; self.private_key_len()]);
+		let bytes = sk.expose_mut();
+
+		let (rho_part, rest) = bytes.split_at_mut(SEED_LEN);
+		let (key_part, rest) = rest.split_at_mut(SEED_LEN);
+		let (tr, rest) = rest.split_at_mut(HASH_LEN);
+		let (s1_part, rest) = rest.split_at_mut(eta_poly_len * l);
+		let (s2_part, t0_part) = rest.split_at_mut(eta_poly_len * k);
+
+		rho_part.copy_from_slice(rho);
+		key_part.copy_from_slice(key);
+		h(&[&pk], tr);
+
+		for (s, chunk) in s1.iter().zip(s1_part.chunks_exact_mut(eta_poly_len)) {
+			bit_pack(s, self.eta, self.eta_bits(), chunk);
+		}
+		for (s, chunk) in s2.iter().zip(s2_part.chunks_exact_mut(eta_poly_len)) {
+			bit_pack(s, self.eta, self.eta_bits(), chunk);
+		}
+		for (t, chunk) in t0.iter().zip(t0_part.chunks_exact_mut(T0_POLY_LEN)) {
+			bit_pack(t, 1 << (D - 1), D, chunk);
+		}
+
+		seeds.scrub();
+		s1.scrub();
+		s2.scrub();
+		s1_hat.scrub();
+		t0.scrub();
+
+		(pk, sk)
+	}
+
+	/// Generates a key pair
+	pub fn keygen(&self) -> (PublicKey, PrivateKey) {
+		self.keygen_with_rng(&mut StdRng::from_entropy())
+	}
+
+	/// Generates a key pair, drawing the seed xi from `rng`
+	pub fn keygen_with_rng(&self, rng: &mut (impl RngCore + CryptoRng)) -> (PublicKey, PrivateKey) {
+		let mut xi = [0 ; SEED_LEN];
+		rng.fill_bytes(&mut xi);
+
+		let keys = self.keygen_internal(&xi);
+		xi.scrub();
+
+		keys
+	}
+
+	/// Signs the already formatted message `m_prime`, mixing in `rnd`, which is all zeros for a
+	/// deterministic signature (ML-DSA.Sign_internal). This does no checks on `sk`, and is only
+	/// for known-answer tests.
+	pub fn sign_internal(&self, sk: &PrivateKey, m_prime: &[u8], rnd: &[u8 ; SEED_LEN]) -> Signature {
+		let (k, l) = (self.k, self.l);
+		let eta_poly_len = 32 * self.eta_bits();
+		let (beta, gamma1, gamma2) = (self.beta(), self.gamma1, self.gamma2);
+
+		let bytes = sk.expose();
+		let (rho, rest) = bytes.split_at(SEED_LEN);
+		let (key, rest) = rest.split_at(SEED_LEN);
+		let (tr, rest) = rest.split_at(HASH_LEN);
+		let (s1_part, rest) = rest.split_at(eta_poly_len * l);
+		let (s2_part, t0_part) = rest.split_at(eta_poly_len * k);
+
+		let decode_ntt = |part: &[u8], poly_len: usize, b: i32, bits: usize| -> Vec<[i32 ; N]> {
+			part.chunks_exact(poly_len).map(|chunk| {
+				let mut poly = bit_unpack(chunk, b, bits);
+				let transformed = to_ntt(&poly);
+				poly.scrub();
+				transformed
+			}).collect()
+		};
+
+		let mut s1_hat = decode_ntt(s1_part, eta_poly_len, self.eta, self.eta_bits());
+		let mut s2_hat = decode_ntt(s2_part, eta_poly_len, self.eta, self.eta_bits());
+		let mut t0_hat = decode_ntt(t0_part, T0_POLY_LEN, 1 << (D - 1), D);
+		let a = expand_a(rho, k, l);
+
+		let mut mu = [0 ; HASH_LEN];
+		h(&[tr, m_prime], &mut mu);
+
+		// the seed of every mask, from the key, the randomness, and the message
+		let mut rho_prime = [0 ; HASH_LEN];
+		h(&[key, rnd, &mu], &mut rho_prime);
+
+		let mut c_tilde = vec![0 ; self.c_tilde_len()];
+
+		// keep trying fresh masks until one doesn't leak anything about the key
+		for kappa in (0..).step_by(l) {
+			let mut y = self.expand_mask(&rho_prime, kappa);
+			let mut y_hat: Vec<[i32 ; N]> = y.iter().map(to_ntt).collect();
+
+			// the commitment w = Ay, and the challenge from its high bits
+			let mut w: Vec<[i32 ; N]> = (0..k).map(|i| from_ntt(&ntt_dot(a[(i * l)..((i + 1) * l)].iter(), &y_hat))).collect();
+			let w1: Vec<[i32 ; N]> = w.iter().map(|poly| poly.map(|x| self.high_bits(x))).collect();
+
+			h(&[&mu, &self.w1_encode(&w1)], &mut c_tilde);
+			let c_hat = to_ntt(&self.sample_in_ball(&c_tilde));
+
+			// the response z = y + c s1, and r = w - c s2, whose high bits the verifier will see
+			let mut z: Vec<[i32 ; N]> = y.iter().zip(&s1_hat).map(|(y, s)| {
+				let cs1 = from_ntt(&pointwise_mul(&c_hat, s));
+				std::array::from_fn(|j| centered(y[j] + cs1[j]))
+			}).collect();
+
+			let mut r: Vec<[i32 ; N]> = w.iter().zip(&s2_hat).map(|(w, s)| {
+				let cs2 = from_ntt(&pointwise_mul(&c_hat, s));
+				std::array::from_fn(|j| canonical(w[j] - cs2[j]))
+			}).collect();
+
+			let mut signature = None;
+
+			let leaks = z.iter().any(|poly| exceeds(poly, gamma1 - beta))
+				|| r.iter().any(|poly| poly.iter().any(|x| self.low_bits(*x).abs() >= gamma2 - beta));
+
+			if !leaks {
+				let mut ct0: Vec<[i32 ; N]> = t0_hat.iter().map(|t| from_ntt(&pointwise_mul(&c_hat, t))).collect();
+
+				// hints for wherever adding c t0 back would change the high bits of r
+				let hints: Vec<[bool ; N]> = r.iter().zip(&ct0).map(|(r, ct0)| {
+					std::array::from_fn(|j| self.high_bits(canonical(r[j] + ct0[j])) != self.high_bits(r[j]))
+				}).collect();
+
+				let count: usize = hints.iter().map(|h| h.iter().filter(|hint| **hint).count()).sum();
+
+				if !ct0.iter().any(|poly| exceeds(poly, gamma2)) && count <= self.omega {
+					signature = Some(self.encode_signature(&c_tilde, &z, &hints));
+				}
+
+				ct0.scrub();
+			}
+
+			y.scrub();
+			y_hat.scrub();
+			w.scrub();
+			z.scrub();
+			r.scrub();
+
+			if let Some(signature) = signature {
+				s1_hat.scrub();
+				s2_hat.scrub();
+				t0_hat.scrub();
+				rho_prime.scrub();
+
+				return signature;
+			}
+		}
+
+		unreachable!()
+	}
+
+	/// Packs c~, z, and the hints into a signature (sigEncode)
+	fn encode_signature(&self, c_tilde: &[u8], z: &[[i32 ; N]], hints: &[[bool ; N]]) -> Signature {
+		let z_poly_len = 32 * self.gamma1_bits();
+
+		let mut signature = vec![0 ; self.signature_len()];
+		let (c_part, rest) = signature.split_at_mut(self.c_tilde_len());
+		let (z_part, h_part) = rest.split_at_mut(z_poly_len * self.l);
+
+		c_part.copy_from_slice(c_tilde);
+		for (poly, chunk) in z.iter().zip(z_part.chunks_exact_mut(z_poly_len)) {
+			bit_pack(poly, self.gamma1, self.gamma1_bits(), chunk);
+		}
+		self.hint_bit_pack(hints, h_part);
+
+		signature
+	}
+
+	/// Checks the signature `sig` on the already formatted message `m_prime` (ML-DSA.Verify_internal)
+	pub fn verify_internal(&self, pk: &[u8], m_prime: &[u8], sig: &[u8]) -> bool {
+		if pk.len() != self.public_key_len() || sig.len() != self.signature_len() {
+			return false;
+		}
+
+		let l = self.l;
+		let z_poly_len = 32 * self.gamma1_bits();
+
+		let (rho, t1_part) = pk.split_at(SEED_LEN);
+		let (c_tilde, rest) = sig.split_at(self.c_tilde_len());
+		let (z_part, h_part) = rest.split_at(z_poly_len * l);
+
+		let z: Vec<[i32 ; N]> = z_part.chunks_exact(z_poly_len).map(|chunk| bit_unpack(chunk, self.gamma1, self.gamma1_bits())).collect();
+		let hints = match self.hint_bit_unpack(h_part) {
+			Some(hints) => hints,
+			None => return false
+		};
+
+		if z.iter().any(|poly| exceeds(poly, self.gamma1 - self.beta())) {
+			return false;
+		}
+
+		let a = expand_a(rho, self.k, l);
+
+		let mut tr = [0 ; HASH_LEN];
+		h(&[pk], &mut tr);
+		let mut mu = [0 ; HASH_LEN];
+		h(&[&tr, m_prime], &mut mu);
+
+		let c_hat = to_ntt(&self.sample_in_ball(c_tilde));
+		let z_hat: Vec<[i32 ; N]> = z.iter().map(to_ntt).collect();
+
+		// Az - c t1 2^d is w - c s2 + c t0, and the hints fix its high bits up to those of w
+		let w1: Vec<[i32 ; N]> = t1_part.chunks_exact(T1_POLY_LEN).zip(hints).enumerate().map(|(i, (chunk, h))| {
+			let t1_hat = to_ntt(&unpack(chunk, T1_BITS).map(|x| x << D));
+			let az = ntt_dot(a[(i * l)..((i + 1) * l)].iter(), &z_hat);
+			let ct1 = pointwise_mul(&c_hat, &t1_hat);
+
+			let w_approx = from_ntt(&std::array::from_fn(|j| reduce32(az[j] - ct1[j])));
+			std::array::from_fn(|j| self.use_hint(h[j], w_approx[j]))
+		}).collect();
+
+		let mut expected = vec![0 ; self.c_tilde_len()];
+		h(&[&mu, &self.w1_encode(&w1)], &mut expected);
+
+		expected == c_tilde
+	}
+
+	/// Signs `message`, bound to the context string `ctx`, with fresh randomness mixed in
+	pub fn sign(&self, sk: &PrivateKey, message: &[u8], ctx: &[u8]) -> Result<Signature, MlDsaError> {
+		self.sign_with_rng(sk, message, ctx, &mut StdRng::from_entropy())
+	}
+
+	/// Signs `message`, bound to the context string `ctx`, drawing the randomness from `rng`
+	pub fn sign_with_rng(&self, sk: &PrivateKey, message: &[u8], ctx: &[u8], rng: &mut (impl RngCore + CryptoRng)) -> Result<Signature, MlDsaError> {
+		let mut rnd = [0 ; SEED_LEN];
+		rng.fill_bytes(&mut rnd);
+
+		let signature = self.sign_with_rnd(sk, message, ctx, &rnd);
+		rnd.scrub();
+
+		signature
+	}
+
+	/// Signs `message`, bound to the context string `ctx`, with no randomness, so that the same
+	/// message always gets the same signature
+	pub fn sign_deterministic(&self, sk: &PrivateKey, message: &[u8], ctx: &[u8]) -> Result<Signature, MlDsaError> {
+		self.sign_with_rnd(sk, message, ctx, &[0 ; SEED_LEN])
+	}
+
+	/// Checks `sk` and `ctx`, and then signs
+	fn sign_with_rnd(&self, sk: &PrivateKey, message: &[u8], ctx: &[u8], rnd: &[u8 ; SEED_LEN]) -> Result<Signature, MlDsaError> {
+		let expected = self.private_key_len();
+		if sk.expose().len() != expected {
+			return Err(MlDsaError::WrongLength { expected, actual: sk.expose().len() });
+		}
+
+		Ok(self.sign_internal(sk, &format_message(message, ctx)?, rnd))
+	}
+
+	/// Checks that `sig` is a signature on `message` with the context string `ctx`, under `pk`.
+	///
+	/// A public key or signature of the wrong length, or a context string that is too long, just
+	/// doesn't verify.
+	pub fn verify(&self, pk: &[u8], message: &[u8], ctx: &[u8], sig: &[u8]) -> bool {
+		match format_message(message, ctx) {
+			Ok(m_prime) => self.verify_internal(pk, &m_prime, sig),
+			Err(_) => false
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	use super::*;
+
+	const ALL: [MlDsaParams ; 3] = [MlDsaParams::ML_DSA_44, MlDsaParams::ML_DSA_65, MlDsaParams::ML_DSA_87];
+
+	#[test]
+	fn test_sizes() {
+		// the byte lengths listed in table 2 of FIPS 204
+		let expected = [(1312, 2560, 2420), (1952, 4032, 3309), (2592, 4896, 4627)];
+
+		for (params, (pk, sk, sig)) in ALL.iter().zip(expected) {
+			assert_eq!(params.public_key_len(), pk);
+			assert_eq!(params.private_key_len(), sk);
+			assert_eq!(params.signature_len(), sig);
+		}
+	}
+
+	#[test]
+	fn test_rounding() {
+		for params in [MlDsaParams::ML_DSA_44, MlDsaParams::ML_DSA_65] {
+			let alpha = 2 * params.gamma2;
+
+			// the multiplication really does divide, everywhere, including where r1 wraps around
+			for r in 0..Q {
+				let mut r0 = r % alpha;
+				if r0 > params.gamma2 {
+					r0 -= alpha;
+				}
+
+				let expected = if r - r0 == Q - 1 { (0, r0 - 1) } else { ((r - r0) / alpha, r0) };
+				assert_eq!(params.decompose(r), expected, "r = {:?}", r);
+			}
+		}
+
+		for r in 0..Q {
+			let (r1, r0) = power2round(r);
+			assert!(r0 > -(1 << (D - 1)) && r0 <= 1 << (D - 1));
+			assert_eq!((r1 << D) + r0, r);
+		}
+	}
+
+	#[test]
+	fn test_hints() {
+		let mut rng = rand::thread_rng();
+
+		for params in ALL {
+			// a hint made for a small change always recovers the high bits after the change
+			for _ in 0..10000 {
+				let r = rng.gen_range(0..Q);
+				let z = rng.gen_range(-params.gamma2..=params.gamma2);
+				let hint = params.high_bits(r) != params.high_bits(canonical(r + z));
+
+				assert_eq!(params.use_hint(hint, r), params.high_bits(canonical(r + z)));
+			}
+		}
+	}
+
+	#[test]
+	fn test_encoding() {
+		let mut rng = rand::thread_rng();
+
+		for bits in [3, 4, 10, 13, 18, 20] {
+			let values: [i32 ; N] = std::array::from_fn(|_| rng.gen_range(0..(1 << bits)));
+			let mut bytes = vec![0 ; 32 * bits];
+
+			pack(&values, bits, &mut bytes);
+			assert_eq!(unpack(&bytes, bits), values);
+
+			let b = 1 << (bits - 1);
+			let signed = values.map(|x| b - x);
+			bit_pack(&signed, b, bits, &mut bytes);
+			assert_eq!(bit_unpack(&bytes, b, bits), signed);
+		}
+
+		// hints only have one encoding
+		let params = MlDsaParams::ML_DSA_44;
+		let mut hints = vec![[false ; N] ; params.k];
+		hints[0][3] = true;
+		hints[0][200] = true;
+		hints[2][0] = true;
+
+		let mut bytes = vec![0 ; params.omega + params.k];
+		params.hint_bit_pack(&hints, &mut bytes);
+		assert_eq!(params.hint_bit_unpack(&bytes), Some(hints));
+
+		let mut out_of_order = bytes.clone();
+		out_of_order.swap(0, 1);
+		assert_eq!(params.hint_bit_unpack(&out_of_order), None);
+
+		let mut trailing = bytes.clone();
+		trailing[params.omega - 1] = 1;
+		assert_eq!(params.hint_bit_unpack(&trailing), None);
+	}
+
+	#[test]
+	fn test_sample_in_ball() {
+		for params in ALL {
+			let c = params.sample_in_ball(&[0x49 ; 64][..params.c_tilde_len()]);
+			assert_eq!(c.iter().filter(|x| **x != 0).count(), params.tau);
+			assert!(c.iter().all(|x| x.abs() <= 1));
+		}
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let mut rng = rand::thread_rng();
+
+		for params in ALL {
+			for _ in 0..4 {
+				let (pk, sk) = params.keygen();
+				assert_eq!(pk.len(), params.public_key_len());
+				assert_eq!(sk.expose().len(), params.private_key_len());
+
+				let mut message = vec![0 ; rng.gen_range(0..1000)];
+				rng.fill(&mut message[..]);
+
+				let sig = params.sign(&sk, &message, b"").unwrap();
+				assert_eq!(sig.len(), params.signature_len());
+				assert!(params.verify(&pk, &message, b"", &sig));
+
+				let sig = params.sign_deterministic(&sk, &message, b"context").unwrap();
+				assert!(params.verify(&pk, &message, b"context", &sig));
+				assert_eq!(params.sign_deterministic(&sk, &message, b"context").unwrap(), sig);
+			}
+		}
+	}
+
+	#[test]
+	fn test_forgeries() {
+		let params = MlDsaParams::ML_DSA_65;
+		let (pk, sk) = params.keygen_with_rng(&mut StdRng::seed_from_u64(0x49));
+		let message = b"attack at dawn";
+		let sig = params.sign(&sk, message, b"ctx").unwrap();
+
+		assert!(params.verify(&pk, message, b"ctx", &sig));
+
+		// a different message, context, key, or any changed bit of the signature
+		assert!(!params.verify(&pk, b"attack at dusk", b"ctx", &sig));
+		assert!(!params.verify(&pk, message, b"", &sig));
+		assert!(!params.verify(&params.keygen().0, message, b"ctx", &sig));
+
+		for i in [0, params.c_tilde_len(), params.signature_len() / 2, params.signature_len() - params.k - 1] {
+			let mut tampered = sig.clone();
+			tampered[i] ^= 1;
+			assert!(!params.verify(&pk, message, b"ctx", &tampered), "flipping a bit of byte {:?} still verified", i);
+		}
+
+		assert!(!params.verify(&pk, message, b"ctx", &sig[1..]));
+		assert!(!params.verify(&pk[1..], message, b"ctx", &sig));
+	}
+
+	#[test]
+	fn test_errors() {
+		let params = MlDsaParams::ML_DSA_44;
+		let (pk, sk) = params.keygen();
+
+		let long = [0 ; MAX_CONTEXT_LEN + 1];
+		assert_eq!(params.sign(&sk, b"", &long), Err(MlDsaError::ContextTooLong { len: 256 }));
+		assert!(!params.verify(&pk, b"", &long, &params.sign(&sk, b"", &long[1..]).unwrap()));
+
+		let short = PrivateKey::new(sk.expose()[1..].to_vec());
+		assert_eq!(params.sign(&short, b"", b""), Err(MlDsaError::WrongLength { expected: 2560, actual: 2559 }));
+	}
+
+	#[test]
+	fn test_hedged_signatures_differ() {
+		let params = MlDsaParams::ML_DSA_44;
+		let (pk, sk) = params.keygen();
+
+		let first = params.sign(&sk, b"same message", b"").unwrap();
+		let second = params.sign(&sk, b"same message", b"").unwrap();
+
+		assert_ne!(first, second);
+		assert!(params.verify(&pk, b"same message", b"", &first) && params.verify(&pk, b"same message", b"", &second));
+	}
+}
@@ -0,0 +1,593 @@
+//
+// ML-KEM, the module-lattice key encapsulation mechanism of FIPS 203, in all three of its
+// parameter sets
+//
+
+use std::fmt::Display;
+
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+
+use crate::ntt::{canonical, ntt_dot, Poly, N, Q};
+use crate::secret::{eq_mask, Scrub, Secret};
+use crate::sha3::{sha3_256, sha3_512, shake256, Shake};
+
+/// The length, in bytes, of the seeds d, z and m, and of the shared secret
+pub const SEED_LEN: usize = 32;
+
+/// The length, in bytes, of a shared secret
+pub const SHARED_SECRET_LEN: usize = 32;
+
+/// The length, in bytes, of a polynomial with 12 bits per coefficient
+const ENCODED_POLY_LEN: usize = 12 * N / 8;
+
+/// floor(2^36 / q) + 1, so that floor(n / q) is (n * this) >> 36 for every n that comes up
+const DIV_Q_MULTIPLIER: u64 = (1 << 36) / Q as u64 + 1;
+
+/// An encapsulation key, t in the NTT domain followed by the seed rho of A
+pub type EncapsulationKey = Vec<u8>;
+
+/// A decapsulation key: s in the NTT domain, the encapsulation key, its hash, and the secret
+/// z used for implicit rejection
+pub type DecapsulationKey = Secret<Vec<u8>>;
+
+/// A ciphertext, u compressed to du bits per coefficient followed by v compressed to dv bits
+pub type Ciphertext = Vec<u8>;
+
+/// The secret both sides end up with
+pub type SharedSecret = Secret<[u8 ; SHARED_SECRET_LEN]>;
+
+/// Why a key or ciphertext was rejected by the input checks of FIPS 203
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MlKemError {
+	/// The input is not as long as this parameter set calls for
+	WrongLength { expected: usize, actual: usize },
+
+	/// A coefficient of the encapsulation key is q or more, so it fails the modulus check
+	UnreducedKey,
+
+	/// The hash in the decapsulation key doesn't match the encapsulation key next to it
+	HashMismatch
+}
+
+impl Display for MlKemError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			MlKemError::WrongLength { expected, actual } => write!(f, "Expected {:?} bytes, got {:?}", expected, actual),
+			MlKemError::UnreducedKey => write!(f, "Encapsulation key has a coefficient that isn't reduced mod q"),
+			MlKemError::HashMismatch => write!(f, "Decapsulation key has the wrong hash of its encapsulation key")
+		}
+	}
+}
+
+impl std::error::Error for MlKemError { }
+
+/// One of the parameter sets of FIPS 203
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MlKemParams {
+	/// The module rank
+	pub k: usize,
+
+	/// The binomial parameter of the secret and the key errors
+	pub eta1: u32,
+
+	/// The binomial parameter of the encryption errors
+	pub eta2: u32,
+
+	/// The bits kept of each coefficient of u
+	pub du: u32,
+
+	/// The bits kept of each coefficient of v
+	pub dv: u32
+}
+
+impl MlKemParams {
+
+	pub const ML_KEM_512: MlKemParams = MlKemParams { k: 2, eta1: 3, eta2: 2, du: 10, dv: 4 };
+
+	pub const ML_KEM_768: MlKemParams = MlKemParams { k: 3, eta1: 2, eta2: 2, du: 10, dv: 4 };
+
+	pub const ML_KEM_1024: MlKemParams = MlKemParams { k: 4, eta1: 2, eta2: 2, du: 11, dv: 5 };
+
+	/// The length, in bytes, of an encapsulation key
+	pub const fn encapsulation_key_len(&self) -> usize {
+		ENCODED_POLY_LEN * self.k + SEED_LEN
+	}
+
+	/// The length, in bytes, of a decapsulation key
+	pub const fn decapsulation_key_len(&self) -> usize {
+		ENCODED_POLY_LEN * self.k + self.encapsulation_key_len() + 2 * SEED_LEN
+	}
+
+	/// The length, in bytes, of a ciphertext
+	pub const fn ciphertext_len(&self) -> usize {
+		32 * (self.du as usize * self.k + self.dv as usize)
+	}
+}
+
+// MARK: Encoding
+
+/// Packs 256 values of d bits each, least significant bit first (ByteEncode_d)
+fn byte_encode(values: &[i16 ; N], d: u32, out: &mut [u8]) {
+	let mut buffer: u32 = 0;
+	let mut buffered = 0;
+	let mut bytes = out.iter_mut();
+
+	for v in values {
+		buffer |= (*v as u32) << buffered;
+		buffered += d;
+
+		while buffered >= 8 {
+			*bytes.next().unwrap() = buffer as u8;
+			buffer >>= 8;
+			buffered -= 8;
+		}
+	}
+
+	buffer.scrub();
+}
+
+/// Unpacks 256 values of d bits each (ByteDecode_d)
+fn byte_decode(bytes: &[u8], d: u32) -> [i16 ; N] {
+	let mask = (1 << d) - 1;
+	let mut values = [0 ; N];
+
+	let mut buffer: u32 = 0;
+	let mut buffered = 0;
+	let mut bytes = bytes.iter();
+
+	for v in values.iter_mut() {
+		while buffered < d {
+			buffer |= (*bytes.next().unwrap() as u32) << buffered;
+			buffered += 8;
+		}
+
+		*v = (buffer & mask) as i16;
+		buffer >>= d;
+		buffered -= d;
+	}
+
+	buffer.scrub();
+
+	values
+}
+
+/// Decodes 12-bit values and reduces them mod q, which only changes anything for keys that
+/// would fail the modulus check
+fn decode_reduced(bytes: &[u8]) -> [i16 ; N] {
+	byte_decode(bytes, 12).map(canonical)
+}
+
+/// round(2^d x / q) mod 2^d, for x in [0, q), dividing with a multiplication so that the time this
+/// takes doesn't depend on x
+fn compress(x: i16, d: u32) -> i16 {
+	let n = ((x as u64) << d) + Q as u64 / 2;
+	(((n * DIV_Q_MULTIPLIER) >> 36) & ((1 << d) - 1)) as i16
+}
+
+/// round(q y / 2^d)
+fn decompress(y: i16, d: u32) -> i16 {
+	((y as u32 * Q as u32 + (1 << (d - 1))) >> d) as i16
+}
+
+fn to_poly(values: &[i16 ; N]) -> Poly {
+	Poly::from_coefficients(&values.map(|x| x as i64))
+}
+
+// MARK: Sampling
+
+/// A uniformly random polynomial in the NTT domain, from 12-bit pieces of SHAKE128 of the
+/// seed and both indices, skipping any that are q or more (SampleNTT)
+fn sample_ntt(rho: &[u8], j: usize, i: usize) -> [i16 ; N] {
+	let mut xof = Shake::shake128(&[rho, &[j as u8, i as u8]].concat());
+	let mut a = [0 ; N];
+	let mut filled = 0;
+	let mut c = [0u8 ; 3];
+
+	while filled < N {
+		xof.squeeze(&mut c);
+		let d1 = c[0] as i16 | ((c[1] as i16 & 0xf) << 8);
+		let d2 = (c[1] as i16 >> 4) | ((c[2] as i16) << 4);
+
+		for d in [d1, d2] {
+			if d < Q && filled < N {
+				a[filled] = d;
+				filled += 1;
+			}
+		}
+	}
+
+	a
+}
+
+/// The K x K matrix A, row by row, where entry (i, j) is SampleNTT(rho || j || i)
+fn expand_matrix(rho: &[u8], k: usize) -> Vec<[i16 ; N]> {
+	(0..(k * k)).map(|index| sample_ntt(rho, index % k, index / k)).collect()
+}
+
+/// A polynomial with centered binomial coefficients, drawn from the bits of
+/// SHAKE256(seed || nonce) (SamplePolyCBD with PRF)
+fn sample_cbd(eta: u32, seed: &[u8], nonce: usize) -> Poly {
+	let eta = eta as usize;
+
+	let mut input = [seed, &[nonce as u8]].concat();
+	let mut bytes = vec![0 ; 64 * eta];
+	Shake::shake256(&input).squeeze(&mut bytes);
+
+	let bit = |i: usize| ((bytes[i / 8] >> (i % 8)) & 1) as i64;
+
+	let mut coefficients = [0 ; N];
+	for (i, c) in coefficients.iter_mut().enumerate() {
+		let x: i64 = (0..eta).map(|j| bit(2 * i * eta + j)).sum();
+		let y: i64 = (0..eta).map(|j| bit(2 * i * eta + eta + j)).sum();
+		*c = x - y;
+	}
+
+	let poly = Poly::from_coefficients(&coefficients);
+
+	input.scrub();
+	bytes.scrub();
+	coefficients.scrub();
+
+	poly
+}
+
+// MARK: K-PKE
+
+impl MlKemParams {
+
+	/// The bytes holding the polynomials of the encapsulation key, before rho
+	fn t_len(&self) -> usize {
+		ENCODED_POLY_LEN * self.k
+	}
+
+	/// Makes the encryption key and the decryption key of the inner public-key scheme from the seed d
+	fn pke_keygen(&self, d: &[u8 ; SEED_LEN]) -> (EncapsulationKey, Secret<Vec<u8>>) {
+		let k = self.k;
+
+		// the rank goes into the hash, so the parameter sets never share keys
+		let mut input = [d.as_slice(), &[k as u8]].concat();
+		let mut g = sha3_512(&input);
+		input.scrub();
+		let (rho, sigma) = g.split_at(SEED_LEN);
+
+		let a = expand_matrix(rho, k);
+		let mut s_hat: Vec<[i16 ; N]> = (0..k).map(|i| sample_cbd(self.eta1, sigma, i).ntt()).collect();
+		let mut e_hat: Vec<[i16 ; N]> = (0..k).map(|i| sample_cbd(self.eta1, sigma, k + i).ntt()).collect();
+
+		// t = As + e, all in the NTT domain
+		let mut ek = vec![0 ; self.encapsulation_key_len()];
+		for (i, chunk) in ek[0..self.t_len()].chunks_exact_mut(ENCODED_POLY_LEN).enumerate() {
+			let product = ntt_dot(a[(i * k)..((i + 1) * k)].iter(), &s_hat);
+			let t_hat = std::array::from_fn(|c| canonical(product[c] + e_hat[i][c]));
+			byte_encode(&t_hat, 12, chunk);
+		}
+		ek[self.t_len()..].copy_from_slice(rho);
+
+		let mut dk = Secret::new(vec![0 ; self.t_len()]);
+		for (s, chunk) in s_hat.iter().zip(dk.expose_mut().chunks_exact_mut(ENCODED_POLY_LEN)) {
+			let mut reduced = s.map(canonical);
+			byte_encode(&reduced, 12, chunk);
+			reduced.scrub();
+		}
+
+		g.scrub();
+		s_hat.scrub();
+		e_hat.scrub();
+
+		(ek, dk)
+	}
+
+	/// Encrypts the 256 bits of `m` under `ek`, with every random choice drawn from the seed `r`
+	fn pke_encrypt(&self, ek: &[u8], m: &[u8 ; SEED_LEN], r: &[u8]) -> Ciphertext {
+		let k = self.k;
+		let (du, dv) = (self.du, self.dv);
+
+		let t_hat: Vec<[i16 ; N]> = ek[0..self.t_len()].chunks_exact(ENCODED_POLY_LEN).map(decode_reduced).collect();
+		let a = expand_matrix(&ek[self.t_len()..], k);
+
+		let mut y_hat: Vec<[i16 ; N]> = (0..k).map(|i| sample_cbd(self.eta1, r, i).ntt()).collect();
+
+		let mut c = vec![0 ; self.ciphertext_len()];
+		let (c1, c2) = c.split_at_mut(32 * du as usize * k);
+
+		// u = A^T y + e1, going down column i of A for entry i
+		for (i, chunk) in c1.chunks_exact_mut(32 * du as usize).enumerate() {
+			let mut e1 = sample_cbd(self.eta2, r, k + i);
+			let u = Poly::from_ntt(&ntt_dot((0..k).map(|j| &a[j * k + i]), &y_hat)) + e1;
+			byte_encode(&u.coefficients().map(|x| compress(x, du)), du, chunk);
+			e1.scrub();
+		}
+
+		// v = t^T y + e2 + the message, with each bit decompressed to 0 or round(q / 2)
+		let mut e2 = sample_cbd(self.eta2, r, 2 * k);
+		let mut mu = to_poly(&byte_decode(m, 1).map(|bit| decompress(bit, 1)));
+		let v = Poly::from_ntt(&ntt_dot(t_hat.iter(), &y_hat)) + e2 + mu;
+		byte_encode(&v.coefficients().map(|x| compress(x, dv)), dv, c2);
+
+		y_hat.scrub();
+		e2.scrub();
+		mu.scrub();
+
+		c
+	}
+
+	/// Decrypts the 256 bits in `c` with the inner decryption key
+	fn pke_decrypt(&self, dk_pke: &[u8], c: &[u8]) -> [u8 ; SEED_LEN] {
+		let (du, dv) = (self.du, self.dv);
+		let (c1, c2) = c.split_at(32 * du as usize * self.k);
+
+		let u_hat: Vec<[i16 ; N]> = c1.chunks_exact(32 * du as usize)
+			.map(|chunk| to_poly(&byte_decode(chunk, du).map(|y| decompress(y, du))).ntt())
+			.collect();
+		let v = to_poly(&byte_decode(c2, dv).map(|y| decompress(y, dv)));
+
+		let mut s_hat: Vec<[i16 ; N]> = dk_pke.chunks_exact(ENCODED_POLY_LEN).map(decode_reduced).collect();
+
+		// v - s^T u leaves the message plus a small error
+		let mut w = v - Poly::from_ntt(&ntt_dot(s_hat.iter(), &u_hat));
+
+		let mut m = [0 ; SEED_LEN];
+		byte_encode(&w.coefficients().map(|x| compress(x, 1)), 1, &mut m);
+
+		s_hat.scrub();
+		w.scrub();
+
+		m
+	}
+}
+
+// MARK: ML-KEM
+
+impl MlKemParams {
+
+	/// Derives a key pair from the seeds d and z (ML-KEM.KeyGen_internal). This is only for
+	/// known-answer tests and for deriving keys from a seed that has been kept secret.
+	pub fn keygen_internal(&self, d: &[u8 ; SEED_LEN], z: &[u8 ; SEED_LEN]) -> (EncapsulationKey, DecapsulationKey) {
+		let (ek, dk_pke) = self.pke_keygen(d);
+
+		let mut dk = DecapsulationKey::new(vec![0 ; self.decapsulation_key_len()]);
+		let bytes = dk.expose_mut();
+
+		let (dk_part, rest) = bytes.split_at_mut(self.t_len());
+		let (ek_part, rest) = rest.split_at_mut(self.encapsulation_key_len());
+		let (ek_hash, z_part) = rest.split_at_mut(SEED_LEN);
+
+		dk_part.copy_from_slice(dk_pke.expose());
+		ek_part.copy_from_slice(&ek);
+		ek_hash.copy_from_slice(&sha3_256(&ek));
+		z_part.copy_from_slice(z);
+
+		(ek, dk)
+	}
+
+	/// Encapsulates the shared secret that comes from the message m (ML-KEM.Encaps_internal).
+	/// This does no checks on `ek`, and is only for known-answer tests.
+	pub fn encaps_internal(&self, ek: &[u8], m: &[u8 ; SEED_LEN]) -> (Ciphertext, SharedSecret) {
+		let mut input = [m.as_slice(), &sha3_256(ek)].concat();
+		let mut g = sha3_512(&input);
+		input.scrub();
+
+		let c = self.pke_encrypt(ek, m, &g[SEED_LEN..]);
+
+		let mut shared = [0 ; SHARED_SECRET_LEN];
+		shared.copy_from_slice(&g[0..SEED_LEN]);
+		g.scrub();
+
+		(c, Secret::new(shared))
+	}
+
+	/// Generates a key pair
+	pub fn keygen(&self) -> (EncapsulationKey, DecapsulationKey) {
+		self.keygen_with_rng(&mut StdRng::from_entropy())
+	}
+
+	/// Generates a key pair, drawing d and then z from `rng`
+	pub fn keygen_with_rng(&self, rng: &mut (impl RngCore + CryptoRng)) -> (EncapsulationKey, DecapsulationKey) {
+		let mut d = [0 ; SEED_LEN];
+		let mut z = [0 ; SEED_LEN];
+		rng.fill_bytes(&mut d);
+		rng.fill_bytes(&mut z);
+
+		let keys = self.keygen_internal(&d, &z);
+
+		d.scrub();
+		z.scrub();
+
+		keys
+	}
+
+	/// The input check on an encapsulation key: it has to be the right length, and re-encoding
+	/// its coefficients has to give back the same bytes, so none of them can be q or more
+	pub fn check_encapsulation_key(&self, ek: &[u8]) -> Result<(), MlKemError> {
+		let expected = self.encapsulation_key_len();
+		if ek.len() != expected {
+			return Err(MlKemError::WrongLength { expected, actual: ek.len() });
+		}
+
+		for chunk in ek[0..self.t_len()].chunks_exact(ENCODED_POLY_LEN) {
+			if byte_decode(chunk, 12).iter().any(|x| *x >= Q) {
+				return Err(MlKemError::UnreducedKey);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The input check on a decapsulation key: it has to be the right length, and the hash
+	/// inside it has to match the encapsulation key inside it
+	pub fn check_decapsulation_key(&self, dk: &DecapsulationKey) -> Result<(), MlKemError> {
+		let bytes = dk.expose();
+
+		let expected = self.decapsulation_key_len();
+		if bytes.len() != expected {
+			return Err(MlKemError::WrongLength { expected, actual: bytes.len() });
+		}
+
+		let ek_start = self.t_len();
+		let hash_start = ek_start + self.encapsulation_key_len();
+
+		if sha3_256(&bytes[ek_start..hash_start]) != bytes[hash_start..(hash_start + SEED_LEN)] {
+			return Err(MlKemError::HashMismatch);
+		}
+
+		Ok(())
+	}
+
+	/// Checks `ek`, then makes up a fresh shared secret and encapsulates it
+	pub fn encaps(&self, ek: &[u8]) -> Result<(Ciphertext, SharedSecret), MlKemError> {
+		self.encaps_with_rng(ek, &mut StdRng::from_entropy())
+	}
+
+	/// Checks `ek`, then makes up a fresh shared secret from `rng` and encapsulates it
+	pub fn encaps_with_rng(&self, ek: &[u8], rng: &mut (impl RngCore + CryptoRng)) -> Result<(Ciphertext, SharedSecret), MlKemError> {
+		self.check_encapsulation_key(ek)?;
+
+		let mut m = [0 ; SEED_LEN];
+		rng.fill_bytes(&mut m);
+
+		let encapsulated = self.encaps_internal(ek, &m);
+		m.scrub();
+
+		Ok(encapsulated)
+	}
+
+	/// Checks `dk` and the length of `c`, and then recovers the shared secret.
+	///
+	/// A ciphertext that doesn't re-encrypt to itself gives a pseudorandom secret derived from z
+	/// rather than an error, and the work done is the same either way.
+	pub fn decaps(&self, dk: &DecapsulationKey, c: &[u8]) -> Result<SharedSecret, MlKemError> {
+		let expected = self.ciphertext_len();
+		if c.len() != expected {
+			return Err(MlKemError::WrongLength { expected, actual: c.len() });
+		}
+
+		self.check_decapsulation_key(dk)?;
+
+		let bytes = dk.expose();
+		let (dk_pke, rest) = bytes.split_at(self.t_len());
+		let (ek, rest) = rest.split_at(self.encapsulation_key_len());
+		let (ek_hash, z) = rest.split_at(SEED_LEN);
+
+		let mut m = self.pke_decrypt(dk_pke, c);
+		let mut g_input = [m.as_slice(), ek_hash].concat();
+		let mut g = sha3_512(&g_input);
+		let mut rejected_input = [z, c].concat();
+		let mut rejected: [u8 ; SHARED_SECRET_LEN] = shake256(&rejected_input);
+		g_input.scrub();
+		rejected_input.scrub();
+
+		let reencrypted = self.pke_encrypt(ek, &m, &g[SEED_LEN..]);
+
+		// pick one without branching on which
+		let mask = eq_mask(&reencrypted, c);
+		let shared = std::array::from_fn(|i| (g[i] & mask) | (rejected[i] & !mask));
+
+		m.scrub();
+		g.scrub();
+		rejected.scrub();
+
+		Ok(Secret::new(shared))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	use super::*;
+
+	#[test]
+	fn test_sizes() {
+		// the byte lengths listed in table 3 of FIPS 203
+		let expected = [(800, 1632, 768), (1184, 2400, 1088), (1568, 3168, 1568)];
+
+		for (params, (ek, dk, c)) in [MlKemParams::ML_KEM_512, MlKemParams::ML_KEM_768, MlKemParams::ML_KEM_1024].iter().zip(expected) {
+			assert_eq!(params.encapsulation_key_len(), ek);
+			assert_eq!(params.decapsulation_key_len(), dk);
+			assert_eq!(params.ciphertext_len(), c);
+		}
+	}
+
+	#[test]
+	fn test_compress() {
+		for d in 1..=12 {
+			for x in 0..Q {
+				// the multiplication really does divide by q
+				let n = ((x as u64) << d) + Q as u64 / 2;
+				assert_eq!(compress(x, d) as u64, (n / Q as u64) % (1 << d));
+
+				// and decompressing lands within round(q / 2^(d + 1)) of where it started
+				let error = (decompress(compress(x, d), d) - x).rem_euclid(Q);
+				let bound = ((Q as i32 + (1 << d)) >> (d + 1)) as i16;
+				assert!(error.min(Q - error) <= bound, "{:?} is off by {:?} with d = {:?}", x, error, d);
+			}
+		}
+	}
+
+	#[test]
+	fn test_byte_encode() {
+		let mut rng = rand::thread_rng();
+
+		for d in [1, 4, 5, 10, 11, 12] {
+			let values: [i16 ; N] = std::array::from_fn(|_| rng.gen_range(0..(1 << d)));
+			let mut bytes = vec![0 ; 32 * d as usize];
+
+			byte_encode(&values, d, &mut bytes);
+			assert_eq!(byte_decode(&bytes, d), values);
+		}
+	}
+
+	#[test]
+	fn test_round_trip() {
+		for params in [MlKemParams::ML_KEM_512, MlKemParams::ML_KEM_768, MlKemParams::ML_KEM_1024] {
+			for _ in 0..8 {
+				let (ek, dk) = params.keygen();
+				assert_eq!(params.check_encapsulation_key(&ek), Ok(()));
+				assert_eq!(params.check_decapsulation_key(&dk), Ok(()));
+
+				let (c, shared) = params.encaps(&ek).unwrap();
+				assert_eq!(params.decaps(&dk, &c).unwrap(), shared);
+			}
+		}
+	}
+
+	#[test]
+	fn test_key_checks() {
+		let params = MlKemParams::ML_KEM_512;
+		let (ek, dk) = params.keygen_with_rng(&mut StdRng::seed_from_u64(0x41));
+
+		// a first coefficient of 0xfff is past q
+		let mut unreduced = ek.clone();
+		unreduced[0] = 0xff;
+		unreduced[1] |= 0x0f;
+		assert_eq!(params.encaps(&unreduced).map(|_| ()), Err(MlKemError::UnreducedKey));
+		assert_eq!(params.encaps(&ek[1..]).map(|_| ()), Err(MlKemError::WrongLength { expected: 800, actual: 799 }));
+
+		// changing the encapsulation key inside the decapsulation key breaks its hash
+		let mut tampered = dk.clone();
+		tampered.expose_mut()[params.t_len()] ^= 1;
+		assert_eq!(params.check_decapsulation_key(&tampered), Err(MlKemError::HashMismatch));
+
+		let (c, _) = params.encaps(&ek).unwrap();
+		assert_eq!(params.decaps(&tampered, &c).map(|_| ()), Err(MlKemError::HashMismatch));
+		assert_eq!(params.decaps(&dk, &c[1..]).map(|_| ()), Err(MlKemError::WrongLength { expected: 768, actual: 767 }));
+	}
+
+	#[test]
+	fn test_implicit_rejection() {
+		let params = MlKemParams::ML_KEM_768;
+		let (ek, dk) = params.keygen();
+		let (c, shared) = params.encaps(&ek).unwrap();
+
+		let mut tampered = c.clone();
+		tampered[0] ^= 1;
+
+		// J(z || c) from the end of the decapsulation key
+		let z = &dk.expose()[(params.decapsulation_key_len() - SEED_LEN)..];
+		let rejected: [u8 ; SHARED_SECRET_LEN] = shake256(&[z, &tampered].concat());
+
+		assert_ne!(params.decaps(&dk, &tampered).unwrap(), shared);
+		assert_eq!(params.decaps(&dk, &tampered).unwrap(), Secret::new(rejected));
+	}
+}
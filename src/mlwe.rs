@@ -0,0 +1,381 @@
+//
+// Public-key encryption from module learning with errors, where the entries of A, the secret
+// and the errors are polynomials in Z_3329[X]/(X^256 + 1) instead of integers mod q. Every
+// 256-bit plaintext takes up a single polynomial, so keys are a couple of kilobytes at most.
+//
+
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+
+use crate::lwe::expand::Sha512Xof;
+use crate::lwe::{ErrorDistribution, Seed, SEED_LEN};
+use crate::ntt::{ntt_dot, Poly, N, Q};
+use crate::secret::{Scrub, Secret};
+
+/// The same 256-bit plaintexts as plain LWE, one bit per coefficient
+pub use crate::lwe::{Plaintext, PLAINTEXT_LEN};
+
+/// The length, in bytes, of a polynomial with 12 bits per coefficient
+pub const POLY_BYTES: usize = 12 * N / 8;
+
+/// Separates the hashes that expand A from any other use of SHA-512 with the same seed
+const DOMAIN: &[u8] = b"rusty_crypto mlwe A";
+
+/// A public key, the seed A is expanded from followed by the K polynomials of t = As + e
+pub type PublicKey = Vec<u8>;
+
+/// A secret key, the K polynomials of s
+pub type SecretKey = Secret<Vec<u8>>;
+
+/// A ciphertext, the K polynomials of u followed by the polynomial v
+pub type Ciphertext = Vec<u8>;
+
+/// The rank and error sizes of an instance of module-LWE over Z_3329[X]/(X^256 + 1).
+///
+/// A is a K x K matrix of polynomials, so this is as hard as plain LWE in 256 K dimensions.
+/// The secret and key errors are centered binomial with parameter eta1, and the errors added
+/// during encryption with parameter eta2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MlweParams {
+	/// The module rank, the number of polynomials in the secret
+	pub k: usize,
+
+	/// The binomial parameter of the secret and the errors in the public key
+	pub eta1: u32,
+
+	/// The binomial parameter of the errors added during encryption
+	pub eta2: u32
+}
+
+impl MlweParams {
+
+	/// Rank 2, with the noise of Kyber-512
+	pub const K2: MlweParams = MlweParams { k: 2, eta1: 3, eta2: 2 };
+
+	/// Rank 3, with the noise of Kyber-768
+	pub const K3: MlweParams = MlweParams { k: 3, eta1: 2, eta2: 2 };
+
+	/// Rank 4, with the noise of Kyber-1024
+	pub const K4: MlweParams = MlweParams { k: 4, eta1: 2, eta2: 2 };
+
+	/// The parameters used by the free functions in this module
+	pub const DEFAULT: MlweParams = MlweParams::K3;
+
+	/// A custom parameter set
+	pub fn new(k: usize, eta1: u32, eta2: u32) -> MlweParams {
+		assert!((1..256).contains(&k), "The module rank {:?} should be in [1, 256)", k);
+		assert!(eta1 > 0 && eta1 <= 8 && eta2 > 0 && eta2 <= 8, "The binomial parameters should be in [1, 8]");
+
+		MlweParams { k, eta1, eta2 }
+	}
+
+	/// The length, in bytes, of a public key
+	pub const fn public_key_len(&self) -> usize {
+		SEED_LEN + self.k * POLY_BYTES
+	}
+
+	/// The length, in bytes, of a secret key
+	pub const fn secret_key_len(&self) -> usize {
+		self.k * POLY_BYTES
+	}
+
+	/// The length, in bytes, of a ciphertext
+	pub const fn ciphertext_len(&self) -> usize {
+		(self.k + 1) * POLY_BYTES
+	}
+}
+
+impl Default for MlweParams {
+	fn default() -> Self {
+		MlweParams::DEFAULT
+	}
+}
+
+// MARK: Helpers
+
+/// Deterministically expands `seed` into the K x K matrix A, listed row by row, with every
+/// entry already in the NTT domain. Entry (i, j) comes from its own stream, with i and j
+/// appended to the seed, taking the low 12 bits of each u32 and drawing again if that is q or more.
+fn expand_matrix(seed: &Seed, k: usize) -> Vec<[i16 ; N]> {
+	let mut a = Vec::with_capacity(k * k);
+
+	for i in 0..k {
+		for j in 0..k {
+			let mut input = seed.to_vec();
+			input.extend_from_slice(&[i as u8, j as u8]);
+
+			let mut xof = Sha512Xof::new(DOMAIN, &input);
+
+			a.push(std::array::from_fn(|_| loop {
+				let candidate = (xof.next_u32() & 0xfff) as i16;
+
+				if candidate < Q {
+					break candidate;
+				}
+			}));
+		}
+	}
+
+	a
+}
+
+/// A polynomial with centered binomial coefficients
+fn sample_poly(eta: u32, rng: &mut dyn RngCore) -> Poly {
+	let mut coefficients = [0 ; N];
+	ErrorDistribution::CenteredBinomial { eta }.sample_into(&mut coefficients, rng);
+
+	let poly = Poly::from_coefficients(&coefficients);
+	coefficients.scrub();
+
+	poly
+}
+
+/// Writes the coefficients 12 bits at a time, least significant bit first, so every two
+/// coefficients take up three bytes
+fn encode_poly(poly: &Poly, into: &mut [u8]) {
+	for (pair, chunk) in poly.coefficients().chunks_exact(2).zip(into.chunks_exact_mut(3)) {
+		let (a, b) = (pair[0] as u16, pair[1] as u16);
+		chunk.copy_from_slice(&[a as u8, ((a >> 8) | (b << 4)) as u8, (b >> 4) as u8]);
+	}
+}
+
+/// Reads back a polynomial written by `encode_poly`, reducing every coefficient mod q
+fn decode_poly(bytes: &[u8]) -> Poly {
+	let mut coefficients = [0 ; N];
+
+	for (pair, chunk) in coefficients.chunks_exact_mut(2).zip(bytes.chunks_exact(3)) {
+		pair[0] = chunk[0] as i64 | ((chunk[1] as i64 & 0xf) << 8);
+		pair[1] = (chunk[1] as i64 >> 4) | ((chunk[2] as i64) << 4);
+	}
+
+	let poly = Poly::from_coefficients(&coefficients);
+	coefficients.scrub();
+
+	poly
+}
+
+/// Reads `count` polynomials one after the other and transforms them
+fn decode_ntt_vector(bytes: &[u8], count: usize) -> Vec<[i16 ; N]> {
+	bytes.chunks_exact(POLY_BYTES).take(count).map(|chunk| decode_poly(chunk).ntt()).collect()
+}
+
+/// Each bit of the plaintext, least significant first, as 0 or round(q / 2)
+fn message_poly(pt: &Plaintext) -> Poly {
+	let mut coefficients = [0 ; N];
+
+	for (i, c) in coefficients.iter_mut().enumerate() {
+		*c = ((pt[i / 8] >> (i % 8)) & 1) as i64 * ((Q as i64 + 1) / 2);
+	}
+
+	let poly = Poly::from_coefficients(&coefficients);
+	coefficients.scrub();
+
+	poly
+}
+
+/// Rounds every coefficient to whichever of 0 and q / 2 is closer, and reads that off as a bit
+fn poly_message(poly: &Poly) -> Plaintext {
+	let mut pt = [0 ; PLAINTEXT_LEN];
+
+	for (i, c) in poly.coefficients().iter().enumerate() {
+		// round(2c / q) mod 2, which is 1 exactly when c is in (q / 4, 3q / 4)
+		let bit = ((((*c as u32) << 1) + Q as u32 / 2) / Q as u32) & 1;
+		pt[i / 8] |= (bit as u8) << (i % 8);
+	}
+
+	pt
+}
+
+// MARK: Interface
+
+impl MlweParams {
+
+	/// Generates a key pair for this parameter set
+	pub fn gen(&self) -> (SecretKey, PublicKey) {
+		self.gen_with_rng(&mut StdRng::from_entropy())
+	}
+
+	/// Generates a key pair, drawing the seed of A, the secret and the errors from `rng`, in
+	/// that order
+	pub fn gen_with_rng(&self, rng: &mut (impl RngCore + CryptoRng)) -> (SecretKey, PublicKey) {
+		let k = self.k;
+
+		let mut seed: Seed = [0 ; SEED_LEN];
+		rng.fill_bytes(&mut seed);
+		let a = expand_matrix(&seed, k);
+
+		let mut s: Vec<Poly> = (0..k).map(|_| sample_poly(self.eta1, rng)).collect();
+		let mut s_hat: Vec<[i16 ; N]> = s.iter().map(Poly::ntt).collect();
+
+		let mut sk = SecretKey::new(vec![0 ; self.secret_key_len()]);
+		for (poly, chunk) in s.iter().zip(sk.expose_mut().chunks_exact_mut(POLY_BYTES)) {
+			encode_poly(poly, chunk);
+		}
+
+		let mut pk = vec![0 ; self.public_key_len()];
+		pk[0..SEED_LEN].copy_from_slice(&seed);
+
+		// t = As + e
+		for (i, chunk) in pk[SEED_LEN..].chunks_exact_mut(POLY_BYTES).enumerate() {
+			let mut e = sample_poly(self.eta1, rng);
+			let t = Poly::from_ntt(&ntt_dot(a[(i * k)..((i + 1) * k)].iter(), &s_hat)) + e;
+			encode_poly(&t, chunk);
+			e.scrub();
+		}
+
+		s.scrub();
+		s_hat.scrub();
+
+		(sk, pk)
+	}
+
+	/// Encrypts a plaintext under a public key for this parameter set
+	pub fn enc(&self, pk: PublicKey, pt: Plaintext) -> Ciphertext {
+		self.enc_with_rng(pk, pt, &mut StdRng::from_entropy())
+	}
+
+	/// Encrypts a plaintext, drawing r, then the errors of u, then the error of v from `rng`
+	pub fn enc_with_rng(&self, pk: PublicKey, pt: Plaintext, rng: &mut (impl RngCore + CryptoRng)) -> Ciphertext {
+		assert_eq!(pk.len(), self.public_key_len(), "Public key has the wrong length for these parameters");
+		let k = self.k;
+
+		let seed: Seed = pk[0..SEED_LEN].try_into().unwrap();
+		let a = expand_matrix(&seed, k);
+		let t_hat = decode_ntt_vector(&pk[SEED_LEN..], k);
+
+		let mut r: Vec<[i16 ; N]> = (0..k).map(|_| sample_poly(self.eta1, rng).ntt()).collect();
+
+		let mut ct = vec![0 ; self.ciphertext_len()];
+		let (u_bytes, v_bytes) = ct.split_at_mut(k * POLY_BYTES);
+
+		// u = A^T r + e1, going down column i of A for entry i
+		for (i, chunk) in u_bytes.chunks_exact_mut(POLY_BYTES).enumerate() {
+			let mut e1 = sample_poly(self.eta2, rng);
+			let u = Poly::from_ntt(&ntt_dot((0..k).map(|j| &a[j * k + i]), &r)) + e1;
+			encode_poly(&u, chunk);
+			e1.scrub();
+		}
+
+		// v = t^T r + e2 + the message
+		let mut e2 = sample_poly(self.eta2, rng);
+		let mut m = message_poly(&pt);
+		let v = Poly::from_ntt(&ntt_dot(t_hat.iter(), &r)) + e2 + m;
+		encode_poly(&v, v_bytes);
+
+		r.scrub();
+		e2.scrub();
+		m.scrub();
+
+		ct
+	}
+
+	/// Decrypts a ciphertext with a secret key for this parameter set
+	pub fn dec(&self, sk: &SecretKey, ct: Ciphertext) -> Plaintext {
+		assert_eq!(sk.expose().len(), self.secret_key_len(), "Secret key has the wrong length for these parameters");
+		assert_eq!(ct.len(), self.ciphertext_len(), "Ciphertext has the wrong length for these parameters");
+		let k = self.k;
+
+		let mut s_hat = decode_ntt_vector(sk.expose(), k);
+		let u_hat = decode_ntt_vector(&ct, k);
+		let v = decode_poly(&ct[(k * POLY_BYTES)..]);
+
+		// v - s^T u leaves the message plus a small error
+		let mut w = v - Poly::from_ntt(&ntt_dot(s_hat.iter(), &u_hat));
+		let pt = poly_message(&w);
+
+		s_hat.scrub();
+		w.scrub();
+
+		pt
+	}
+}
+
+/// Generates a key pair with the default parameters
+pub fn gen() -> (SecretKey, PublicKey) {
+	MlweParams::DEFAULT.gen()
+}
+
+/// Generates a key pair with the default parameters, drawing everything from `rng`
+pub fn gen_with_rng(rng: &mut (impl RngCore + CryptoRng)) -> (SecretKey, PublicKey) {
+	MlweParams::DEFAULT.gen_with_rng(rng)
+}
+
+/// Encrypts a plaintext with the default parameters
+pub fn enc(pk: PublicKey, pt: Plaintext) -> Ciphertext {
+	MlweParams::DEFAULT.enc(pk, pt)
+}
+
+/// Encrypts a plaintext with the default parameters, drawing everything from `rng`
+pub fn enc_with_rng(pk: PublicKey, pt: Plaintext, rng: &mut (impl RngCore + CryptoRng)) -> Ciphertext {
+	MlweParams::DEFAULT.enc_with_rng(pk, pt, rng)
+}
+
+/// Decrypts a ciphertext with the default parameters
+pub fn dec(sk: &SecretKey, ct: Ciphertext) -> Plaintext {
+	MlweParams::DEFAULT.dec(sk, ct)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	use crate::ntt::{Poly, N};
+
+	use super::*;
+
+	#[test]
+	fn test_encoding() {
+		let mut rng = rand::thread_rng();
+		let poly = Poly::from_coefficients(&(0..N).map(|_| rng.gen_range(0..(Q as i64))).collect::<Vec<i64>>());
+
+		let mut bytes = [0 ; POLY_BYTES];
+		encode_poly(&poly, &mut bytes);
+		assert_eq!(decode_poly(&bytes), poly);
+
+		let pt: Plaintext = rng.gen();
+		assert_eq!(poly_message(&message_poly(&pt)), pt);
+	}
+
+	#[test]
+	fn test_mlwe() {
+		for params in [MlweParams::K2, MlweParams::K3, MlweParams::K4, MlweParams::new(1, 2, 2)] {
+			for _ in 0..16 {
+				let (sk, pk) = params.gen();
+
+				assert_eq!(pk.len(), params.public_key_len());
+				assert_eq!(sk.expose().len(), params.secret_key_len());
+
+				let pt: Plaintext = rand::thread_rng().gen();
+				let ct = params.enc(pk, pt);
+
+				assert_eq!(ct.len(), params.ciphertext_len());
+				assert_eq!(params.dec(&sk, ct), pt);
+			}
+		}
+	}
+
+	#[test]
+	fn test_key_sizes() {
+		// a few kilobytes, compared to the hundreds of kilobytes plain LWE takes
+		assert_eq!(MlweParams::K3.public_key_len(), 1184);
+		assert_eq!(MlweParams::K3.secret_key_len(), 1152);
+		assert_eq!(MlweParams::K3.ciphertext_len(), 1536);
+		assert!(MlweParams::K4.public_key_len() * 100 < crate::lwe::PUBKEY_LEN);
+	}
+
+	#[test]
+	fn test_reproducibility() {
+		let (first_sk, first_pk) = gen_with_rng(&mut StdRng::seed_from_u64(0x40));
+		let (second_sk, second_pk) = gen_with_rng(&mut StdRng::seed_from_u64(0x40));
+
+		assert_eq!(first_sk, second_sk);
+		assert_eq!(first_pk, second_pk);
+
+		let pt = [0x5a ; PLAINTEXT_LEN];
+		let first_ct = enc_with_rng(first_pk.clone(), pt, &mut StdRng::seed_from_u64(0x41));
+		let second_ct = enc_with_rng(second_pk, pt, &mut StdRng::seed_from_u64(0x41));
+
+		assert_eq!(first_ct, second_ct);
+		assert_eq!(dec(&first_sk, first_ct), pt);
+	}
+}
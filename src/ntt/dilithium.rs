@@ -0,0 +1,201 @@
+//
+// Arithmetic in Z_8380417[X]/(X^256 + 1), the ring Dilithium and ML-DSA are built on. Unlike
+// 3329, this q has 512th roots of unity, so the transform goes all the way down to single
+// coefficients and multiplication in the NTT domain is just coefficient by coefficient.
+//
+// The reduction and the transform are the ones in `transform`, instantiated for this modulus.
+//
+
+use super::transform::{self, Modulus};
+use super::N;
+
+/// The modulus, a prime with q = 1 mod 512
+pub const Q: i32 = 8380417;
+
+/// 2^64 mod q, which turns x * 2^-32 back into x under Montgomery multiplication
+const MONT_SQUARED: i32 = 2365951;
+
+/// The constants of the transform for q = 8380417
+pub struct Dilithium;
+
+impl Modulus for Dilithium {
+	type Coefficient = i32;
+
+	const Q: i64 = Q as i64;
+	const R: u32 = 32;
+	const QINV: i64 = 58728449;
+	const MONT: i64 = -4186625;
+
+	/// A primitive 512th root of unity mod q
+	const ZETA: i64 = 1753;
+	const LAYERS: u32 = 8;
+
+	/// q is just under 2^23, so round(a / 2^23) is already a good enough guess at a / q
+	const BARRETT_MULTIPLIER: i64 = 1;
+	const BARRETT_SHIFT: u32 = 23;
+}
+
+// MARK: Reduction
+
+/// Given |a| < q * 2^31, computes a * 2^-32 mod q, in (-q, q)
+pub fn montgomery_reduce(a: i64) -> i32 {
+	transform::montgomery_reduce::<Dilithium>(a) as i32
+}
+
+/// For a <= 2^31 - 2^22 - 1, computes a representative of a mod q in [-6283008, 6283008]
+pub fn reduce32(a: i32) -> i32 {
+	transform::barrett_reduce::<Dilithium>(a as i64) as i32
+}
+
+/// Multiplies a and b, and then divides by 2^32, mod q
+fn fqmul(a: i32, b: i32) -> i32 {
+	transform::fqmul::<Dilithium>(a as i64, b as i64) as i32
+}
+
+/// The representative of a mod q in [0, q), without branching on a
+pub fn canonical(a: i32) -> i32 {
+	transform::canonical::<Dilithium>(a as i64) as i32
+}
+
+// MARK: Transform
+
+/// The forward transform, in place. The input coefficients must be smaller than q in absolute
+/// value, and the output is in bit-reversed order, with every coefficient smaller than q in
+/// absolute value.
+pub fn ntt(r: &mut [i32 ; N]) {
+	transform::ntt::<Dilithium>(r)
+}
+
+/// The inverse transform, in place, taking bit-reversed input smaller than q in absolute value
+/// back to ordinary coefficients in (-q, q)
+pub fn inv_ntt(r: &mut [i32 ; N]) {
+	transform::inv_ntt::<Dilithium>(r)
+}
+
+/// Multiplies two transformed polynomials coefficient by coefficient, so that the result is the
+/// transform of their product
+pub fn pointwise_mul(a: &[i32 ; N], b: &[i32 ; N]) -> [i32 ; N] {
+	// undoing the 2^-32 from the first multiplication with the second
+	std::array::from_fn(|i| fqmul(fqmul(a[i], b[i]), MONT_SQUARED))
+}
+
+/// The sum of the products of `a` and `b`, one pair at a time, everything transformed
+pub fn ntt_dot<'a>(a: impl Iterator<Item = &'a [i32 ; N]>, b: &[[i32 ; N]]) -> [i32 ; N] {
+	let mut sum = [0 ; N];
+
+	for (x, y) in a.zip(b.iter()) {
+		for (s, p) in sum.iter_mut().zip(pointwise_mul(x, y)) {
+			*s = reduce32(*s + p);
+		}
+	}
+
+	sum
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::Rng;
+
+	use super::*;
+
+	fn random_poly() -> [i32 ; N] {
+		let mut rng = rand::thread_rng();
+		std::array::from_fn(|_| rng.gen_range(0..Q))
+	}
+
+	/// The product mod X^256 + 1, the slow way
+	fn schoolbook_mul(a: &[i32 ; N], b: &[i32 ; N]) -> [i32 ; N] {
+		let mut product = [0i64 ; N];
+
+		for i in 0..N {
+			for j in 0..N {
+				let term = (a[i] as i64 * b[j] as i64) % Q as i64;
+
+				// X^256 = -1
+				if i + j < N {
+					product[i + j] += term;
+				} else {
+					product[i + j - N] -= term;
+				}
+			}
+		}
+
+		product.map(|x| x.rem_euclid(Q as i64) as i32)
+	}
+
+	fn mul(a: &[i32 ; N], b: &[i32 ; N]) -> [i32 ; N] {
+		let (mut a_hat, mut b_hat) = (*a, *b);
+		ntt(&mut a_hat);
+		ntt(&mut b_hat);
+
+		let mut product = pointwise_mul(&a_hat, &b_hat);
+		inv_ntt(&mut product);
+
+		product.map(canonical)
+	}
+
+	#[test]
+	fn test_constants() {
+		assert_eq!((Dilithium::QINV as i32).wrapping_mul(Q), 1);
+		assert_eq!(Dilithium::MONT.rem_euclid(Dilithium::Q), (1 << 32) % Dilithium::Q);
+		assert_eq!(MONT_SQUARED as i128, (1i128 << 64) % Q as i128);
+
+		// zeta really is a primitive 512th root of unity
+		let power = |e: u32| (0..e).fold(1, |x, _| (x * Dilithium::ZETA) % Q as i64);
+		assert_eq!(power(256), Q as i64 - 1);
+
+		// the first few from the Dilithium reference implementation, which leaves out the unused first
+		assert_eq!(Dilithium::ZETAS[1..8], [25847, -2608894, -518909, 237124, -777960, -876248, 466468]);
+	}
+
+	#[test]
+	fn test_reduction() {
+		for a in [i32::MIN, -Q, -1, 0, 1, Q - 1, Q, i32::MAX - (1 << 22)] {
+			let reduced = reduce32(a);
+			assert!(reduced.abs() <= 6283008);
+			assert_eq!((reduced as i64 - a as i64).rem_euclid(Q as i64), 0);
+
+			let canonical = canonical(a);
+			assert!((0..Q).contains(&canonical));
+			assert_eq!((canonical as i64 - a as i64).rem_euclid(Q as i64), 0);
+		}
+
+		for a in [-((Q as i64) << 31) + 1, -1, 0, 1, 1234567890123, ((Q as i64) << 31) - 1] {
+			let reduced = montgomery_reduce(a) as i128;
+			assert!(reduced.abs() < Q as i128);
+			assert_eq!((reduced * (1 << 32) - a as i128).rem_euclid(Q as i128), 0);
+		}
+	}
+
+	#[test]
+	fn test_ntt_round_trip() {
+		for _ in 0..16 {
+			let a = random_poly();
+			let mut transformed = a;
+			ntt(&mut transformed);
+			assert!(transformed.iter().all(|x| x.abs() < Q));
+
+			inv_ntt(&mut transformed);
+			assert_eq!(transformed.map(canonical), a);
+		}
+	}
+
+	#[test]
+	fn test_against_schoolbook() {
+		for _ in 0..16 {
+			let a = random_poly();
+			let b = random_poly();
+			assert_eq!(mul(&a, &b), schoolbook_mul(&a, &b));
+		}
+
+		// X^255 * X = X^256 = -1
+		let mut x = [0 ; N];
+		x[1] = 1;
+		let mut x_255 = [0 ; N];
+		x_255[255] = 1;
+
+		let mut minus_one = [0 ; N];
+		minus_one[0] = Q - 1;
+		assert_eq!(mul(&x, &x_255), minus_one);
+	}
+}
@@ -0,0 +1,379 @@
+//
+// Arithmetic in the polynomial ring Z_3329[X]/(X^256 + 1) that Kyber and ML-KEM are built on,
+// with multiplication done by the number-theoretic transform. The ring ML-DSA uses, with a much
+// bigger modulus, is in `dilithium`. Both rings run the same transform, from `transform`, with
+// their own constants.
+//
+
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use algebra_kit::algebra::Ring;
+
+use crate::secret::Scrub;
+
+pub mod dilithium;
+pub mod transform;
+
+use transform::Modulus;
+
+/// The modulus, a prime with q = 1 mod 256
+pub const Q: i16 = 3329;
+
+/// The degree of X^N + 1, so polynomials have N coefficients
+pub const N: usize = 256;
+
+/// 2^32 mod q, which turns x * 2^-16 back into x under Montgomery multiplication
+const MONT_SQUARED: i16 = 1353;
+
+/// The constants of the transform for q = 3329
+pub struct Kyber;
+
+impl Modulus for Kyber {
+	type Coefficient = i16;
+
+	const Q: i64 = Q as i64;
+	const R: u32 = 16;
+	const QINV: i64 = -3327;
+	const MONT: i64 = -1044;
+
+	/// A primitive 256th root of unity mod q
+	const ZETA: i64 = 17;
+
+	/// q = 3329 only has 256th roots of unity, not 512th, so the transform stops one layer short,
+	/// leaving 128 polynomials of degree 1, one for each factor X^2 - zeta^(2 bitrev7(i) + 1) of
+	/// X^256 + 1
+	const LAYERS: u32 = 7;
+
+	/// floor((2^26 + q / 2) / q)
+	const BARRETT_MULTIPLIER: i64 = ((1 << 26) + Q as i64 / 2) / Q as i64;
+	const BARRETT_SHIFT: u32 = 26;
+}
+
+const ZETAS: [i64 ; N] = Kyber::ZETAS;
+
+// MARK: Reduction
+
+/// Given |a| < q * 2^15, computes a * 2^-16 mod q, in (-q, q)
+pub fn montgomery_reduce(a: i32) -> i16 {
+	transform::montgomery_reduce::<Kyber>(a as i64) as i16
+}
+
+/// Computes the representative of a mod q in [-(q - 1) / 2, (q - 1) / 2]
+pub fn barrett_reduce(a: i16) -> i16 {
+	transform::barrett_reduce::<Kyber>(a as i64) as i16
+}
+
+/// Multiplies a and b, and then divides by 2^16, mod q
+fn fqmul(a: i16, b: i16) -> i16 {
+	transform::fqmul::<Kyber>(a as i64, b as i64) as i16
+}
+
+/// The representative of a mod q in [0, q), without branching on a
+pub fn canonical(a: i16) -> i16 {
+	transform::canonical::<Kyber>(a as i64) as i16
+}
+
+// MARK: Transform
+
+/// The forward transform, in place. The input coefficients must be smaller than q in absolute
+/// value, and the output is in bit-reversed order, reduced to [-(q - 1) / 2, (q - 1) / 2].
+pub fn ntt(r: &mut [i16 ; N]) {
+	transform::ntt::<Kyber>(r)
+}
+
+/// The inverse transform, in place, taking bit-reversed input smaller than q in absolute value
+/// back to ordinary coefficients in (-q, q)
+pub fn inv_ntt(r: &mut [i16 ; N]) {
+	transform::inv_ntt::<Kyber>(r)
+}
+
+/// Multiplies a0 + a1 X by b0 + b1 X mod X^2 - zeta, leaving a factor of 2^-16 behind
+fn basemul(a: &[i16], b: &[i16], zeta: i16) -> [i16 ; 2] {
+	[
+		fqmul(fqmul(a[1], b[1]), zeta) + fqmul(a[0], b[0]),
+		fqmul(a[0], b[1]) + fqmul(a[1], b[0])
+	]
+}
+
+/// Multiplies two transformed polynomials, degree-1 factor by degree-1 factor, so that the
+/// result is the transform of their product
+pub fn pointwise_mul(a: &[i16 ; N], b: &[i16 ; N]) -> [i16 ; N] {
+	let mut r = [0 ; N];
+
+	for i in 0..(N / 4) {
+		let zeta = ZETAS[64 + i] as i16;
+
+		let lo = basemul(&a[(4 * i)..(4 * i + 2)], &b[(4 * i)..(4 * i + 2)], zeta);
+		let hi = basemul(&a[(4 * i + 2)..(4 * i + 4)], &b[(4 * i + 2)..(4 * i + 4)], -zeta);
+
+		r[(4 * i)..(4 * i + 4)].copy_from_slice(&[lo[0], lo[1], hi[0], hi[1]]);
+	}
+
+	// undoing the 2^-16 from basemul
+	for x in r.iter_mut() {
+		*x = fqmul(*x, MONT_SQUARED);
+	}
+
+	r
+}
+
+/// The sum of the products of `a` and `b`, one pair at a time, everything transformed
+pub fn ntt_dot<'a>(a: impl Iterator<Item = &'a [i16 ; N]>, b: &[[i16 ; N]]) -> [i16 ; N] {
+	let mut sum = [0 ; N];
+
+	for (x, y) in a.zip(b.iter()) {
+		for (s, p) in sum.iter_mut().zip(pointwise_mul(x, y)) {
+			*s = barrett_reduce(*s + p);
+		}
+	}
+
+	sum
+}
+
+// MARK: Polynomials
+
+/// A polynomial in Z_q[X]/(X^256 + 1), with every coefficient kept in [0, q)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Poly {
+	coefficients: [i16 ; N]
+}
+
+impl Poly {
+
+	/// The polynomial with the given coefficients, lowest degree first, reduced mod q
+	pub fn from_coefficients(coefficients: &[i64]) -> Poly {
+		assert_eq!(coefficients.len(), N, "A polynomial has exactly {:?} coefficients", N);
+		Poly { coefficients: std::array::from_fn(|i| coefficients[i].rem_euclid(Q as i64) as i16) }
+	}
+
+	/// The coefficients, lowest degree first, each in [0, q)
+	pub fn coefficients(&self) -> &[i16 ; N] {
+		&self.coefficients
+	}
+
+	/// The number-theoretic transform of this polynomial
+	pub fn ntt(&self) -> [i16 ; N] {
+		let mut transformed = self.coefficients;
+		ntt(&mut transformed);
+		transformed
+	}
+
+	/// The polynomial whose transform is `transformed`
+	pub fn from_ntt(transformed: &[i16 ; N]) -> Poly {
+		let mut coefficients = *transformed;
+		inv_ntt(&mut coefficients);
+
+		Poly { coefficients: coefficients.map(canonical) }
+	}
+
+	/// Multiplies every coefficient by c
+	pub fn scale(&self, c: i64) -> Poly {
+		let c = c.rem_euclid(Q as i64) as i32;
+		Poly { coefficients: self.coefficients.map(|x| ((x as i32 * c) % Q as i32) as i16) }
+	}
+}
+
+impl Debug for Poly {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Poly({:?})", self.coefficients)
+	}
+}
+
+impl Index<usize> for Poly {
+	type Output = i16;
+
+	fn index(&self, index: usize) -> &i16 {
+		&self.coefficients[index]
+	}
+}
+
+impl Scrub for Poly {
+	fn scrub(&mut self) {
+		self.coefficients.scrub()
+	}
+}
+
+impl Add for Poly {
+	type Output = Poly;
+
+	fn add(self, rhs: Poly) -> Poly {
+		Poly { coefficients: std::array::from_fn(|i| canonical(self.coefficients[i] + rhs.coefficients[i])) }
+	}
+}
+
+impl AddAssign for Poly {
+	fn add_assign(&mut self, rhs: Poly) {
+		*self = *self + rhs
+	}
+}
+
+impl Neg for Poly {
+	type Output = Poly;
+
+	fn neg(self) -> Poly {
+		Poly { coefficients: self.coefficients.map(|x| canonical(-x)) }
+	}
+}
+
+impl Sub for Poly {
+	type Output = Poly;
+
+	fn sub(self, rhs: Poly) -> Poly {
+		Poly { coefficients: std::array::from_fn(|i| canonical(self.coefficients[i] - rhs.coefficients[i])) }
+	}
+}
+
+impl SubAssign for Poly {
+	fn sub_assign(&mut self, rhs: Poly) {
+		*self = *self - rhs
+	}
+}
+
+impl Mul for Poly {
+	type Output = Poly;
+
+	fn mul(self, rhs: Poly) -> Poly {
+		Poly::from_ntt(&pointwise_mul(&self.ntt(), &rhs.ntt()))
+	}
+}
+
+impl MulAssign for Poly {
+	fn mul_assign(&mut self, rhs: Poly) {
+		*self = *self * rhs
+	}
+}
+
+impl Ring for Poly {
+	fn one() -> Self {
+		let mut one = Poly::zero();
+		one.coefficients[0] = 1;
+		one
+	}
+
+	fn zero() -> Self {
+		Poly { coefficients: [0 ; N] }
+	}
+
+	fn is_zero(&self) -> bool {
+		self.coefficients == [0 ; N]
+	}
+
+	fn power(&self, n: i64) -> Self {
+		assert!(n >= 0, "Polynomials in this ring aren't generally invertible");
+
+		// square and multiply, from the lowest bit up
+		let mut product = Poly::one();
+		let mut square = *self;
+		let mut n = n;
+
+		while n > 0 {
+			if n & 1 == 1 {
+				product *= square;
+			}
+
+			square *= square;
+			n >>= 1;
+		}
+
+		product
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use algebra_kit::algebra::Ring;
+	use rand::Rng;
+
+	use super::*;
+
+	fn random_poly() -> Poly {
+		let mut rng = rand::thread_rng();
+		Poly::from_coefficients(&(0..N).map(|_| rng.gen_range(0..(Q as i64))).collect::<Vec<i64>>())
+	}
+
+	/// The product mod X^256 + 1, the slow way
+	fn schoolbook_mul(a: &Poly, b: &Poly) -> Poly {
+		let mut product = [0i64 ; N];
+
+		for i in 0..N {
+			for j in 0..N {
+				let term = a[i] as i64 * b[j] as i64;
+
+				// X^256 = -1
+				if i + j < N {
+					product[i + j] += term;
+				} else {
+					product[i + j - N] -= term;
+				}
+			}
+		}
+
+		Poly::from_coefficients(&product)
+	}
+
+	#[test]
+	fn test_constants() {
+		assert_eq!((Kyber::QINV * Kyber::Q) & 0xffff, 1);
+		assert_eq!(Kyber::MONT.rem_euclid(Kyber::Q), (1 << 16) % Kyber::Q);
+		assert_eq!((MONT_SQUARED as i64), (1i64 << 32) % Q as i64);
+
+		// the first few from the Kyber reference implementation
+		assert_eq!(ZETAS[0..8], [-1044, -758, -359, -1517, 1493, 1422, 287, 202]);
+	}
+
+	#[test]
+	fn test_reduction() {
+		for a in [i16::MIN, -Q, -1, 0, 1, Q - 1, Q, i16::MAX] {
+			let reduced = barrett_reduce(a);
+			assert!(reduced.abs() <= (Q - 1) / 2);
+			assert_eq!((reduced as i32 - a as i32).rem_euclid(Q as i32), 0);
+		}
+
+		for a in [-((Q as i32) << 15) + 1, -1, 0, 1, 12345678, ((Q as i32) << 15) - 1] {
+			let reduced = montgomery_reduce(a) as i64;
+			assert!(reduced.abs() < Q as i64);
+			assert_eq!((reduced * (1 << 16) - a as i64).rem_euclid(Q as i64), 0);
+		}
+	}
+
+	#[test]
+	fn test_ntt_round_trip() {
+		for _ in 0..16 {
+			let a = random_poly();
+			assert_eq!(Poly::from_ntt(&a.ntt()), a);
+		}
+	}
+
+	#[test]
+	fn test_against_schoolbook() {
+		for _ in 0..16 {
+			let a = random_poly();
+			let b = random_poly();
+			assert_eq!(a * b, schoolbook_mul(&a, &b));
+		}
+
+		// X^255 * X = X^256 = -1
+		let mut x = [0 ; N];
+		x[1] = 1;
+		let mut x_255 = [0 ; N];
+		x_255[255] = 1;
+
+		assert_eq!(Poly::from_coefficients(&x) * Poly::from_coefficients(&x_255), -Poly::one());
+	}
+
+	#[test]
+	fn test_ring() {
+		let a = random_poly();
+		let b = random_poly();
+		let c = random_poly();
+
+		assert_eq!(a * (b + c), a * b + a * c);
+		assert_eq!(a - a, Poly::zero());
+		assert_eq!(a * Poly::one(), a);
+		assert_eq!(a.power(3), a * a * a);
+		assert_eq!(a.power(0), Poly::one());
+		assert_eq!(a.scale(-1), -a);
+		assert!((a * Poly::zero()).is_zero());
+	}
+}
@@ -0,0 +1,218 @@
+//
+// The number-theoretic transform over Z_q[X]/(X^256 + 1), written once for any prime q = 1 mod
+// 2^(layers + 1). The ring in the parent module (q = 3329) and the one in `dilithium`
+// (q = 8380417) are both instances of it. They differ only in the constants of `Modulus` and in
+// the signed word their coefficients are stored in. Arithmetic happens in i64, which has room
+// for the products of either.
+//
+
+use super::N;
+
+/// A signed word that coefficients mod q are stored in
+pub trait Coefficient: Copy + Into<i64> {
+
+	/// The low bits of a, which the caller makes sure fit
+	fn truncate(a: i64) -> Self;
+}
+
+impl Coefficient for i16 {
+	fn truncate(a: i64) -> i16 {
+		a as i16
+	}
+}
+
+impl Coefficient for i32 {
+	fn truncate(a: i64) -> i32 {
+		a as i32
+	}
+}
+
+/// The constants that pick out one ring Z_q[X]/(X^256 + 1) and the transform over it
+pub trait Modulus {
+
+	/// The word coefficients are stored in, which has R bits
+	type Coefficient: Coefficient;
+
+	/// The modulus, a prime with q = 1 mod 2^(LAYERS + 1)
+	const Q: i64;
+
+	/// Montgomery multiplication divides by 2^R
+	const R: u32;
+
+	/// q^-1 mod 2^R
+	const QINV: i64;
+
+	/// The Montgomery factor, 2^R mod q, centered around zero
+	const MONT: i64;
+
+	/// A primitive 2^(LAYERS + 1)th root of unity mod q
+	const ZETA: i64;
+
+	/// How many layers of butterflies the transform has. The polynomials it leaves behind have
+	/// 256 / 2^LAYERS coefficients each.
+	const LAYERS: u32;
+
+	/// Barrett reduction computes a - q * round(a * BARRETT_MULTIPLIER / 2^BARRETT_SHIFT)
+	const BARRETT_MULTIPLIER: i64;
+	const BARRETT_SHIFT: u32;
+
+	/// The powers of zeta used by the transform, zeta^bitrev(i) * 2^R mod q, centered around zero
+	const ZETAS: [i64 ; N] = zetas(Self::Q, Self::MONT, Self::ZETA, Self::LAYERS);
+}
+
+/// Reverses the lowest `bits` bits of i
+const fn bit_reverse(i: usize, bits: u32) -> usize {
+	let mut reversed = 0;
+	let mut bit = 0;
+
+	while bit < bits {
+		reversed |= ((i >> bit) & 1) << (bits - 1 - bit);
+		bit += 1;
+	}
+
+	reversed
+}
+
+const fn zetas(q: i64, mont: i64, zeta: i64, layers: u32) -> [i64 ; N] {
+	let mut table = [0 ; N];
+	let mut i = 0;
+
+	while i < 1 << layers {
+		let mut power = mont + q;
+		let mut e = 0;
+
+		while e < bit_reverse(i, layers) {
+			power = (power * zeta) % q;
+			e += 1;
+		}
+
+		if power > q / 2 {
+			power -= q;
+		}
+
+		table[i] = power;
+		i += 1;
+	}
+
+	table
+}
+
+// MARK: Reduction
+
+/// Given |a| < q * 2^(R - 1), computes a * 2^-R mod q, in (-q, q)
+pub fn montgomery_reduce<M: Modulus>(a: i64) -> i64 {
+	// a * q^-1 mod 2^R, sign extended from bit R - 1
+	let t = (a.wrapping_mul(M::QINV) << (64 - M::R)) >> (64 - M::R);
+	(a - t * M::Q) >> M::R
+}
+
+/// A representative of a mod q that is at most about q / 2 in absolute value
+pub fn barrett_reduce<M: Modulus>(a: i64) -> i64 {
+	let t = (M::BARRETT_MULTIPLIER * a + (1 << (M::BARRETT_SHIFT - 1))) >> M::BARRETT_SHIFT;
+	a - t * M::Q
+}
+
+/// Multiplies a and b, and then divides by 2^R, mod q
+pub fn fqmul<M: Modulus>(a: i64, b: i64) -> i64 {
+	montgomery_reduce::<M>(a * b)
+}
+
+/// The representative of a mod q in [0, q), without branching on a
+pub fn canonical<M: Modulus>(a: i64) -> i64 {
+	let a = barrett_reduce::<M>(a);
+	a + ((a >> 63) & M::Q)
+}
+
+// MARK: Transform
+
+/// The forward transform, in place. The input coefficients must be smaller than q in absolute
+/// value, and the output is in bit-reversed order, Barrett reduced.
+pub fn ntt<M: Modulus>(r: &mut [M::Coefficient ; N]) {
+	let zetas = M::ZETAS;
+	let mut k = 1;
+	let mut len = N / 2;
+
+	while len >= N >> M::LAYERS {
+		for start in (0..N).step_by(2 * len) {
+			let zeta = zetas[k];
+			k += 1;
+
+			for j in start..(start + len) {
+				let t = fqmul::<M>(zeta, r[j + len].into());
+				let a: i64 = r[j].into();
+
+				r[j + len] = Coefficient::truncate(a - t);
+				r[j] = Coefficient::truncate(a + t);
+			}
+		}
+
+		len >>= 1;
+	}
+
+	for x in r.iter_mut() {
+		*x = Coefficient::truncate(barrett_reduce::<M>((*x).into()));
+	}
+}
+
+/// The inverse transform, in place, taking bit-reversed input smaller than q in absolute value
+/// back to ordinary coefficients in (-q, q)
+pub fn inv_ntt<M: Modulus>(r: &mut [M::Coefficient ; N]) {
+	let zetas = M::ZETAS;
+	let mut k = (1 << M::LAYERS) - 1;
+	let mut len = N >> M::LAYERS;
+
+	while len <= N / 2 {
+		for start in (0..N).step_by(2 * len) {
+			let zeta = zetas[k];
+			k -= 1;
+
+			for j in start..(start + len) {
+				let (a, b): (i64, i64) = (r[j].into(), r[j + len].into());
+
+				r[j] = Coefficient::truncate(barrett_reduce::<M>(a + b));
+				r[j + len] = Coefficient::truncate(fqmul::<M>(zeta, b - a));
+			}
+		}
+
+		len <<= 1;
+	}
+
+	// 2^R / 2^LAYERS, which undoes both the 2^LAYERS of the inverse transform and the factor of
+	// 2^-R the Montgomery multiplication leaves behind
+	let factor = 1 << (M::R - M::LAYERS);
+
+	for x in r.iter_mut() {
+		*x = Coefficient::truncate(fqmul::<M>((*x).into(), factor));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ntt::dilithium::Dilithium;
+	use crate::ntt::Kyber;
+
+	/// The constants agree with each other, whichever modulus they are for
+	fn check_constants<M: Modulus>() {
+		assert_eq!((M::QINV * M::Q) & ((1 << M::R) - 1), 1);
+		assert_eq!(M::MONT.rem_euclid(M::Q), (1i128 << M::R).rem_euclid(M::Q as i128) as i64);
+
+		// zeta really is a primitive 2^(LAYERS + 1)th root of unity
+		let power = |e: u64| (0..e).fold(1, |x, _| (x * M::ZETA) % M::Q);
+		assert_eq!(power(1 << M::LAYERS), M::Q - 1);
+
+		// the table holds zeta^bitrev(i), in Montgomery form
+		for i in 0..(1 << M::LAYERS) {
+			assert_eq!(fqmul::<M>(M::ZETAS[i], 1).rem_euclid(M::Q), power(bit_reverse(i, M::LAYERS) as u64));
+		}
+	}
+
+	#[test]
+	fn test_constants() {
+		check_constants::<Kyber>();
+		check_constants::<Dilithium>();
+
+		assert_eq!(bit_reverse(1, 7), 64);
+		assert_eq!(bit_reverse(0b1101, 8), 0b1011_0000);
+	}
+}
@@ -20,8 +20,6 @@
 // 	}
 // }
 
-use std::mem::transmute;
-
 use crate::lwe::enc;
 
 /**
@@ -49,6 +47,7 @@ macro_rules! make_sha_pad {
 	(
 		$name:ident,
 		$unname:ident,
+		$try_unname:ident,
 		$test_suite_name:ident,
 		$block_len:literal,
 		$len_type:ty,
@@ -58,7 +57,9 @@ macro_rules! make_sha_pad {
 		const $lt_cnst: usize = std::mem::size_of::<$len_type>();
 
 		/// $name 
-		pub fn $name(pt: Vec<u8>) -> Vec<u8> {
+		pub fn $name(pt: impl AsRef<[u8]>) -> Vec<u8> {
+			let pt = pt.as_ref();
+
 			// compute how many bytes we need to add in order to get be a multiple
 			// of $block_len
 
@@ -76,51 +77,38 @@ macro_rules! make_sha_pad {
 			// add the 1 bit
 			padded_pt[pt.len()] = 0x80;
 
-			// now, we write the original length, as a $len_type.
-			let original_bit_len = ((pt.len() * 8) as $len_type).to_le();
-
-			// This will be little-endian, but this is okay, since we will be reading it in reverse order.
-			let bit_len_bytes: [u8 ; std::mem::size_of::<$len_type>()] = unsafe { transmute(original_bit_len) };
-
-			for i in 0..std::mem::size_of::<$len_type>() {
-				padded_pt[padded_len - 1 - i] = bit_len_bytes[i];
-			}
+			// now, we write the original length, as a big-endian $len_type.
+			let bit_len_bytes = ((pt.len() * 8) as $len_type).to_be_bytes();
+			padded_pt[(padded_len - $lt_cnst)..].copy_from_slice(&bit_len_bytes);
 
 			padded_pt
 			
 		}
 
+		/// Removes the padding added by $name, panicking if it is corrupted
 		pub fn $unname(padded: Vec<u8>) -> Vec<u8> {
-			// first, go ahead and trim off the original size.
-			let padded_len = padded.len();
-			let original_len_bytes: [u8 ; $lt_cnst] = padded[(padded_len - $lt_cnst)..].try_into().unwrap();
-
-			let original_len = <$len_type>::from_be_bytes(original_len_bytes);
-
-			// now, we find where the 0x80 byte is!
-			let mut truncate_index = 0;
-
-			for i in (0..(padded.len() - $lt_cnst)).rev() {
-				if i == 0 && padded[i] != 0x80 {
-					panic!("Corrupted pad: Never found 1 bit");
-				}
+			$try_unname(&padded).expect("Corrupted pad")
+		}
 
-				if padded[i] == 0x80 {
-					truncate_index = i;
-					break;
-				} else if padded[i] != 0x00 {
-					panic!("Corrupted pad: Found nonzero byte in padding");
-				}
+		/// Removes the padding added by $name, or gives `None` if it isn't padding $name could
+		/// have added, so that a ciphertext from someone else can't crash us
+		pub fn $try_unname(padded: &[u8]) -> Option<Vec<u8>> {
+			if padded.len() <= $lt_cnst {
+				return None;
 			}
 
-			let original = &padded[..truncate_index];
+			// first, go ahead and trim off the original size.
+			let length_index = padded.len() - $lt_cnst;
+			let original_len = <$len_type>::from_be_bytes(padded[length_index..].try_into().unwrap());
 
-			if (original.len() * 8) as $len_type  != original_len {
-				panic!("Corrupted pad: Lengths do not match");
-			}
+			// now, we find where the 0x80 byte is, which is the last one that isn't 0
+			let truncate_index = padded[..length_index].iter().rposition(|byte| *byte != 0x00)?;
 
-			original.to_vec()
+			if padded[truncate_index] != 0x80 || (truncate_index as $len_type).checked_mul(8) != Some(original_len) {
+				return None;
+			}
 
+			Some(padded[..truncate_index].to_vec())
 		}
 
 		#[cfg(test)]
@@ -146,6 +134,23 @@ macro_rules! make_sha_pad {
 
 			}
 
+			#[test]
+			fn test_corrupted() {
+				let mut padded = $name(b"abc".to_vec());
+				assert_eq!($try_unname(&padded), Some(b"abc".to_vec()));
+
+				// a wrong length, a missing 1 bit, and too little to hold a length at all
+				let last = padded.len() - 1;
+				padded[last] ^= 8;
+				assert_eq!($try_unname(&padded), None);
+				padded[last] ^= 8;
+
+				padded[3] = 0;
+				assert_eq!($try_unname(&padded), None);
+				assert_eq!($try_unname(&[0 ; $lt_cnst]), None);
+				assert_eq!($try_unname(&vec![0 ; 64]), None);
+			}
+
 			#[test]
 			fn test_abc() {
 				let string = "abc".as_bytes().to_vec();
@@ -167,6 +172,7 @@ macro_rules! make_sha_pad {
 make_sha_pad!(
 	pad_sha512,
 	unpad_sha512,
+	try_unpad_sha512,
 	pad_sha512_tests,
 	1025,
 	u128,
@@ -176,6 +182,7 @@ make_sha_pad!(
 make_sha_pad!(
 	pad_sha256,
 	unpad_sha256,
+	try_unpad_sha256,
 	pad_sha256_tests,
 	512,
 	u64,
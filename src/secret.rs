@@ -0,0 +1,181 @@
+//
+// Secret values that wipe themselves when they are dropped
+//
+
+use std::{fmt::Debug, sync::atomic::{compiler_fence, Ordering}};
+
+/// Memory that can be securely overwritten with zeroes.
+///
+/// The writes are volatile, so the compiler can't decide they're pointless and optimize them out
+/// just because the value is never read again.
+pub trait Scrub {
+	fn scrub(&mut self);
+}
+
+macro_rules! impl_scrub_int {
+	($($t: ty),*) => {
+		$(
+			impl Scrub for $t {
+				fn scrub(&mut self) {
+					// SAFETY: self is a valid, aligned, exclusive reference
+					unsafe { std::ptr::write_volatile(self, 0) };
+					compiler_fence(Ordering::SeqCst);
+				}
+			}
+		)*
+	};
+}
+
+impl_scrub_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: Scrub> Scrub for [T] {
+	fn scrub(&mut self) {
+		for x in self.iter_mut() {
+			x.scrub();
+		}
+	}
+}
+
+impl<T: Scrub, const N: usize> Scrub for [T ; N] {
+	fn scrub(&mut self) {
+		self.as_mut_slice().scrub()
+	}
+}
+
+/// Only the elements are scrubbed, not any spare capacity, so secrets should not be pushed
+/// into a vector that might reallocate.
+impl<T: Scrub> Scrub for Vec<T> {
+	fn scrub(&mut self) {
+		self.as_mut_slice().scrub()
+	}
+}
+
+impl<T: Scrub + ?Sized> Scrub for Box<T> {
+	fn scrub(&mut self) {
+		self.as_mut().scrub()
+	}
+}
+
+impl<const Q: i64> Scrub for algebra_kit::std_impls::ZM<Q> {
+	fn scrub(&mut self) {
+		self.val.scrub()
+	}
+}
+
+/// A secret value, like a key or a share.
+///
+/// The value is zeroed out when it is dropped, it never shows up when debug printed, and it can
+/// only be copied with an explicit `clone`. The contents can be read with `expose`, which should
+/// be done as late as possible.
+pub struct Secret<T: Scrub>(T);
+
+impl<T: Scrub> Secret<T> {
+	pub fn new(value: T) -> Secret<T> {
+		Secret(value)
+	}
+
+	/// Gives access to the secret value
+	pub fn expose(&self) -> &T {
+		&self.0
+	}
+
+	/// Gives mutable access to the secret value
+	pub fn expose_mut(&mut self) -> &mut T {
+		&mut self.0
+	}
+}
+
+impl<T: Scrub> From<T> for Secret<T> {
+	fn from(value: T) -> Self {
+		Secret::new(value)
+	}
+}
+
+impl<T: Scrub> Drop for Secret<T> {
+	fn drop(&mut self) {
+		self.0.scrub();
+	}
+}
+
+impl<T: Scrub> Debug for Secret<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Secret([REDACTED])")
+	}
+}
+
+impl<T: Scrub + Clone> Clone for Secret<T> {
+	fn clone(&self) -> Self {
+		Secret(self.0.clone())
+	}
+}
+
+/// Compares two secrets without stopping at the first differing byte, so that the time the
+/// comparison takes doesn't give away where they differ.
+impl<T: Scrub + AsRef<[u8]>> PartialEq for Secret<T> {
+	fn eq(&self, other: &Self) -> bool {
+		let a = self.0.as_ref();
+		let b = other.0.as_ref();
+
+		if a.len() != b.len() {
+			return false;
+		}
+
+		a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+	}
+}
+
+impl<T: Scrub + AsRef<[u8]>> Eq for Secret<T> { }
+
+/// All ones if `a` and `b` are the same, and zero otherwise, looking at every byte either way, so
+/// the result can pick between two secrets without branching
+pub(crate) fn eq_mask(a: &[u8], b: &[u8]) -> u8 {
+	assert_eq!(a.len(), b.len());
+
+	let difference = a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+
+	// only a difference of 0 wraps around to set the high byte
+	((difference as u16).wrapping_sub(1) >> 8) as u8
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_scrub() {
+		let mut array = [0xffu8 ; 100];
+		array.scrub();
+		assert_eq!(array, [0 ; 100]);
+
+		let mut words = vec![u64::MAX ; 10];
+		words.scrub();
+		assert_eq!(words, vec![0 ; 10]);
+
+		let mut boxed = Box::new([[1i64 ; 3] ; 3]);
+		boxed.scrub();
+		assert_eq!(*boxed, [[0 ; 3] ; 3]);
+	}
+
+	#[test]
+	fn test_redacted() {
+		let secret = Secret::new([0x42u8 ; 4]);
+		let printed = format!("{:?}", secret);
+
+		assert!(!printed.contains("42"));
+		assert!(printed.contains("REDACTED"));
+	}
+
+	#[test]
+	fn test_eq() {
+		let a = Secret::new([1u8, 2, 3]);
+		assert_eq!(a, a.clone());
+		assert_ne!(a, Secret::new([1u8, 2, 4]));
+	}
+
+	#[test]
+	fn test_eq_mask() {
+		assert_eq!(eq_mask(&[1, 2, 3], &[1, 2, 3]), 0xff);
+		assert_eq!(eq_mask(&[1, 2, 3], &[1, 2, 4]), 0);
+		assert_eq!(eq_mask(&[0], &[0xff]), 0);
+	}
+}
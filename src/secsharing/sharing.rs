@@ -7,11 +7,11 @@
 // and removing the import causes an error
 // use rand::seq::IteratorRandom;
 
-use std::{mem::transmute, u64, usize};
 use super::types::*;
+use crate::secret::{Scrub, Secret};
 
 use algebra_kit::algebra::*;
-use sylvan_number::ubignumber::{UBigNumber, Word};
+use rand::{rngs::StdRng, CryptoRng, Rng, RngCore, SeedableRng};
 
 // MARK: The Math Stuff
 
@@ -23,15 +23,20 @@ type Point = (u64, ZMQ);
 /// Adi Shamir's t-out-of-k secret sharing scheme, where only t out of k total 
 /// shares are required to recover the secret, and the secret can be contained in 256 bits.
 pub fn create_curve(t: usize, k: usize, secret: Intercept) -> Vec<Point> {
+	create_curve_with_rng(t, k, secret, &mut StdRng::from_entropy())
+}
+
+/// Same as `create_curve`, but the random coefficients of the polynomial are drawn from `rng`.
+pub fn create_curve_with_rng(t: usize, k: usize, secret: Intercept, rng: &mut (impl RngCore + CryptoRng)) -> Vec<Point> {
 	// we create a random polynomial with the intercept being the secret, and each share is a point somewhere!
 
-	let mut coefficients: Vec<ZMQ> = vec![ZMQ { data: [0 ; 5] } ; t];
+	let mut coefficients: Vec<ZMQ> = vec![ZMQ::zero() ; t];
 	coefficients[0] = secret;
 	for i in 1..t {
-		coefficients[i] = ZMQ::rnd();
+		coefficients[i] = ZMQ::rnd_with_rng(rng);
 	}
 
-	let mut points: Vec<Point> = vec![(0, ZMQ { data: [0 ; 5] }) ; k];
+	let mut points: Vec<Point> = vec![(0, ZMQ::zero()) ; k];
 
 	for i in 0..k {
 
@@ -39,17 +44,20 @@ pub fn create_curve(t: usize, k: usize, secret: Intercept) -> Vec<Point> {
 		let mut polynomial_value = ZMQ::zero();
 
 		for j in 0..t {
-			polynomial_value += coefficients[j] * ZMQ::from_ubn((i as u64).into()).power(j as i64);
+			polynomial_value += &coefficients[j] * &ZMQ::from(i as u64).power(j as i64);
 		}
 
 		points[i] = (i as u64, polynomial_value)
 	}
 
+	// the coefficients are as good as the secret itself, so they shouldn't outlive this function
+	coefficients.scrub();
+
 	points
 
 }
 
-fn h(t: usize, i: usize, a: Vec<ZMQ>, x: ZMQ) -> ZMQ {
+fn h(t: usize, i: usize, a: &[ZMQ], x: &ZMQ) -> ZMQ {
 	let mut value = ZMQ::one();
 
 	for j in 0..t {
@@ -57,7 +65,7 @@ fn h(t: usize, i: usize, a: Vec<ZMQ>, x: ZMQ) -> ZMQ {
 			continue;
 		}
 
-		value *= x - a[j]
+		value *= x - &a[j]
 	}
 	
 	value
@@ -71,9 +79,9 @@ fn lagrange_interpolate(t: usize, points: Vec<Point>) -> Intercept {
 	let mut ys = vec![ZMQ::zero() ; t];
 
 	for i in 0..t {
-		let (x, y) = points[i];
-		xs[i] = ZMQ::from_ubn(UBigNumber::from_int(x as Word));
-		ys[i] = y;
+		let (x, y) = &points[i];
+		xs[i] = ZMQ::from(*x);
+		ys[i] = y.clone();
 	}
 
 	// we need polynomials p_0 ... p_T-1 so that p_i(shares[i]_0) = 1, but p_i(shares[j]_0) = 0 for all i != j
@@ -86,101 +94,174 @@ fn lagrange_interpolate(t: usize, points: Vec<Point>) -> Intercept {
 	let mut inverses = vec![ZMQ::zero() ; t];
 	
 	for i in 0..t {
-		inverses[i] = h(t, i, xs.clone(), xs[i]).inverse();
+		inverses[i] = h(t, i, &xs, &xs[i]).inverse();
 	}
 
 	let mut ps = vec![ZMQ::zero() ; t];
 
 	for i in 0..t {
-		ps[i] = inverses[i] * h(t, i, xs.clone(), ZMQ::zero());
+		ps[i] = &inverses[i] * &h(t, i, &xs, &ZMQ::zero());
 	}
 
 	let mut secret = ZMQ::zero();
 
 	for i in 0..t {
-		secret += ys[i] * ps[i];
+		secret += &ys[i] * &ps[i];
 	}
 
 	secret
 
 }
 
+// -- The Same Over a Small Prime --
+//
+// These are for sharing secrets that live in the integers mod some prime q that fits in an i64,
+// like the coefficients of an LWE secret key, one coefficient at a time.
+
+/// A share of a secret mod q
+pub(crate) type PointMod = (u64, i64);
+
+/// Shamir sharing of `secret` mod the prime q, the same as `create_curve_with_rng`.
+///
+/// The points are at x = 1, ..., k, since the point at 0 is the secret itself.
+pub(crate) fn create_curve_mod_with_rng(t: usize, k: usize, secret: i64, q: i64, rng: &mut (impl RngCore + CryptoRng)) -> Vec<PointMod> {
+	assert!(t >= 1 && t <= k, "Need 1 <= t <= k, but t = {:?} and k = {:?}", t, k);
+	assert!((k as i64) < q, "Can't hand out {:?} distinct shares mod {:?}", k, q);
+
+	let mut coefficients = vec![0 ; t];
+	coefficients[0] = secret.rem_euclid(q);
+	for coefficient in coefficients.iter_mut().skip(1) {
+		*coefficient = rng.gen_range(0..q);
+	}
+
+	let points = (1..=(k as u64)).map(|x| {
+		// Horner's rule, from the top coefficient down
+		let value = coefficients.iter().rev().fold(0, |value, coefficient| (value * x as i64 + coefficient) % q);
+		(x, value)
+	}).collect();
+
+	coefficients.scrub();
+
+	points
+}
+
+/// The inverse of a mod q, which must exist
+pub(crate) fn inverse_mod(a: i64, q: i64) -> i64 {
+	// the extended Euclidean algorithm, only keeping track of the coefficient of a
+	let (mut r0, mut r1) = (q, a.rem_euclid(q));
+	let (mut s0, mut s1) = (0, 1);
+
+	while r1 != 0 {
+		let quotient = r0 / r1;
+		(r0, r1) = (r1, r0 - quotient * r1);
+		(s0, s1) = (s1, s0 - quotient * s1);
+	}
+
+	assert_eq!(r0, 1, "{:?} has no inverse mod {:?}", a, q);
+	s0.rem_euclid(q)
+}
+
+/// `h` mod q
+fn h_mod(i: usize, xs: &[u64], x: i64, q: i64) -> i64 {
+	let mut value = 1;
+
+	for (j, x_j) in xs.iter().enumerate() {
+		if i == j {
+			continue;
+		}
+
+		value = value * (x - *x_j as i64).rem_euclid(q) % q;
+	}
+
+	value
+}
+
+/// The values p_i(0) from `lagrange_interpolate`, mod q, for points at `xs`. The secret is then
+/// the sum of each share's value times its coefficient, and those products can be summed
+/// without anybody seeing anyone else's share.
+pub(crate) fn lagrange_coefficients_mod(xs: &[u64], q: i64) -> Vec<i64> {
+	(0..xs.len()).map(|i| {
+		let alpha = inverse_mod(h_mod(i, xs, xs[i] as i64, q), q);
+		alpha * h_mod(i, xs, 0, q) % q
+	}).collect()
+}
+
 // MARK: Secret Sharing Interface
 
 pub const SECRET_SIZE_BYTES: usize = 32;
 pub const SHARE_SIZE_BYTES: usize = SECRET_SIZE_BYTES + (2 * std::mem::size_of::<u64>());
 
 /// A 256-bit secret
-pub type Secret256 = [u8 ; SECRET_SIZE_BYTES];
+pub type Secret256 = Secret<[u8 ; SECRET_SIZE_BYTES]>;
 
 /// A share of a 256-bit secret
 /// 
 /// This has two additional words to hold the "x coordinate" of the share, and to hold a possibly large share.
-pub type Share256 = [u8 ; SHARE_SIZE_BYTES];
-
-fn secret_to_intercept(secret: Secret256) -> Intercept {
-	// TODO: This could behave differently on different machines depending on endianness.
-	// Consider forcing endianness to avoid this.
-	let words: [u64 ; 4] = unsafe { std::mem::transmute(secret) };
-	let data = [words[0], words[1], words[2], words[3], 0];
+pub type Share256 = Secret<[u8 ; SHARE_SIZE_BYTES]>;
 
-	ZMQ { data }
+// The secret is read as four little-endian words, least significant word first, so it is
+// the little-endian encoding of the intercept.
+fn secret_to_intercept(secret: &Secret256) -> Intercept {
+	ZMQ::from_le_bytes(secret.expose())
 }
 
 fn intercept_to_secret(intercept: Intercept) -> Secret256 {
 	// this fails if the incertept is too big, so it is only to be used with intercepts generated from 4 words.
-	assert_eq!(intercept.data[4], 0);
+	assert_eq!(intercept.words()[4], 0);
 
-	unsafe {
-		std::mem::transmute([intercept.data[0], intercept.data[1], intercept.data[2], intercept.data[3]])
+	let mut secret = Secret256::new([0 ; SECRET_SIZE_BYTES]);
+
+	for (i, bytes) in secret.expose_mut().chunks_exact_mut(8).enumerate() {
+		bytes.copy_from_slice(&intercept.words()[i].to_le_bytes());
 	}
+
+	secret
 }
 
-fn share_to_point(share: Share256) -> Point {
+// A share is the label as a big-endian word, followed by the five words of the value, each 
+// little-endian and least significant word first.
+fn share_to_point(share: &Share256) -> Point {
 	// we first take the first word (8 bytes) and make it into the share label, then the remaining 5 words 
 	// are the actual value.
 
+	let share = share.expose();
 	let label = u64::from_be_bytes(share[0..8].try_into().unwrap());
 
-	let data: [u64 ; 5] = unsafe {
-		transmute::<[u8 ; SECRET_SIZE_BYTES + 8], [u64 ; 5]>(share[8..].try_into().unwrap())
-	};
-
-	(label, ZMQ { data })
+	(label, ZMQ::from_le_bytes(&share[8..]))
 }
 
 fn point_to_share(point: Point) -> Share256 {
 	let (label, value) = point;
 
-	let mut share = [0u8 ; SHARE_SIZE_BYTES];
+	let mut share = Share256::new([0u8 ; SHARE_SIZE_BYTES]);
+	let bytes = share.expose_mut();
 
-	let label_bytes: [u8 ; 8] = label.to_be_bytes().try_into().unwrap();
-	for i in 0..8 {
-		share[i] = label_bytes[i];
-	}
-
-	let data = unsafe {
-		transmute::<[u64 ; 5], [u8 ; SECRET_SIZE_BYTES + 8]>(value.data)
-	};
+	bytes[0..8].copy_from_slice(&label.to_be_bytes());
 
-	for i in 0..40 {
-		share[i + 8] = data[i];
+	for (i, word_bytes) in bytes[8..].chunks_exact_mut(8).enumerate() {
+		word_bytes.copy_from_slice(&value.words()[i].to_le_bytes());
 	}
 
 	share
 }
 
 /// Creates K shares of a 256-bit secret, out of which T are required to reconstruct the secret.
-pub fn distribute(t: usize, k: usize, secret: Secret256) -> Vec<Share256> {
-	let points = create_curve(t, k, secret_to_intercept(secret));
-	points.into_iter().map(|p| point_to_share(p)).collect()
+pub fn distribute(t: usize, k: usize, secret: &Secret256) -> Vec<Share256> {
+	distribute_with_rng(t, k, secret, &mut StdRng::from_entropy())
+}
+
+/// Creates K shares of a 256-bit secret, out of which T are required to reconstruct the secret.
+/// The polynomial hiding the secret is sampled from `rng`.
+pub fn distribute_with_rng(t: usize, k: usize, secret: &Secret256, rng: &mut (impl RngCore + CryptoRng)) -> Vec<Share256> {
+	let points = create_curve_with_rng(t, k, secret_to_intercept(secret), rng);
+	points.into_iter().map(point_to_share).collect()
 }
 
 /// Combines T shares of a secret.
 /// 
 /// shares must have length at least t.
 pub fn reconstruct(t: usize, shares: Vec<Share256>) -> Secret256 {
-	let points = shares.into_iter().map(|s| share_to_point(s)).collect();
+	let points = shares.iter().map(share_to_point).collect();
 	intercept_to_secret(lagrange_interpolate(t, points))
 }
 
@@ -191,53 +272,81 @@ mod tests {
 
     use algebra_kit::algebra::Ring;
     use rand::seq::IteratorRandom;
+    use rand::Rng;
 
-    use crate::{secsharing::sharing::{create_curve, intercept_to_secret, lagrange_interpolate, point_to_share, secret_to_intercept, share_to_point}, speck};
+    use crate::{secsharing::sharing::{create_curve, create_curve_mod_with_rng, intercept_to_secret, lagrange_coefficients_mod, lagrange_interpolate, point_to_share, secret_to_intercept, share_to_point}, speck};
 
-    use super::{distribute, Intercept, Secret256, ZMQ};
+    use super::{distribute, distribute_with_rng, reconstruct, Intercept, Secret256, ZMQ};
 
 	#[test]
 	fn test_converstion_symmetry() {
 		for _ in 0..100 {
 			let secret: Secret256 = speck::gen();
-			let shares = distribute(3, 4, secret);
+			let shares = distribute(3, 4, &secret);
 
-			assert_eq!(secret, intercept_to_secret(secret_to_intercept(secret)));
+			assert_eq!(secret, intercept_to_secret(secret_to_intercept(&secret)));
 
 			for share in shares {
-				assert_eq!(share, point_to_share(share_to_point(share)));
+				assert_eq!(share, point_to_share(share_to_point(&share)));
 			}
 		}
 	}
 
+	#[test]
+	fn test_seeded_distribution() {
+		use rand::{rngs::StdRng, SeedableRng};
+
+		let secret: Secret256 = speck::gen_with_rng(&mut StdRng::seed_from_u64(7));
+
+		let first = distribute_with_rng(3, 5, &secret, &mut StdRng::seed_from_u64(8));
+		let second = distribute_with_rng(3, 5, &secret, &mut StdRng::seed_from_u64(8));
+
+		assert_eq!(first, second);
+		assert_eq!(reconstruct(3, first[1..4].to_vec()), secret);
+	}
+
+	#[test]
+	fn test_byte_layout() {
+		// secrets and shares must decode the same way on every machine
+		let mut secret_bytes = [0u8 ; 32];
+		secret_bytes[0] = 0x01;
+		secret_bytes[8] = 0x02;
+		secret_bytes[31] = 0x80;
+		let intercept = secret_to_intercept(&Secret256::new(secret_bytes));
+		assert_eq!(intercept.words(), &[0x01, 0x02, 0, 0x8000000000000000, 0]);
+
+		let share = point_to_share((3, ZMQ::from_words(&[0x0102030405060708, 0, 0, 0, 1])));
+		let mut expected = [0u8 ; 48];
+		expected[7] = 3;
+		expected[8..16].copy_from_slice(&[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]);
+		expected[40] = 1;
+		assert_eq!(*share.expose(), expected);
+	}
+
 	#[test]
 	fn test_simple_recovery() {
-		let secret: Intercept = ZMQ::from_ubn(4.into());
+		let secret: Intercept = ZMQ::from(4);
 
-		let share1 = (1, ZMQ::from_ubn(8.into()));
-		let share2 = (2, ZMQ::from_ubn(8.into()));
-		let share3 = (3, ZMQ::from_ubn(4.into()));
+		let share1 = (1, ZMQ::from(8));
+		let share2 = (2, ZMQ::from(8));
+		let share3 = (3, ZMQ::from(4));
 
 		assert_eq!(secret, lagrange_interpolate(3, [share1, share2, share3].to_vec()));
 	}
 
-	fn sss_test<const T: usize, const K: usize>() where [() ; K * T]: Sized, [() ; T * 1]: Sized, [() ; K * 1]: Sized {
+	fn sss_test<const T: usize, const K: usize>() {
 		for _ in 0..100 {
 			let secret = ZMQ::rnd();
-			let shares = create_curve(T, K, secret);
+			let shares = create_curve(T, K, secret.clone());
 
 			// first, go ahead and recombibe the first T shares.
 			assert_eq!(lagrange_interpolate(T, shares[0..T].to_vec()), secret);
 			// come up with random ways of combining the shares!
 			for _ in 0..10 {
 				let share_combo_refs = shares.iter().choose_multiple(&mut rand::thread_rng(), T);
-				let mut share_combo = [(0, ZMQ::zero()) ; T];
-
-				for i in 0..T {
-					share_combo[i] = *share_combo_refs[i]
-				}
+				let share_combo: Vec<_> = share_combo_refs.into_iter().cloned().collect();
 
-				assert_eq!(lagrange_interpolate(T, share_combo[0..T].to_vec()), secret);
+				assert_eq!(lagrange_interpolate(T, share_combo), secret);
 			}
 		}
 	}
@@ -254,4 +363,25 @@ mod tests {
 		// sss_test::<50, 50>();
 	
 	}
+
+	#[test]
+	fn test_sharing_mod_q() {
+		let q = 3329;
+
+		for (t, k) in [(1, 1), (2, 3), (3, 5), (5, 5)] {
+			let secret = rand::thread_rng().gen_range(0..q);
+			let points = create_curve_mod_with_rng(t, k, secret, q, &mut rand::thread_rng());
+
+			for _ in 0..10 {
+				let chosen = points.iter().choose_multiple(&mut rand::thread_rng(), t);
+				let xs: Vec<u64> = chosen.iter().map(|(x, _)| *x).collect();
+
+				let recovered = lagrange_coefficients_mod(&xs, q).iter()
+					.zip(chosen)
+					.fold(0, |sum, (coefficient, (_, y))| (sum + coefficient * y) % q);
+
+				assert_eq!(recovered, secret);
+			}
+		}
+	}
 }
@@ -1,20 +1,77 @@
 use std::{fmt::Debug, ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign}};
 
-use rand::{rngs::StdRng, Rng, SeedableRng};
-use sylvan_number::{bignumber::BigNumber, ubignumber::UBigNumber};
-use algebra_kit::{algebra::*, std_impls::ZM};
+use rand::{rngs::StdRng, CryptoRng, Rng, RngCore, SeedableRng};
+use algebra_kit::algebra::*;
 
+#[cfg(feature = "bignum")]
+use sylvan_number::ubignumber::UBigNumber;
 
-macro_rules! secret_modulus {
-	() => {
-		UBigNumber::from_words(vec![0x9B, 0, 0, 0, 2])
-	};
+use crate::secret::Scrub;
+
+pub use crate::words::Word;
+use crate::words;
+
+/// The prime Q = 2^257 + 155, least significant word first
+const MODULUS: [Word ; 5] = [0x9B, 0, 0, 0, 2];
+
+// MARK: Reduction
+
+/// Reduces an arbitrarily long number modulo Q.
+///
+/// Writing x = hi * 2^257 + lo, we have 2^257 = -155 (mod Q), so x = lo - 155 * hi, where 
+/// 155 * hi is about 249 bits shorter than x. That is repeated until nothing is above bit 257.
+fn reduce(x: &[Word]) -> [Word ; 5] {
+	let mut lo = [0 ; 5];
+	for (i, w) in x.iter().take(5).enumerate() {
+		lo[i] = *w;
+	}
+	lo[4] &= 1;
+
+	// hi = x >> 257, times 155
+	let mut hi_times_155 = vec![0 ; x.len().saturating_sub(4) + 1];
+	let mut carry: u128 = 0;
+	for i in 0..(hi_times_155.len() - 1) {
+		let shifted = (x[i + 4] >> 1) | (x.get(i + 5).copied().unwrap_or(0) << 63);
+		let product = shifted as u128 * 155 + carry;
+		hi_times_155[i] = product as Word;
+		carry = product >> 64;
+	}
+	*hi_times_155.last_mut().unwrap() = carry as Word;
+
+	if hi_times_155.iter().all(|w| *w == 0) {
+		// lo < 2^257 < Q, so it's already reduced
+		return lo;
+	}
+
+	if words::cmp(&lo, &hi_times_155).is_ge() {
+		words::sub_assign(&mut lo, &hi_times_155);
+		lo
+	} else {
+		// the difference is negative, so we reduce its absolute value and negate that
+		words::sub_assign(&mut hi_times_155, &lo);
+		let mut negated = reduce(&hi_times_155);
+
+		if negated != [0 ; 5] {
+			let mut q = MODULUS;
+			words::sub_assign(&mut q, &negated);
+			negated = q;
+		}
+
+		negated
+	}
 }
 
 /// The field of integers modulo Q, where Q is that big prime.
-#[derive(Clone, Copy)]
+///
+/// These hold secrets and the coefficients hiding them, so they are never implicitly copied, and
+/// they wipe themselves when they are dropped. The arithmetic works on references, to avoid
+/// cloning just to add or multiply.
+///
+/// The words are only ever set through a reducing constructor, so they always hold a value below
+/// Q, which the arithmetic relies on.
+#[derive(Clone)]
 pub struct ZMQ {
-	pub data: [sylvan_number::ubignumber::Word ; 5]
+	data: [Word ; 5]
 }
 
 impl Debug for ZMQ {
@@ -27,30 +84,74 @@ impl Debug for ZMQ {
 impl ZMQ {
 
 	/// Generates a secret from a UBN
+	#[cfg(feature = "bignum")]
+	#[deprecated(note = "use `ZMQ::from_le_bytes` or `ZMQ::from_words`, which don't need the nightly-only `bignum` feature")]
 	pub fn from_ubn(ubn: UBigNumber) -> ZMQ {
-		let modded = ubn % secret_modulus!();
+		ZMQ::from_words(&ubn.words)
+	}
 
-		let mut words = [0 ; 5];
+	/// Reduces a number of any length, least significant word first, modulo Q
+	pub fn from_words(words: &[Word]) -> ZMQ {
+		ZMQ { data: reduce(words) }
+	}
+
+	/// Reduces a little-endian number of any length modulo Q
+	pub fn from_le_bytes(bytes: &[u8]) -> ZMQ {
+		let mut words = vec![0 ; bytes.len().div_ceil(8)];
 
-		for i in 0..5 {
-			words[i] = modded.safe_word(i);
+		for (word, chunk) in words.iter_mut().zip(bytes.chunks(8)) {
+			let mut word_bytes = [0u8 ; 8];
+			word_bytes[..chunk.len()].copy_from_slice(chunk);
+			*word = Word::from_le_bytes(word_bytes);
+			word_bytes.scrub();
 		}
 
-		ZMQ { data: words }
+		let reduced = ZMQ::from_words(&words);
+		words.scrub();
+
+		reduced
+	}
+
+	/// The reduced value, least significant word first
+	pub fn words(&self) -> &[Word ; 5] {
+		&self.data
 	}
 
 	/// A helper function that does modular exponentiation when the exponent is a power of 2, written as 2^n
-	fn pow_mod_pow2(self, n: i64) -> ZMQ {
-		if n == 0 {
-			self
-		} else {
-			self.clone().pow_mod_pow2(n - 1) * self.pow_mod_pow2(n - 1)
+	fn pow_mod_pow2(&self, n: i64) -> ZMQ {
+		let mut power = self.clone();
+
+		for _ in 0..n {
+			power = &power * &power;
 		}
+
+		power
+	}
+
+	/// Raises this to a power given as words, least significant first
+	fn pow_words(&self, exponent: &[Word]) -> ZMQ {
+		let mut product = ZMQ::one();
+
+		for w in exponent.iter().rev() {
+			for i in (0..64).rev() {
+				product = &product * &product;
+
+				if (w >> i) & 1 == 1 {
+					product *= self;
+				}
+			}
+		}
+
+		product
 	}
 
 	/// Securely generates a random integer modulo q
 	pub fn rnd() -> ZMQ {
-		let mut rng = StdRng::from_entropy();
+		ZMQ::rnd_with_rng(&mut StdRng::from_entropy())
+	}
+
+	/// Generates a random integer modulo q, drawing from the supplied generator
+	pub fn rnd_with_rng(rng: &mut (impl RngCore + CryptoRng)) -> ZMQ {
 		let msw_set: bool = rng.gen();
 
 		let mut words = [0 ; 5];
@@ -66,83 +167,145 @@ impl ZMQ {
 
 }
 
+impl From<u64> for ZMQ {
+	fn from(value: u64) -> Self {
+		ZMQ { data: [value, 0, 0, 0, 0] }
+	}
+}
+
+impl Scrub for ZMQ {
+	fn scrub(&mut self) {
+		self.data.scrub()
+	}
+}
+
+impl Drop for ZMQ {
+	fn drop(&mut self) {
+		self.scrub();
+	}
+}
+
 impl PartialEq for ZMQ {
 	fn eq(&self, other: &Self) -> bool {
 		self.data == other.data
 	}
 }
 
-impl Add for ZMQ {
+impl Add for &ZMQ {
 	type Output = ZMQ;
 
 	fn add(self, rhs: Self) -> Self::Output {
-		ZMQ::from_ubn(UBigNumber::from_words(self.data.to_vec()) + UBigNumber::from_words(rhs.data.to_vec()))
-	}
-}
+		let mut sum = self.data;
 
-impl AddAssign for ZMQ {
-	fn add_assign(&mut self, rhs: Self) {
-		*self = self.clone() + rhs;
+		// both are below Q < 2^258, so the sum fits in five words without a carry out
+		words::add_assign(&mut sum, &rhs.data);
+
+		if words::cmp(&sum, &MODULUS).is_ge() {
+			words::sub_assign(&mut sum, &MODULUS);
+		}
+
+		ZMQ { data: sum }
 	}
 }
 
-impl Neg for ZMQ {
+impl Neg for &ZMQ {
 	type Output = ZMQ;
 
 	fn neg(self) -> Self::Output {
-		ZMQ::from_ubn(secret_modulus!() - UBigNumber::from_words(self.data.to_vec()))
+		if self.is_zero() {
+			return ZMQ::zero();
+		}
+
+		let mut negated = MODULUS;
+		words::sub_assign(&mut negated, &self.data);
+
+		ZMQ { data: negated }
 	}
 }
 
-impl Sub for ZMQ {
+impl Sub for &ZMQ {
 	type Output = ZMQ;
 
 	fn sub(self, rhs: Self) -> Self::Output {
-		self + (-rhs)
-	}
-}
-
-impl SubAssign for ZMQ {
-	fn sub_assign(&mut self, rhs: Self) {
-		*self = self.clone() - rhs;
+		self + &(-rhs)
 	}
 }
 
-impl Mul for ZMQ {
+impl Mul for &ZMQ {
 	type Output = ZMQ;
 
 	fn mul(self, rhs: Self) -> Self::Output {
-		ZMQ::from_ubn(UBigNumber::from_words(self.data.to_vec()) * UBigNumber::from_words(rhs.data.to_vec()))
-	}
-}
+		// schoolbook multiplication, then reduce the ten-word product
+		let mut product = words::mul(&self.data, &rhs.data);
+
+		let reduced = ZMQ::from_words(&product);
+		product.scrub();
 
-impl MulAssign for ZMQ {
-	fn mul_assign(&mut self, rhs: Self) {
-		*self = self.clone() * rhs;
+		reduced
 	}
 }
 
-impl Div for ZMQ {
+impl Div for &ZMQ {
 	type Output = ZMQ;
 
 	fn div(self, rhs: Self) -> Self::Output {
-		self * rhs.inverse()
+		self * &rhs.inverse()
 	}
 }
 
-impl DivAssign for ZMQ {
-	fn div_assign(&mut self, rhs: Self) {
-		*self = self.clone() / rhs;
+impl Neg for ZMQ {
+	type Output = ZMQ;
+
+	fn neg(self) -> Self::Output {
+		-&self
 	}
 }
 
+// the by-value and assigning versions of each operation, all in terms of the one on references
+macro_rules! impl_owned_op {
+	($op: ident, $method: ident, $op_assign: ident, $method_assign: ident) => {
+		impl $op for ZMQ {
+			type Output = ZMQ;
+
+			fn $method(self, rhs: Self) -> Self::Output {
+				(&self).$method(&rhs)
+			}
+		}
+
+		impl $op<&ZMQ> for ZMQ {
+			type Output = ZMQ;
+
+			fn $method(self, rhs: &ZMQ) -> Self::Output {
+				(&self).$method(rhs)
+			}
+		}
+
+		impl $op_assign for ZMQ {
+			fn $method_assign(&mut self, rhs: Self) {
+				*self = (&*self).$method(&rhs);
+			}
+		}
+
+		impl $op_assign<&ZMQ> for ZMQ {
+			fn $method_assign(&mut self, rhs: &ZMQ) {
+				*self = (&*self).$method(rhs);
+			}
+		}
+	};
+}
+
+impl_owned_op!(Add, add, AddAssign, add_assign);
+impl_owned_op!(Sub, sub, SubAssign, sub_assign);
+impl_owned_op!(Mul, mul, MulAssign, mul_assign);
+impl_owned_op!(Div, div, DivAssign, div_assign);
+
 impl Ring for ZMQ {
 	fn one() -> Self {
-		ZMQ::from_ubn(UBigNumber::one())
+		ZMQ::from(1)
 	}
 
 	fn zero() -> Self {
-		ZMQ::from_ubn(UBigNumber::zero())
+		ZMQ::from(0)
 	}
 
 	fn is_zero(&self) -> bool {
@@ -154,7 +317,7 @@ impl Ring for ZMQ {
 
 		for i in 0..64 { // check n, bit by bit!
 			if n & (1 << i) != 0 {
-				product *= self.clone().pow_mod_pow2(i)
+				product *= self.pow_mod_pow2(i)
 			}
 		}
 
@@ -164,30 +327,23 @@ impl Ring for ZMQ {
 
 impl Field for ZMQ {
 	fn inverse(&self) -> Self {
-		// compute a modular inverse!
-		let bn_self: BigNumber = BigNumber::from_ubn(UBigNumber::from_words(self.data.to_vec()));
-		let bn_modulus: BigNumber = secret_modulus!().into();
-
-		let (_, mut x, _) = algebra_kit::algebra::ext_gcd(bn_self, bn_modulus.clone());
-		
-		while x.is_negative {
-			x += bn_modulus.clone();
-		}
+		// Q is prime, so by Fermat's little theorem the inverse is a^(Q - 2)
+		let mut exponent = MODULUS;
+		words::sub_assign(&mut exponent, &[2]);
 
-		ZMQ::from_ubn(x.into())
+		self.pow_words(&exponent)
 	}
 }
 
 #[cfg(test)]
 mod tests {
 
-	use sylvan_number::ubignumber::UBigNumber;
     use algebra_kit::algebra::{Field, Ring};
 	use rand::Rng;
 	
-    use super::ZMQ;
+    use super::{MODULUS, ZMQ};
 
-	fn naive_pow(a: ZMQ, b: i64) -> ZMQ {
+	fn naive_pow(a: &ZMQ, b: i64) -> ZMQ {
 		let mut product = ZMQ::one();
 
 		for _ in 0..b {
@@ -203,10 +359,20 @@ mod tests {
 			let x = ZMQ::rnd();
 			let pow = rand::thread_rng().gen_range(0..10000);
 
-			assert_eq!(naive_pow(x, pow), x.power(pow))
+			assert_eq!(naive_pow(&x, pow), x.power(pow))
 		}
 	}
 
+	#[test]
+	fn test_rnd_with_rng() {
+		use rand::{rngs::StdRng, SeedableRng};
+
+		let first = ZMQ::rnd_with_rng(&mut StdRng::seed_from_u64(42));
+		let second = ZMQ::rnd_with_rng(&mut StdRng::seed_from_u64(42));
+
+		assert_eq!(first, second);
+	}
+
 	#[test]
 	fn test_mul_inv() {
 		for _ in 0..1000 {
@@ -218,13 +384,43 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_reduction() {
+		// 2^257 = -155 (mod Q)
+		let two_257 = ZMQ::from_words(&[0, 0, 0, 0, 2]);
+		assert_eq!(two_257, -ZMQ::from(155));
+		assert_eq!(ZMQ::from_words(&MODULUS), ZMQ::zero());
+
+		// (Q - 1)^2 = 1
+		let minus_one = -ZMQ::one();
+		assert_eq!(minus_one.words(), &[0x9A, 0, 0, 0, 2]);
+		assert_eq!(&minus_one * &minus_one, ZMQ::one());
+		assert_eq!(minus_one + ZMQ::from(2), ZMQ::one());
+	}
+
+	#[test]
+	fn test_from_le_bytes() {
+		let mut bytes = [0u8 ; 40];
+		bytes[0] = 0x9B;
+		bytes[32] = 2;
+		assert_eq!(ZMQ::from_le_bytes(&bytes), ZMQ::zero());
+
+		// values past Q are reduced, so sums can't carry out of the top word
+		bytes[0] = 0x9A;
+		bytes[39] = 0xFF;
+		let big = ZMQ::from_le_bytes(&bytes);
+		assert!(big.words()[4] < 4);
+		assert_eq!(big, ZMQ::from_words(&[0x9A, 0, 0, 0, 0xFF00000000000002]));
+		assert_eq!(&big + &big, ZMQ::from_words(&[0x134, 0, 0, 0, 0xFE00000000000004, 1]));
+	}
+
 	#[test]
 	fn test_add() {
-		println!("{:?}", secret_modulus!());
+		println!("{:?}", MODULUS);
 		for _ in 0..10 {
 			let a = ZMQ::rnd();
 			let b = ZMQ::rnd();
-			let c = a + b;
+			let c = &a + &b;
 
 			println!("\n");
 			println!("\t{:?}\n+\t{:?}\n------------------------------------------------------------------------- \n\t{:?}", a, b, c);
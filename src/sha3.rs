@@ -0,0 +1,241 @@
+//
+// The SHA-3 hash functions and the SHAKE extendable-output functions from FIPS 202, all built on
+// the Keccak-f[1600] permutation
+//
+
+use crate::secret::Scrub;
+
+/// The number of 64-bit lanes in the Keccak state
+const LANES: usize = 25;
+
+/// The number of rounds of Keccak-f[1600]
+const ROUNDS: usize = 24;
+
+/// The byte appended to the message before padding for the SHA-3 hashes
+const SHA3_SUFFIX: u8 = 0x06;
+
+/// The byte appended to the message before padding for SHAKE
+const SHAKE_SUFFIX: u8 = 0x1f;
+
+type State = [u64 ; LANES];
+
+// MARK: Algorithm Constants
+
+/// The round constants for iota, made by the linear feedback shift register of FIPS 202, 3.2.5
+const fn round_constants() -> [u64 ; ROUNDS] {
+	let mut constants = [0 ; ROUNDS];
+	let mut r: u8 = 1;
+	let mut i = 0;
+
+	while i < ROUNDS {
+		let mut j = 0;
+
+		while j < 7 {
+			if r & 1 == 1 {
+				constants[i] |= 1 << ((1 << j) - 1);
+			}
+
+			// stepping x^8 + x^6 + x^5 + x^4 + 1
+			r = (r << 1) ^ (if r & 0x80 != 0 { 0x71 } else { 0 });
+			j += 1;
+		}
+
+		i += 1;
+	}
+
+	constants
+}
+
+/// The rotation of each lane in rho, indexed by x + 5y, from FIPS 202, 3.2.2
+const fn rotations() -> [u32 ; LANES] {
+	let mut offsets = [0 ; LANES];
+	let (mut x, mut y) = (1, 0);
+	let mut t = 0;
+
+	while t < 24 {
+		offsets[x + 5 * y] = (((t + 1) * (t + 2) / 2) % 64) as u32;
+		(x, y) = (y, (2 * x + 3 * y) % 5);
+		t += 1;
+	}
+
+	offsets
+}
+
+const ROUND_CONSTANTS: [u64 ; ROUNDS] = round_constants();
+const ROTATIONS: [u32 ; LANES] = rotations();
+
+// MARK: Permutation
+
+/// Keccak-f[1600], in place
+fn keccak_f(a: &mut State) {
+	for round_constant in ROUND_CONSTANTS {
+		// theta
+		let c: [u64 ; 5] = std::array::from_fn(|x| a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20]);
+		for x in 0..5 {
+			let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+			for y in 0..5 {
+				a[x + 5 * y] ^= d;
+			}
+		}
+
+		// rho and pi
+		let mut b = [0 ; LANES];
+		for x in 0..5 {
+			for y in 0..5 {
+				b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotate_left(ROTATIONS[x + 5 * y]);
+			}
+		}
+
+		// chi
+		for x in 0..5 {
+			for y in 0..5 {
+				a[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+			}
+		}
+
+		// iota
+		a[0] ^= round_constant;
+
+		b.scrub();
+	}
+}
+
+/// XORs a byte into the state, where the lanes are little-endian
+fn xor_byte(state: &mut State, index: usize, byte: u8) {
+	state[index / 8] ^= (byte as u64) << (8 * (index % 8));
+}
+
+fn get_byte(state: &State, index: usize) -> u8 {
+	(state[index / 8] >> (8 * (index % 8))) as u8
+}
+
+// MARK: Sponge
+
+/// Absorbs all of `input` followed by the padding, leaving the state ready to be squeezed
+fn absorb(input: &[u8], rate: usize, suffix: u8) -> State {
+	let mut state = [0 ; LANES];
+
+	let mut blocks = input.chunks_exact(rate);
+	for block in blocks.by_ref() {
+		for (i, byte) in block.iter().enumerate() {
+			xor_byte(&mut state, i, *byte);
+		}
+		keccak_f(&mut state);
+	}
+
+	let rest = blocks.remainder();
+	for (i, byte) in rest.iter().enumerate() {
+		xor_byte(&mut state, i, *byte);
+	}
+
+	// pad10*1, with the domain bits in front of it
+	xor_byte(&mut state, rest.len(), suffix);
+	xor_byte(&mut state, rate - 1, 0x80);
+	keccak_f(&mut state);
+
+	state
+}
+
+/// The first N bytes of the output of a fixed-length SHA-3 hash
+fn sha3<const N: usize>(input: &[u8]) -> [u8 ; N] {
+	let rate = 200 - 2 * N;
+	let mut state = absorb(input, rate, SHA3_SUFFIX);
+
+	let digest = std::array::from_fn(|i| get_byte(&state, i));
+	state.scrub();
+
+	digest
+}
+
+/// SHA3-256 of `input`
+pub fn sha3_256(input: &[u8]) -> [u8 ; 32] {
+	sha3(input)
+}
+
+/// SHA3-512 of `input`
+pub fn sha3_512(input: &[u8]) -> [u8 ; 64] {
+	sha3(input)
+}
+
+/// SHAKE128 or SHAKE256 with all of its input absorbed, and output read off with `squeeze` as
+/// many bytes at a time as needed
+pub struct Shake {
+	state: State,
+	rate: usize,
+	used: usize
+}
+
+impl Shake {
+	/// SHAKE128 of `input`
+	pub fn shake128(input: &[u8]) -> Shake {
+		Shake { state: absorb(input, 168, SHAKE_SUFFIX), rate: 168, used: 0 }
+	}
+
+	/// SHAKE256 of `input`
+	pub fn shake256(input: &[u8]) -> Shake {
+		Shake { state: absorb(input, 136, SHAKE_SUFFIX), rate: 136, used: 0 }
+	}
+
+	/// Fills `out` with the next bytes of output
+	pub fn squeeze(&mut self, out: &mut [u8]) {
+		for byte in out.iter_mut() {
+			if self.used == self.rate {
+				keccak_f(&mut self.state);
+				self.used = 0;
+			}
+
+			*byte = get_byte(&self.state, self.used);
+			self.used += 1;
+		}
+	}
+}
+
+impl Drop for Shake {
+	fn drop(&mut self) {
+		self.state.scrub()
+	}
+}
+
+/// The first N bytes of SHAKE256 of `input`
+pub fn shake256<const N: usize>(input: &[u8]) -> [u8 ; N] {
+	let mut out = [0 ; N];
+	Shake::shake256(input).squeeze(&mut out);
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn hex(bytes: &[u8]) -> String {
+		bytes.iter().map(|b| format!("{:02x}", b)).collect()
+	}
+
+	#[test]
+	fn test_sha3() {
+		assert_eq!(hex(&sha3_256(b"abc")), "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532");
+		assert_eq!(hex(&sha3_512(b"abc")), "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0");
+
+		// more than one block, and not a whole number of them
+		let long: Vec<u8> = (0..512).map(|i| i as u8).collect();
+		assert_eq!(hex(&sha3_256(&long)), "d4728ea5e9f3819f2b4760151a8f802dbe9f941fd6fb59b3715892436555772a");
+	}
+
+	#[test]
+	fn test_shake() {
+		let mut out = [0 ; 32];
+		Shake::shake128(b"").squeeze(&mut out);
+		assert_eq!(hex(&out), "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26");
+
+		assert_eq!(hex(&shake256::<32>(b"abc")), "483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739");
+
+		// squeezing across block boundaries, in uneven pieces
+		let long: Vec<u8> = (0..512).map(|i| i as u8).collect();
+		let mut shake = Shake::shake128(&long);
+		let mut out = vec![0 ; 400];
+		for chunk in out.chunks_mut(7) {
+			shake.squeeze(chunk);
+		}
+		assert_eq!(hex(&out[368..]), "82cf694b47aa92dba1d24cde24937bad87de4eb7d263145c18d0ce38f2a0212e");
+	}
+}
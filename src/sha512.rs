@@ -1,9 +1,7 @@
 
 // MARK: Types
 
-use std::mem::transmute;
-
-use crate::padding;
+use crate::{padding, secret::Scrub};
 
 /// The words in the SHA-512 Algorithm
 type Word = u64;
@@ -69,18 +67,15 @@ const K: [Word ; ROUNDS] = [
 
 /// Splits an array of bytes into 1024 bit chunks. This assumes the bytes
 /// array is already padded to an appropriate length.
-fn bytes_to_chunks(bytes: Vec<u8>) -> Vec<Chunk> {
+fn bytes_to_chunks(bytes: &[u8]) -> Vec<Chunk> {
 	let mut chunks = vec![[0 ; CHUNK_WORD_COUNT] ; bytes.len() / CHUNK_BYTE_COUNT];
 
 	for i in 0..chunks.len() {
-		// each chunk is 16 words. we need those words!
-		chunks[i] = unsafe {
-			let be: [u8 ; CHUNK_BYTE_COUNT] = bytes[(i * CHUNK_BYTE_COUNT)..(i * CHUNK_BYTE_COUNT + CHUNK_BYTE_COUNT)].try_into().unwrap();
-			transmute(be)
-		};
+		// each chunk is 16 big-endian words. we need those words!
+		let chunk_bytes = &bytes[(i * CHUNK_BYTE_COUNT)..(i * CHUNK_BYTE_COUNT + CHUNK_BYTE_COUNT)];
 		
 		for j in 0..CHUNK_WORD_COUNT {
-			chunks[i][j] = (chunks[i][j] as u64).to_be();
+			chunks[i][j] = Word::from_be_bytes(chunk_bytes[(j * 8)..(j * 8 + 8)].try_into().unwrap());
 		}
 	}
 
@@ -89,16 +84,21 @@ fn bytes_to_chunks(bytes: Vec<u8>) -> Vec<Chunk> {
 
 pub fn hash(bytes: Vec<u8>) -> Digest {
 
-	let padded = padding::pad_sha512(bytes);
+	// the message may well be secret, so every copy of it is wiped once it has been used
+	let mut bytes = bytes;
+	let mut padded = padding::pad_sha512(&bytes);
+	bytes.scrub();
 
 	// now we divide it into chunks!
-	let chunks = bytes_to_chunks(padded);
+	let mut chunks = bytes_to_chunks(&padded);
+	padded.scrub();
 
 	let mut hash_buffer = H;
 
-	for chunk in chunks {
-		// message schedule
-		let mut w = [0u64 ; ROUNDS];
+	// message schedule
+	let mut w = [0u64 ; ROUNDS];
+
+	for chunk in chunks.iter() {
 
 		// copy chunk into first 16 words
 		for i in 0..CHUNK_WORD_COUNT {
@@ -136,21 +136,29 @@ pub fn hash(bytes: Vec<u8>) -> Digest {
 		for i in 0..8 {
 			hash_buffer[i] = hash_buffer[i].wrapping_add(local_buffer[i]);
 		}
+
+		local_buffer.scrub();
 	}
 
-	// we do this so that when we transmute, we don't end up with the bytes in the wrong order!
-	for i in 0..8 {
-		hash_buffer[i] = hash_buffer[i].to_be();
+	// the message schedule and working variables are derived from the message, which may well be secret
+	w.scrub();
+	chunks.scrub();
+
+	// the digest is the hash words, each written out big-endian
+	let mut digest = [0 ; DIGEST_BYTE_COUNT];
+	for i in 0..DIGEST_WORD_COUNT {
+		digest[(i * 8)..(i * 8 + 8)].copy_from_slice(&hash_buffer[i].to_be_bytes());
 	}
 
-	unsafe { transmute(hash_buffer) }
+	digest
 	
 }
 
 #[cfg(test)]
 mod tests {
 
-    use super::{hash, Digest};
+    use super::{bytes_to_chunks, hash, Digest};
+    use crate::padding;
 
 	#[test]
 	fn test_abc() {
@@ -165,6 +173,16 @@ mod tests {
 		assert_eq!(digest, known_digest);
 	}
 
+	#[test]
+	fn test_chunk_layout() {
+		// message words are big-endian, no matter the machine
+		let chunks = bytes_to_chunks(&padding::pad_sha512(vec![0x61, 0x62, 0x63]));
+
+		assert_eq!(chunks.len(), 1);
+		assert_eq!(chunks[0][0], 0x6162638000000000);
+		assert_eq!(chunks[0][15], 24);
+	}
+
 	#[test]
 	fn test_long_abc() {
 		let string = "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu".as_bytes().to_vec();
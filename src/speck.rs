@@ -1,9 +1,11 @@
 use core::panic;
-use std::{fs::File, io::{Read, Write}, mem::transmute, vec};
+use std::{fs::File, io::{BufReader, BufWriter, Read, Write}, thread, vec};
 
-use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
 
-use crate::{lwe::Ciphertext, padding, utility::PaddedFileStream};
+use crate::{lwe::Ciphertext, padding, secret::{Scrub, Secret}, utility::PaddedFileStream};
+
+mod simd;
 
 ///
 /// The Speck algorithm designed by the NSA, with a word size of 128 bits, and a 
@@ -19,57 +21,78 @@ pub const BLOCK_SIZE: usize = 16;
 /// The size, in bytes, of a key
 pub const KEY_SIZE: usize = 32;
 
+/// The size, in bytes, of a counter mode nonce
+pub const NONCE_SIZE: usize = 8;
+
 pub type Word = u64;
 pub type Block = [u8 ; BLOCK_SIZE];
-pub type Key = [u8 ; KEY_SIZE];
+pub type Key = Secret<[u8 ; KEY_SIZE]>;
+pub type Nonce = [u8 ; NONCE_SIZE];
 
-const ROUNDS: usize = 34;
+/// The number of rounds of the full cipher
+pub const ROUNDS: usize = 34;
 
 /// 
 /// Speck encryption scheme
 /// 
 
 pub fn gen() -> Key {
-	StdRng::from_entropy().gen()
+	gen_with_rng(&mut StdRng::from_entropy())
 }
 
-pub fn key_to_str(key: Key) -> String {
-	key.map(|b| format!("{:02x}", b)).concat()
+/// Generates a key using the supplied random number generator, so that a seeded
+/// generator always produces the same key.
+pub fn gen_with_rng(rng: &mut (impl RngCore + CryptoRng)) -> Key {
+	let mut key = Key::new([0 ; KEY_SIZE]);
+	rng.fill_bytes(key.expose_mut());
+	key
+}
+
+pub fn key_to_str(key: &Key) -> String {
+	key.expose().map(|b| format!("{:02x}", b)).concat()
 }
 
 pub fn str_to_key(keystr: String) -> Key {
-	let mut bytes = [0 ; KEY_SIZE];
+	let mut key = Key::new([0 ; KEY_SIZE]);
 
 	for i in 0..KEY_SIZE {
-		bytes[i] = u8::from_str_radix(&keystr[(i * 2)..=(i * 2 + 1)], 16).unwrap()
+		key.expose_mut()[i] = u8::from_str_radix(&keystr[(i * 2)..=(i * 2 + 1)], 16).unwrap()
 	}
 
-	bytes
+	key
 }
 
 /// 
 /// Utility Functions
 /// 
 
+/// Reads a block as its two words. Each 8 byte half is a little-endian word, and the
+/// second half is the x word, so the block `b0 b1 ... b15` is `x = b15...b8`, `y = b7...b0`,
+/// as in the implementation guide.
 fn bytes_to_words(bytes: [u8 ; 16]) -> [Word ; 2] {
-	let mut rev_block: [Word ; 2] = unsafe { transmute(bytes) };
-	rev_block.reverse();
-	rev_block
+	[
+		Word::from_le_bytes(bytes[8..16].try_into().unwrap()),
+		Word::from_le_bytes(bytes[0..8].try_into().unwrap())
+	]
 }
 
-const fn key_bytes_to_words(bytes: [u8 ; 32]) -> [Word ; 4] {
-	unsafe {
-		transmute(bytes)
+/// Reads a key as four little-endian words, (k0, l0, l1, l2)
+fn key_bytes_to_words(bytes: &[u8 ; 32]) -> [Word ; 4] {
+	let mut words = [0 ; 4];
+
+	for i in 0..4 {
+		words[i] = Word::from_le_bytes(bytes[(i * 8)..(i * 8 + 8)].try_into().unwrap());
 	}
+
+	words
 }
 
+/// The inverse of `bytes_to_words`
 fn words_to_bytes(block: [Word ; 2]) -> Block {
-	let mut rev_block = block;
-	rev_block.reverse();
-
-	unsafe {
-		transmute(rev_block)
-	}
+	let mut bytes = [0 ; BLOCK_SIZE];
+	bytes[0..8].copy_from_slice(&block[1].to_le_bytes());
+	bytes[8..16].copy_from_slice(&block[0].to_le_bytes());
+	bytes
 }
 
 ///
@@ -92,31 +115,91 @@ const fn speck128256_round_inv(x: &[Word ; 2], round_key: u64) -> [Word ; 2] {
 
 // MARK: Blocks
 
-pub fn enc_block(key: Key, plaintext: Block) -> Block {
+pub fn enc_block(key: &Key, plaintext: Block) -> Block {
+	enc_block_scheduled(speck128256_key_schedule(key).expose(), plaintext)
+}
 
-	let keys = speck128256_key_schedule(key);
-	let mut ciphertext = bytes_to_words(plaintext);
+/// Encrypts a block with an already expanded key schedule, so that callers encrypting
+/// many blocks only have to expand the key once.
+fn enc_block_scheduled(keys: &[Word ; ROUNDS], plaintext: Block) -> Block {
+	words_to_bytes(enc_words_rounds(keys, bytes_to_words(plaintext), ROUNDS))
+}
+
+/// Runs only the first `rounds` rounds of Speck128/256 on the words of a block
+pub(crate) fn enc_words_rounds(keys: &[Word ; ROUNDS], plaintext: [Word ; 2], rounds: usize) -> [Word ; 2] {
+	let mut ciphertext = plaintext;
+
+	for k in &keys[..rounds] {
+		ciphertext = speck128256_round(ciphertext, *k);
+	}
+
+	ciphertext
+}
+
+/// Undoes the first `rounds` rounds of Speck128/256 on the words of a block
+pub(crate) fn dec_words_rounds(keys: &[Word ; ROUNDS], ciphertext: [Word ; 2], rounds: usize) -> [Word ; 2] {
+	let mut plaintext = ciphertext;
 
-	for i in 0..ROUNDS {
-		ciphertext = speck128256_round(ciphertext, keys[i]);
+	for k in keys[..rounds].iter().rev() {
+		plaintext = speck128256_round_inv(&plaintext, *k);
 	}
 
-	words_to_bytes(ciphertext)
+	plaintext
 }
 
-pub fn dec_block(key: Key, ciphertext: Block) -> Block {
+/// Encrypts a block with a reduced-round Speck128/256, which runs only the first `rounds` of the 
+/// 34 rounds. This is NOT secure, and is only here for studying the cipher.
+pub fn enc_block_rounds(key: &Key, plaintext: Block, rounds: usize) -> Block {
+	assert!(rounds <= ROUNDS, "Speck128/256 only has {} rounds", ROUNDS);
+	words_to_bytes(enc_words_rounds(speck128256_key_schedule(key).expose(), bytes_to_words(plaintext), rounds))
+}
+
+/// Decrypts a block encrypted with `enc_block_rounds`
+pub fn dec_block_rounds(key: &Key, ciphertext: Block, rounds: usize) -> Block {
+	assert!(rounds <= ROUNDS, "Speck128/256 only has {} rounds", ROUNDS);
+	words_to_bytes(dec_words_rounds(speck128256_key_schedule(key).expose(), bytes_to_words(ciphertext), rounds))
+}
+
+/// Encrypts every block of `blocks` in place, independently of each other (so, ECB style).
+/// 
+/// Several blocks are encrypted at once with SIMD instructions when the processor supports them,
+/// and the result is always identical to calling `enc_block` on each block.
+pub fn enc_blocks(key: &Key, blocks: &mut [Block]) {
+	enc_blocks_scheduled(speck128256_key_schedule(key).expose(), blocks)
+}
+
+fn enc_blocks_scheduled(keys: &[Word ; ROUNDS], blocks: &mut [Block]) {
+	let mut x = vec![0 ; blocks.len()];
+	let mut y = vec![0 ; blocks.len()];
+
+	for i in 0..blocks.len() {
+		[x[i], y[i]] = bytes_to_words(blocks[i]);
+	}
+
+	simd::enc_words(keys, &mut x, &mut y);
+
+	for i in 0..blocks.len() {
+		blocks[i] = words_to_bytes([x[i], y[i]]);
+	}
+
+	// these held the plaintext (or the keystream, in counter mode) word by word
+	x.scrub();
+	y.scrub();
+}
+
+pub fn dec_block(key: &Key, ciphertext: Block) -> Block {
 
 	let keys = speck128256_key_schedule(key);
 	let mut plaintext = bytes_to_words(ciphertext);
 
 	for i in (0..ROUNDS).rev() {
-		plaintext = speck128256_round_inv(&plaintext, keys[i]);
+		plaintext = speck128256_round_inv(&plaintext, keys.expose()[i]);
 	}
 
 	words_to_bytes(plaintext)
 }
 
-pub fn enc_block_chain(key: Key, previous_ct: Block, plaintext: Block) -> Block {
+pub fn enc_block_chain(key: &Key, previous_ct: Block, plaintext: Block) -> Block {
 	let mut to_encrypt = [0 ; BLOCK_SIZE];
 	for i in 0..BLOCK_SIZE {
 		to_encrypt[i] = previous_ct[i] ^ plaintext[i];
@@ -125,7 +208,7 @@ pub fn enc_block_chain(key: Key, previous_ct: Block, plaintext: Block) -> Block
 	enc_block(key, to_encrypt)
 }
 
-pub fn dec_block_chain(key: Key, previous_ct: Block, ciphertext: Block) -> Block {
+pub fn dec_block_chain(key: &Key, previous_ct: Block, ciphertext: Block) -> Block {
 	let mut pt = dec_block(key, ciphertext);
 	for i in 0..BLOCK_SIZE {
 		pt[i] ^= previous_ct[i]
@@ -160,7 +243,7 @@ pub fn blocks_to_bytes(blocks: Vec<Block>) -> Vec<u8> {
 }
 
 /// Encrypts a byte vector
-pub fn enc_vec(key: Key, plaintext: Vec<u8>) -> Vec<u8> {
+pub fn enc_vec(key: &Key, plaintext: Vec<u8>) -> Vec<u8> {
 	let padded_pt = padding::pad_sha256(plaintext);
 	let pt_blocks = bytes_to_blocks(padded_pt);
 	let mut ct_blocks = vec![[0 ; BLOCK_SIZE] ; pt_blocks.len()];
@@ -174,8 +257,8 @@ pub fn enc_vec(key: Key, plaintext: Vec<u8>) -> Vec<u8> {
 	blocks_to_bytes(ct_blocks)
 }
 
-/// Decrypts a byte vector
-pub fn dec_vec(key: Key, ciphertext: Vec<u8>) -> Vec<u8> {
+/// Decrypts the blocks of a byte vector, leaving the padding on
+fn dec_blocks(key: &Key, ciphertext: Vec<u8>) -> Vec<u8> {
 	let ct_blocks = bytes_to_blocks(ciphertext);
 	let mut pt_blocks = vec![[0 ; BLOCK_SIZE] ; ct_blocks.len()];
 
@@ -185,7 +268,26 @@ pub fn dec_vec(key: Key, ciphertext: Vec<u8>) -> Vec<u8> {
 		pt_blocks[i] = dec_block_chain(key, ct_blocks[i - 1], ct_blocks[i]);
 	}
 
-	padding::unpad_sha256(blocks_to_bytes(pt_blocks))
+	blocks_to_bytes(pt_blocks)
+}
+
+/// Decrypts a byte vector
+pub fn dec_vec(key: &Key, ciphertext: Vec<u8>) -> Vec<u8> {
+	padding::unpad_sha256(dec_blocks(key, ciphertext))
+}
+
+/// Decrypts a byte vector, or gives `None` if it isn't a whole number of blocks or its padding is
+/// corrupted, for ciphertexts that might not have come from `enc_vec`
+pub fn try_dec_vec(key: &Key, ciphertext: Vec<u8>) -> Option<Vec<u8>> {
+	if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(BLOCK_SIZE) {
+		return None;
+	}
+
+	let mut padded = dec_blocks(key, ciphertext);
+	let plaintext = padding::try_unpad_sha256(&padded);
+	padded.scrub();
+
+	plaintext
 }
 
 // MARK: Files
@@ -196,7 +298,7 @@ pub fn dec_vec(key: Key, ciphertext: Vec<u8>) -> Vec<u8> {
  * This assumes that the input stream is already padded, so no additional processing
  * is done on the input stream. Block Chaining is just XORing.
  */
-pub fn enc(key: Key, plaintext: &mut File, ciphertext: &mut File) {
+pub fn enc(key: &Key, plaintext: &mut File, ciphertext: &mut File) {
 
 	let mut padded_pt = PaddedFileStream::<'_, {BLOCK_SIZE}>::new(plaintext);
 
@@ -224,7 +326,7 @@ pub fn enc(key: Key, plaintext: &mut File, ciphertext: &mut File) {
  * It is assumed that the plaintext write stream is already empty, as no processing
  * is done to ensure that the file doesn't have extraneous junk.
  */
-pub fn dec(key: Key, ciphertext: &mut File, plaintext: &mut File) {
+pub fn dec(key: &Key, ciphertext: &mut File, plaintext: &mut File) {
 
 	let mut xor_input 	= [0 ; BLOCK_SIZE];
 	let mut ct_block	= [0 ; BLOCK_SIZE];
@@ -285,15 +387,208 @@ pub fn dec(key: Key, ciphertext: &mut File, plaintext: &mut File) {
 	}
 }
 
-fn speck128256_key_schedule(key: Key) -> [Word ; ROUNDS] {
-	let mut keys = [0; ROUNDS];
-	let mut constants = key_bytes_to_words(key);
+// MARK: Counter Mode
+
+/// The default amount of bytes each thread encrypts at a time in parallel counter mode
+pub const DEFAULT_SEGMENT_SIZE: usize = 1 << 20;
+
+/// How many keystream blocks are generated at once in counter mode
+const KEYSTREAM_BATCH: usize = 64;
+
+/// Configuration for the parallel counter mode functions, made with `new` so that the segment
+/// size is always one the counter arithmetic works for
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelConfig {
+	/// How many threads encrypt segments at the same time
+	threads: usize,
+
+	/// How many bytes each thread handles per segment, a nonzero multiple of `BLOCK_SIZE`
+	segment_size: usize
+}
+
+impl ParallelConfig {
+	/// A configuration with up to `threads` threads at a time (at least one), each handling
+	/// `segment_size` bytes per segment. Fails with `InvalidInput` unless the segment size is a
+	/// nonzero multiple of `BLOCK_SIZE`, since each segment has to start on a block boundary.
+	pub fn new(threads: usize, segment_size: usize) -> std::io::Result<ParallelConfig> {
+		if segment_size == 0 || !segment_size.is_multiple_of(BLOCK_SIZE) {
+			return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Segment size {:?} is not a nonzero multiple of the block size", segment_size)));
+		}
+
+		Ok(ParallelConfig { threads: threads.max(1), segment_size })
+	}
+
+	/// How many threads encrypt segments at the same time
+	pub fn threads(&self) -> usize {
+		self.threads
+	}
+
+	/// How many bytes each thread handles per segment
+	pub fn segment_size(&self) -> usize {
+		self.segment_size
+	}
+}
+
+impl Default for ParallelConfig {
+	fn default() -> Self {
+		ParallelConfig {
+			threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+			segment_size: DEFAULT_SEGMENT_SIZE
+		}
+	}
+}
+
+/// The counter block for the block at `index` in the stream: the nonce followed by the
+/// big-endian block index.
+fn counter_block(nonce: Nonce, index: u64) -> Block {
+	let mut block = [0 ; BLOCK_SIZE];
+	block[..NONCE_SIZE].copy_from_slice(&nonce);
+	block[NONCE_SIZE..].copy_from_slice(&index.to_be_bytes());
+	block
+}
+
+/// XORs the counter mode keystream into `data`, which starts at block `first_block` of the stream.
+/// The final block of `data` may be partial.
+fn ctr_xor_scheduled(keys: &[Word ; ROUNDS], nonce: Nonce, first_block: u64, data: &mut [u8]) {
+	let mut keystream = [[0 ; BLOCK_SIZE] ; KEYSTREAM_BATCH];
+
+	for (b, batch) in data.chunks_mut(BLOCK_SIZE * KEYSTREAM_BATCH).enumerate() {
+		let batch_first_block = first_block + (b * KEYSTREAM_BATCH) as u64;
+		let block_count = batch.len().div_ceil(BLOCK_SIZE);
+
+		for (i, block) in keystream[..block_count].iter_mut().enumerate() {
+			*block = counter_block(nonce, batch_first_block + i as u64);
+		}
+
+		enc_blocks_scheduled(keys, &mut keystream[..block_count]);
+
+		for (byte, k) in batch.iter_mut().zip(keystream.as_flattened()) {
+			*byte ^= k;
+		}
+	}
+
+	keystream.scrub();
+}
+
+/// XORs the counter mode keystream into `data`, which starts at block `first_block` of the stream.
+/// 
+/// Counter mode is its own inverse, so this both encrypts and decrypts. A nonce must never be
+/// used twice with the same key.
+pub fn ctr_xor(key: &Key, nonce: Nonce, first_block: u64, data: &mut [u8]) {
+	ctr_xor_scheduled(speck128256_key_schedule(key).expose(), nonce, first_block, data)
+}
+
+/// Same as `ctr_xor`, but the data is split into segments of `config.segment_size` bytes
+/// which are processed by up to `config.threads` threads at a time.
+/// 
+/// The result is identical to `ctr_xor` with the same key, nonce and starting block.
+pub fn par_ctr_xor(key: &Key, nonce: Nonce, first_block: u64, data: &mut [u8], config: &ParallelConfig) {
+	par_ctr_xor_scheduled(speck128256_key_schedule(key).expose(), nonce, first_block, data, config)
+}
+
+fn par_ctr_xor_scheduled(keys: &[Word ; ROUNDS], nonce: Nonce, first_block: u64, data: &mut [u8], config: &ParallelConfig) {
+	let blocks_per_segment = (config.segment_size / BLOCK_SIZE) as u64;
+	let batch_size = config.segment_size * config.threads;
+
+	for (b, batch) in data.chunks_mut(batch_size).enumerate() {
+		let batch_first_block = first_block + (b * batch_size / BLOCK_SIZE) as u64;
+
+		thread::scope(|scope| {
+			for (i, segment) in batch.chunks_mut(config.segment_size).enumerate() {
+				scope.spawn(move || {
+					ctr_xor_scheduled(keys, nonce, batch_first_block + i as u64 * blocks_per_segment, segment)
+				});
+			}
+		});
+	}
+}
+
+/// Encrypts everything from `plaintext` into `ciphertext` in counter mode, returning the number of 
+/// bytes written. No padding is needed, so the ciphertext is exactly as long as the plaintext.
+pub fn enc_ctr(key: &Key, nonce: Nonce, plaintext: &mut impl Read, ciphertext: &mut impl Write) -> std::io::Result<u64> {
+	let keys = speck128256_key_schedule(key);
+	stream_ctr(plaintext, ciphertext, DEFAULT_SEGMENT_SIZE, |first_block, data| {
+		ctr_xor_scheduled(keys.expose(), nonce, first_block, data)
+	})
+}
+
+/// Decrypts a counter mode stream produced by `enc_ctr` or `par_enc_ctr`
+pub fn dec_ctr(key: &Key, nonce: Nonce, ciphertext: &mut impl Read, plaintext: &mut impl Write) -> std::io::Result<u64> {
+	enc_ctr(key, nonce, ciphertext, plaintext)
+}
+
+/// Encrypts everything from `plaintext` into `ciphertext` in counter mode using several threads.
+/// 
+/// The output is byte-for-byte identical to `enc_ctr` with the same key and nonce.
+pub fn par_enc_ctr(key: &Key, nonce: Nonce, plaintext: &mut impl Read, ciphertext: &mut impl Write, config: &ParallelConfig) -> std::io::Result<u64> {
+	let keys = speck128256_key_schedule(key);
+	stream_ctr(plaintext, ciphertext, config.segment_size * config.threads, |first_block, data| {
+		par_ctr_xor_scheduled(keys.expose(), nonce, first_block, data, config)
+	})
+}
+
+/// Decrypts a counter mode stream using several threads
+pub fn par_dec_ctr(key: &Key, nonce: Nonce, ciphertext: &mut impl Read, plaintext: &mut impl Write, config: &ParallelConfig) -> std::io::Result<u64> {
+	par_enc_ctr(key, nonce, ciphertext, plaintext, config)
+}
+
+/// Reads the input in batches of `batch_size` bytes, runs `apply` on each batch along with the
+/// index of its first block, and writes it out, all through buffered streams.
+fn stream_ctr<F>(input: &mut impl Read, output: &mut impl Write, batch_size: usize, mut apply: F) -> std::io::Result<u64> 
+	where F: FnMut(u64, &mut [u8]) {
+
+	let mut reader = BufReader::new(input);
+	let mut writer = BufWriter::new(output);
+
+	// holds plaintext on one side or the other, so it is wiped however this returns
+	let mut batch = Secret::new(vec![0 ; batch_size]);
+	let buffer = batch.expose_mut();
+	let mut block_index = 0;
+	let mut total = 0;
+
+	loop {
+		// fill the whole buffer, so that every batch but the last is a multiple of the block size
+		let mut filled = 0;
+		while filled < batch_size {
+			match reader.read(&mut buffer[filled..]) {
+				Ok(0) => break,
+				Ok(t) => filled += t,
+				Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(e)
+			}
+		}
+
+		if filled == 0 {
+			break;
+		}
+
+		apply(block_index, &mut buffer[..filled]);
+		writer.write_all(&buffer[..filled])?;
+
+		block_index += (filled / BLOCK_SIZE) as u64;
+		total += filled as u64;
+
+		if filled < batch_size {
+			break;
+		}
+	}
+
+	writer.flush()?;
+	Ok(total)
+}
+
+/// Expands a key into the round keys, which are just as secret as the key itself
+pub(crate) fn speck128256_key_schedule(key: &Key) -> Secret<[Word ; ROUNDS]> {
+	let mut schedule = Secret::new([0 ; ROUNDS]);
+	let keys = schedule.expose_mut();
+	let mut constants = key_bytes_to_words(key.expose());
+	let mut round_map = [0 ; 2];
 	let mut i = 0;
 
 	while i < 33 {
 		keys[i] = constants[0];
 
-		let mut round_map = speck128256_round([constants[1], constants[0]], i as u64);
+		round_map = speck128256_round([constants[1], constants[0]], i as u64);
 		constants[1] = round_map[0];
 		constants[0] = round_map[1];
 
@@ -313,8 +608,10 @@ fn speck128256_key_schedule(key: Key) -> [Word ; ROUNDS] {
 	}
 
 	keys[33] = constants[0];
+	constants.scrub();
+	round_map.scrub();
 
-	keys
+	schedule
 }
 
 
@@ -327,6 +624,8 @@ fn speck128256_key_schedule(key: Key) -> [Word ; ROUNDS] {
 #[cfg(test)]
 mod tests {
 
+	use rand::Rng;
+
 	use super::*;
 
 	const PT_BYTES: [u8 ; 16] = [
@@ -376,15 +675,29 @@ mod tests {
 	fn test_gen() {
 
 		for _ in 1..=10 {
-			let key = unsafe {
-				transmute::<[u8 ; KEY_SIZE], [u128 ; 2]>(gen())
-			};
-
-			println!("{:?}", key);
+			println!("{:?}", key_to_str(&gen()));
 		}
 		
 	}
 
+	#[test]
+	fn test_gen_with_rng() {
+		let first = gen_with_rng(&mut StdRng::seed_from_u64(0x5eed));
+		let second = gen_with_rng(&mut StdRng::seed_from_u64(0x5eed));
+		let other = gen_with_rng(&mut StdRng::seed_from_u64(0x5eee));
+
+		assert_eq!(first, second);
+		assert_ne!(first, other);
+	}
+
+	#[test]
+	fn test_byte_layout() {
+		// these layouts are part of the format, and must not depend on the machine
+		assert_eq!(bytes_to_words(PT_BYTES), [0x65736f6874206e49, 0x202e72656e6f6f70]);
+		assert_eq!(words_to_bytes([0x65736f6874206e49, 0x202e72656e6f6f70]), PT_BYTES);
+		assert_eq!(key_bytes_to_words(&K_BYTES), [0x0706050403020100, 0x0f0e0d0c0b0a0908, 0x1716151413121110, 0x1f1e1d1c1b1a1918]);
+	}
+
 	#[test]
 	fn test_rounds() {
 		// Make sure that each round of encryption yields the correct result
@@ -410,17 +723,32 @@ mod tests {
 
 	#[test]
 	fn test_key_schedule() {
-		assert_eq!(speck128256_key_schedule(K_BYTES), KNOWN_KEY_SCHEDULE)
+		assert_eq!(*speck128256_key_schedule(&Key::new(K_BYTES)).expose(), KNOWN_KEY_SCHEDULE)
 	}
 
 	#[test]
 	fn test_speck_enc() {
-		assert_eq!(enc_block(K_BYTES, PT_BYTES), words_to_bytes(KNOWN_ROUND_RESULTS_WORDS[ROUNDS - 1]));
+		assert_eq!(enc_block(&Key::new(K_BYTES), PT_BYTES), words_to_bytes(KNOWN_ROUND_RESULTS_WORDS[ROUNDS - 1]));
 	}
 
 	#[test]
 	fn test_speck_dec() {
-		assert_eq!(dec_block(K_BYTES, words_to_bytes(KNOWN_ROUND_RESULTS_WORDS[ROUNDS - 1])), PT_BYTES);
+		assert_eq!(dec_block(&Key::new(K_BYTES), words_to_bytes(KNOWN_ROUND_RESULTS_WORDS[ROUNDS - 1])), PT_BYTES);
+	}
+
+	#[test]
+	fn test_reduced_rounds() {
+		let ct = words_to_bytes(KNOWN_ROUND_RESULTS_WORDS[ROUNDS - 1]);
+		assert_eq!(enc_block_rounds(&Key::new(K_BYTES), PT_BYTES, ROUNDS), ct);
+		assert_eq!(dec_block_rounds(&Key::new(K_BYTES), ct, ROUNDS), PT_BYTES);
+
+		for rounds in 1..=ROUNDS {
+			let reduced = enc_block_rounds(&Key::new(K_BYTES), PT_BYTES, rounds);
+			assert_eq!(bytes_to_words(reduced), KNOWN_ROUND_RESULTS_WORDS[rounds - 1]);
+			assert_eq!(dec_block_rounds(&Key::new(K_BYTES), reduced, rounds), PT_BYTES);
+		}
+
+		assert_eq!(enc_block_rounds(&Key::new(K_BYTES), PT_BYTES, 0), PT_BYTES);
 	}
 
 	#[test]
@@ -428,18 +756,116 @@ mod tests {
 		let silly_str = "0000000000000000000000000000000000000000000000000000000000000000".to_string();
 		let silly_key = str_to_key(silly_str);
 		for i in 0..KEY_SIZE {
-			assert_eq!(silly_key[i], 0)
+			assert_eq!(silly_key.expose()[i], 0)
 		}
 
 		for _ in 0..100 {
 			let key = gen();
-			println!("{:?}", key_to_str(key));
-			let key_str = key_to_str(key);
+			println!("{:?}", key_to_str(&key));
+			let key_str = key_to_str(&key);
 			let recovered = str_to_key(key_str);
 			assert_eq!(recovered, key)
 		}
 	}
 
+	#[test]
+	fn test_enc_blocks() {
+		let mut rng = StdRng::seed_from_u64(28);
+
+		for len in [0, 1, 2, 3, 4, 5, 7, 8, 9, 33, 100] {
+			let key = gen_with_rng(&mut rng);
+			let blocks: Vec<Block> = (0..len).map(|_| rng.gen()).collect();
+			let expected: Vec<Block> = blocks.iter().map(|b| enc_block(&key, *b)).collect();
+
+			let mut automatic = blocks.clone();
+			enc_blocks(&key, &mut automatic);
+			assert_eq!(automatic, expected);
+
+			// every backend this machine can run must agree with the scalar code
+			let keys = speck128256_key_schedule(&key);
+			for backend in simd::Backend::available() {
+				let mut x: Vec<Word> = blocks.iter().map(|b| bytes_to_words(*b)[0]).collect();
+				let mut y: Vec<Word> = blocks.iter().map(|b| bytes_to_words(*b)[1]).collect();
+
+				simd::enc_words_with(backend, keys.expose(), &mut x, &mut y);
+
+				for i in 0..len {
+					assert_eq!(words_to_bytes([x[i], y[i]]), expected[i], "{:?} backend disagrees on block {}", backend, i);
+				}
+			}
+		}
+
+		// the known answer from the implementation guide
+		let mut blocks = [PT_BYTES ; 5];
+		enc_blocks(&Key::new(K_BYTES), &mut blocks);
+		for block in blocks {
+			assert_eq!(block, words_to_bytes(KNOWN_ROUND_RESULTS_WORDS[ROUNDS - 1]));
+		}
+	}
+
+	#[test]
+	fn test_parallel_config() {
+		for segment_size in [0, 1, 15, 17, 4097] {
+			let error = ParallelConfig::new(4, segment_size).unwrap_err();
+			assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+		}
+
+		let config = ParallelConfig::new(0, 32).unwrap();
+		assert_eq!((config.threads(), config.segment_size()), (1, 32));
+	}
+
+	#[test]
+	fn test_par_ctr_matches_serial() {
+		let mut rng = StdRng::seed_from_u64(27);
+		let key = gen_with_rng(&mut rng);
+		let nonce: Nonce = rng.gen();
+
+		for len in [0, 1, 15, 16, 17, 1000, 4096, 70001] {
+			let mut data = vec![0u8 ; len];
+			rng.fill(&mut data[..]);
+
+			let mut serial = data.clone();
+			ctr_xor(&key, nonce, 0, &mut serial);
+
+			for (threads, segment_size) in [(1, 16), (3, 64), (4, 1024), (8, 4096)] {
+				let config = ParallelConfig::new(threads, segment_size).unwrap();
+				let mut parallel = data.clone();
+				par_ctr_xor(&key, nonce, 0, &mut parallel, &config);
+				assert_eq!(serial, parallel);
+
+				par_ctr_xor(&key, nonce, 0, &mut parallel, &config);
+				assert_eq!(data, parallel);
+			}
+		}
+	}
+
+	#[test]
+	fn test_ctr_streams() {
+		let mut rng = StdRng::seed_from_u64(28);
+		let key = gen_with_rng(&mut rng);
+		let nonce: Nonce = rng.gen();
+
+		let mut plaintext = vec![0u8 ; 100_003];
+		rng.fill(&mut plaintext[..]);
+
+		let mut serial = Vec::new();
+		let written = enc_ctr(&key, nonce, &mut plaintext.as_slice(), &mut serial).unwrap();
+		assert_eq!(written, plaintext.len() as u64);
+
+		let config = ParallelConfig::new(4, 4096).unwrap();
+		let mut parallel = Vec::new();
+		par_enc_ctr(&key, nonce, &mut plaintext.as_slice(), &mut parallel, &config).unwrap();
+		assert_eq!(serial, parallel);
+
+		let mut recovered = Vec::new();
+		par_dec_ctr(&key, nonce, &mut parallel.as_slice(), &mut recovered, &config).unwrap();
+		assert_eq!(plaintext, recovered);
+
+		let mut recovered = Vec::new();
+		dec_ctr(&key, nonce, &mut serial.as_slice(), &mut recovered).unwrap();
+		assert_eq!(plaintext, recovered);
+	}
+
 	#[test]
 	fn test_vec_enc() {
 		for _ in 0..100 {
@@ -451,8 +877,8 @@ mod tests {
 				rand_vec[i] = rand::thread_rng().gen();
 			}
 
-			let ct = enc_vec(key, rand_vec.clone());
-			let decrypted = dec_vec(key, ct);
+			let ct = enc_vec(&key, rand_vec.clone());
+			let decrypted = dec_vec(&key, ct);
 			assert_eq!(rand_vec, decrypted);
 		}
 	}
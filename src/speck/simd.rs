@@ -0,0 +1,187 @@
+//
+// Multi-block Speck128/256. Since every block goes through exactly the same
+// sequence of additions, rotations and XORs, several blocks can be pushed through
+// the rounds at once, one per vector lane.
+//
+// Blocks are handed over already split into their x and y words, so lane i of the
+// backends holds block i.
+//
+
+use super::{Word, ROUNDS};
+
+/// The different ways of running several blocks through Speck at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Backend {
+	/// Plain Rust over arrays of four words, which the compiler is free to vectorize
+	Portable,
+
+	/// Two blocks at a time in 128-bit SSE2 registers
+	#[cfg(target_arch = "x86_64")]
+	Sse2,
+
+	/// Four blocks at a time in 256-bit AVX2 registers
+	#[cfg(target_arch = "x86_64")]
+	Avx2
+}
+
+impl Backend {
+	/// The fastest backend this machine supports
+	pub(super) fn detect() -> Backend {
+		#[cfg(target_arch = "x86_64")]
+		{
+			if is_x86_feature_detected!("avx2") {
+				return Backend::Avx2;
+			}
+
+			// every x86_64 processor has SSE2
+			return Backend::Sse2;
+		}
+
+		#[allow(unreachable_code)]
+		Backend::Portable
+	}
+
+	/// Every backend that can run on this machine, for testing them against each other
+	#[cfg(test)]
+	pub(super) fn available() -> Vec<Backend> {
+		#[allow(unused_mut)]
+		let mut backends = vec![Backend::Portable];
+
+		#[cfg(target_arch = "x86_64")]
+		{
+			backends.push(Backend::Sse2);
+			if is_x86_feature_detected!("avx2") {
+				backends.push(Backend::Avx2);
+			}
+		}
+
+		backends
+	}
+}
+
+/// Encrypts the blocks whose words are `(x[i], y[i])` in place, using the fastest available backend
+pub(super) fn enc_words(keys: &[Word ; ROUNDS], x: &mut [Word], y: &mut [Word]) {
+	enc_words_with(Backend::detect(), keys, x, y)
+}
+
+/// Encrypts the blocks whose words are `(x[i], y[i])` in place, using a specific backend
+pub(super) fn enc_words_with(backend: Backend, keys: &[Word ; ROUNDS], x: &mut [Word], y: &mut [Word]) {
+	assert_eq!(x.len(), y.len());
+
+	match backend {
+		Backend::Portable => enc_words_portable(keys, x, y),
+
+		// SAFETY: SSE2 is part of the x86_64 baseline, and AVX2 is only picked when detected.
+		#[cfg(target_arch = "x86_64")]
+		Backend::Sse2 => unsafe { x86::enc_words_sse2(keys, x, y) },
+
+		#[cfg(target_arch = "x86_64")]
+		Backend::Avx2 => unsafe { x86::enc_words_avx2(keys, x, y) }
+	}
+}
+
+/// The scalar round function, for whatever doesn't fill up a whole vector
+#[inline(always)]
+fn round(x: &mut Word, y: &mut Word, k: Word) {
+	*x = x.rotate_right(8).wrapping_add(*y) ^ k;
+	*y = y.rotate_left(3) ^ *x;
+}
+
+const PORTABLE_LANES: usize = 4;
+
+fn enc_words_portable(keys: &[Word ; ROUNDS], x: &mut [Word], y: &mut [Word]) {
+	let mut x_chunks = x.chunks_exact_mut(PORTABLE_LANES);
+	let mut y_chunks = y.chunks_exact_mut(PORTABLE_LANES);
+
+	for (xs, ys) in (&mut x_chunks).zip(&mut y_chunks) {
+		let mut xl: [Word ; PORTABLE_LANES] = xs.try_into().unwrap();
+		let mut yl: [Word ; PORTABLE_LANES] = ys.try_into().unwrap();
+
+		for k in keys {
+			for l in 0..PORTABLE_LANES {
+				round(&mut xl[l], &mut yl[l], *k);
+			}
+		}
+
+		xs.copy_from_slice(&xl);
+		ys.copy_from_slice(&yl);
+	}
+
+	enc_words_scalar(keys, x_chunks.into_remainder(), y_chunks.into_remainder());
+}
+
+fn enc_words_scalar(keys: &[Word ; ROUNDS], x: &mut [Word], y: &mut [Word]) {
+	for (xi, yi) in x.iter_mut().zip(y.iter_mut()) {
+		for k in keys {
+			round(xi, yi, *k);
+		}
+	}
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+	use std::arch::x86_64::*;
+
+	use super::{enc_words_scalar, Word, ROUNDS};
+
+	#[target_feature(enable = "sse2")]
+	pub(super) unsafe fn enc_words_sse2(keys: &[Word ; ROUNDS], x: &mut [Word], y: &mut [Word]) {
+		const LANES: usize = 2;
+
+		let mut x_chunks = x.chunks_exact_mut(LANES);
+		let mut y_chunks = y.chunks_exact_mut(LANES);
+
+		for (xs, ys) in (&mut x_chunks).zip(&mut y_chunks) {
+			let mut xv = _mm_loadu_si128(xs.as_ptr() as *const __m128i);
+			let mut yv = _mm_loadu_si128(ys.as_ptr() as *const __m128i);
+
+			for k in keys {
+				let kv = _mm_set1_epi64x(*k as i64);
+
+				// x = ((x >>> 8) + y) ^ k
+				xv = _mm_or_si128(_mm_srli_epi64::<8>(xv), _mm_slli_epi64::<56>(xv));
+				xv = _mm_xor_si128(_mm_add_epi64(xv, yv), kv);
+
+				// y = (y <<< 3) ^ x
+				yv = _mm_or_si128(_mm_slli_epi64::<3>(yv), _mm_srli_epi64::<61>(yv));
+				yv = _mm_xor_si128(yv, xv);
+			}
+
+			_mm_storeu_si128(xs.as_mut_ptr() as *mut __m128i, xv);
+			_mm_storeu_si128(ys.as_mut_ptr() as *mut __m128i, yv);
+		}
+
+		enc_words_scalar(keys, x_chunks.into_remainder(), y_chunks.into_remainder());
+	}
+
+	#[target_feature(enable = "avx2")]
+	pub(super) unsafe fn enc_words_avx2(keys: &[Word ; ROUNDS], x: &mut [Word], y: &mut [Word]) {
+		const LANES: usize = 4;
+
+		let mut x_chunks = x.chunks_exact_mut(LANES);
+		let mut y_chunks = y.chunks_exact_mut(LANES);
+
+		for (xs, ys) in (&mut x_chunks).zip(&mut y_chunks) {
+			let mut xv = _mm256_loadu_si256(xs.as_ptr() as *const __m256i);
+			let mut yv = _mm256_loadu_si256(ys.as_ptr() as *const __m256i);
+
+			for k in keys {
+				let kv = _mm256_set1_epi64x(*k as i64);
+
+				// x = ((x >>> 8) + y) ^ k
+				xv = _mm256_or_si256(_mm256_srli_epi64::<8>(xv), _mm256_slli_epi64::<56>(xv));
+				xv = _mm256_xor_si256(_mm256_add_epi64(xv, yv), kv);
+
+				// y = (y <<< 3) ^ x
+				yv = _mm256_or_si256(_mm256_slli_epi64::<3>(yv), _mm256_srli_epi64::<61>(yv));
+				yv = _mm256_xor_si256(yv, xv);
+			}
+
+			_mm256_storeu_si256(xs.as_mut_ptr() as *mut __m256i, xv);
+			_mm256_storeu_si256(ys.as_mut_ptr() as *mut __m256i, yv);
+		}
+
+		// whatever is left over still benefits from two lanes at a time
+		enc_words_sse2(keys, x_chunks.into_remainder(), y_chunks.into_remainder());
+	}
+}
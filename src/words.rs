@@ -0,0 +1,240 @@
+//
+// Arithmetic on unsigned integers written as arrays of 64-bit words, least significant word
+// first. This is shared by the big prime field of `secsharing` and the integers of any size
+// that lattice reduction needs, so the carry and borrow handling lives in one place.
+//
+
+use std::cmp::Ordering;
+
+/// A machine word, one "digit" of a number
+pub type Word = u64;
+
+/// Drops the most significant words that are 0, so that every number has only one representation
+pub(crate) fn trim(words: &mut Vec<Word>) {
+	while words.last() == Some(&0) {
+		words.pop();
+	}
+}
+
+/// Compares two numbers, which don't have to be the same length or trimmed
+pub(crate) fn cmp(a: &[Word], b: &[Word]) -> Ordering {
+	let len = a.len().max(b.len());
+
+	for i in (0..len).rev() {
+		let x = a.get(i).copied().unwrap_or(0);
+		let y = b.get(i).copied().unwrap_or(0);
+
+		if x != y {
+			return x.cmp(&y);
+		}
+	}
+
+	Ordering::Equal
+}
+
+/// Adds `b` to `a` in place, where `b` is no longer than `a`, returning the carry out of the top word
+pub(crate) fn add_assign(a: &mut [Word], b: &[Word]) -> bool {
+	let mut carry = false;
+
+	for (i, x) in a.iter_mut().enumerate() {
+		let (sum, c1) = x.overflowing_add(b.get(i).copied().unwrap_or(0));
+		let (sum, c2) = sum.overflowing_add(carry as Word);
+		*x = sum;
+		carry = c1 || c2;
+	}
+
+	carry
+}
+
+/// Subtracts `b` from `a` in place, where `b` is no longer than `a`, returning the borrow out of
+/// the top word, which is only set if b > a
+pub(crate) fn sub_assign(a: &mut [Word], b: &[Word]) -> bool {
+	let mut borrow = false;
+
+	for (i, x) in a.iter_mut().enumerate() {
+		let (diff, b1) = x.overflowing_sub(b.get(i).copied().unwrap_or(0));
+		let (diff, b2) = diff.overflowing_sub(borrow as Word);
+		*x = diff;
+		borrow = b1 || b2;
+	}
+
+	borrow
+}
+
+/// The full product of `a` and `b`, schoolbook style, which is `a.len() + b.len()` words long
+pub(crate) fn mul(a: &[Word], b: &[Word]) -> Vec<Word> {
+	let mut product = vec![0 ; a.len() + b.len()];
+
+	for (i, x) in a.iter().enumerate() {
+		let mut carry: u128 = 0;
+
+		for (j, y) in b.iter().enumerate() {
+			let t = *x as u128 * *y as u128 + product[i + j] as u128 + carry;
+			product[i + j] = t as Word;
+			carry = t >> 64;
+		}
+
+		product[i + b.len()] = carry as Word;
+	}
+
+	product
+}
+
+/// Shifts left by fewer than 64 bits, into one extra word
+pub(crate) fn shl(a: &[Word], shift: u32) -> Vec<Word> {
+	let mut shifted = Vec::with_capacity(a.len() + 1);
+	let mut carry = 0;
+
+	for x in a {
+		shifted.push((x << shift) | carry);
+		carry = if shift == 0 { 0 } else { x >> (64 - shift) };
+	}
+
+	shifted.push(carry);
+	shifted
+}
+
+/// The quotient and remainder of a / b, for trimmed a and nonzero b (Knuth's algorithm D)
+pub(crate) fn div_rem(a: &[Word], b: &[Word]) -> (Vec<Word>, Vec<Word>) {
+	assert!(!b.is_empty(), "Division by zero");
+
+	if cmp(a, b) == Ordering::Less {
+		return (vec![], a.to_vec());
+	}
+
+	let n = b.len();
+
+	if n == 1 {
+		let divisor = b[0] as u128;
+		let mut quotient = vec![0 ; a.len()];
+		let mut remainder: u128 = 0;
+
+		for i in (0..a.len()).rev() {
+			let t = (remainder << 64) | a[i] as u128;
+			quotient[i] = (t / divisor) as Word;
+			remainder = t % divisor;
+		}
+
+		trim(&mut quotient);
+		let mut remainder = vec![remainder as Word];
+		trim(&mut remainder);
+
+		return (quotient, remainder);
+	}
+
+	// normalize so that the top bit of the divisor is set, which keeps every guess at a quotient
+	// word within 2 of the truth
+	let shift = b[n - 1].leading_zeros();
+	let v = shl(b, shift);
+	let mut u = shl(a, shift);
+	let m = a.len() - n;
+
+	let mut quotient = vec![0 ; m + 1];
+	let base: u128 = 1 << 64;
+
+	for j in (0..=m).rev() {
+		let top = ((u[j + n] as u128) << 64) | u[j + n - 1] as u128;
+		let mut q_hat = top / v[n - 1] as u128;
+		let mut r_hat = top % v[n - 1] as u128;
+
+		while q_hat >= base || q_hat * v[n - 2] as u128 > ((r_hat << 64) | u[j + n - 2] as u128) {
+			q_hat -= 1;
+			r_hat += v[n - 1] as u128;
+
+			if r_hat >= base {
+				break;
+			}
+		}
+
+		// u -= q_hat * v, at word j
+		let mut carry: u128 = 0;
+		let mut borrow = false;
+
+		for i in 0..=n {
+			let p = q_hat * v.get(i).copied().unwrap_or(0) as u128 + carry;
+			carry = p >> 64;
+
+			let (d, b1) = u[i + j].overflowing_sub(p as Word);
+			let (d, b2) = d.overflowing_sub(borrow as Word);
+			u[i + j] = d;
+			borrow = b1 || b2;
+		}
+
+		// the guess was one too big, so add v back once
+		if borrow {
+			q_hat -= 1;
+			add_assign(&mut u[j..=j + n], &v);
+		}
+
+		quotient[j] = q_hat as Word;
+	}
+
+	// undo the normalization of what is left over
+	let mut remainder: Vec<Word> = (0..n).map(|i| {
+		if shift == 0 { u[i] } else { (u[i] >> shift) | (u[i + 1] << (64 - shift)) }
+	}).collect();
+
+	trim(&mut quotient);
+	trim(&mut remainder);
+
+	(quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cmp::Ordering;
+
+	use rand::Rng;
+
+	use super::*;
+
+	#[test]
+	fn test_against_u128() {
+		let mut rng = rand::thread_rng();
+
+		for _ in 0..10000 {
+			let (a, b) = (rng.gen::<u128>() >> rng.gen_range(1..128), rng.gen::<u128>() >> rng.gen_range(1..128));
+			let words = |x: u128| [x as Word, (x >> 64) as Word];
+
+			assert_eq!(cmp(&words(a), &words(b)), a.cmp(&b));
+
+			let mut sum = words(a);
+			assert!(!add_assign(&mut sum, &words(b)));
+			assert_eq!(sum, words(a + b));
+
+			let (big, small) = (a.max(b), a.min(b));
+			let mut difference = words(big);
+			assert!(!sub_assign(&mut difference, &words(small)));
+			assert_eq!(difference, words(big - small));
+
+			if b != 0 {
+				let trimmed = |x: u128| { let mut w = words(x).to_vec(); trim(&mut w); w };
+				assert_eq!(div_rem(&trimmed(a), &trimmed(b)), (trimmed(a / b), trimmed(a % b)));
+			}
+
+			let (a, b) = (a as u64, b as u64);
+			assert_eq!(mul(&[a], &[b]), words(a as u128 * b as u128));
+		}
+	}
+
+	#[test]
+	fn test_carries() {
+		// carries and borrows run all the way through, and out of the top
+		let mut all_ones = [Word::MAX ; 3];
+		assert!(add_assign(&mut all_ones, &[1]));
+		assert_eq!(all_ones, [0 ; 3]);
+
+		assert!(sub_assign(&mut all_ones, &[1]));
+		assert_eq!(all_ones, [Word::MAX ; 3]);
+
+		// leading zeros make no difference to comparisons
+		assert_eq!(cmp(&[5, 0, 0], &[5]), Ordering::Equal);
+		assert_eq!(cmp(&[0, 1], &[Word::MAX]), Ordering::Greater);
+
+		let mut product = mul(&[Word::MAX, Word::MAX], &[Word::MAX]);
+		assert_eq!(product, [1, Word::MAX, Word::MAX - 1]);
+		trim(&mut product);
+		assert_eq!(product.len(), 3);
+		assert_eq!(mul(&[], &[1]), [0]);
+	}
+}
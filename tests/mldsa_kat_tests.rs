@@ -0,0 +1,105 @@
+/// Known-answer tests for ML-DSA, checked against vectors from another FIPS 204 implementation,
+/// OpenSSL 3.5, rather than against NIST's ACVP vectors, but split up the same way ACVP's are:
+/// key generation, signing, and verification, including signatures that have to be rejected
+use std::collections::HashMap;
+
+use rusty_crypto::mldsa::{MlDsaParams, PrivateKey};
+
+fn unhex(s: &str) -> Vec<u8> {
+	(0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..(i + 2)], 16).unwrap()).collect()
+}
+
+/// Reads the blank-line separated `key = value` records, skipping comments
+fn records(text: &str) -> Vec<HashMap<String, String>> {
+	text
+		.split("\n\n")
+		.map(|record| record.lines()
+			.filter(|line| !line.starts_with('#') && !line.is_empty())
+			.map(|line| {
+				let (key, value) = line.split_once(" = ").unwrap();
+				(key.to_string(), value.to_string())
+			})
+			.collect::<HashMap<String, String>>())
+		.filter(|record| !record.is_empty())
+		.collect()
+}
+
+fn vectors() -> Vec<HashMap<String, String>> {
+	records(include_str!("ml_dsa_kat.txt"))
+}
+
+fn params(name: &str) -> MlDsaParams {
+	match name {
+		"ML-DSA-44" => MlDsaParams::ML_DSA_44,
+		"ML-DSA-65" => MlDsaParams::ML_DSA_65,
+		"ML-DSA-87" => MlDsaParams::ML_DSA_87,
+		_ => panic!("Unknown parameter set {}", name)
+	}
+}
+
+#[test]
+fn test_key_gen() {
+	let vectors = vectors();
+	assert_eq!(vectors.len(), 6);
+
+	for vector in vectors {
+		let params = params(&vector["params"]);
+		let bytes = |key: &str| unhex(&vector[key]);
+
+		let (pk, sk) = params.keygen_internal(&bytes("xi").try_into().unwrap());
+		assert_eq!(pk, bytes("pk"), "{}: public key", vector["params"]);
+		assert_eq!(sk.expose(), &bytes("sk"), "{}: private key", vector["params"]);
+	}
+}
+
+#[test]
+fn test_sig_gen() {
+	for vector in vectors() {
+		let params = params(&vector["params"]);
+		let bytes = |key: &str| unhex(&vector[key]);
+		let (msg, ctx) = (bytes("msg"), bytes("ctx"));
+
+		let sk = PrivateKey::new(bytes("sk"));
+		assert_eq!(params.sign_deterministic(&sk, &msg, &ctx).unwrap(), bytes("det_sig"), "{}: deterministic signature", vector["params"]);
+
+		// the hedged signature, with the randomness fixed
+		let m_prime = [&[0, ctx.len() as u8], &ctx[..], &msg].concat();
+		let sig = params.sign_internal(&sk, &m_prime, &bytes("rnd").try_into().unwrap());
+		assert_eq!(sig, bytes("sig"), "{}: hedged signature", vector["params"]);
+
+		assert!(params.verify(&bytes("pk"), &msg, &ctx, &bytes("det_sig")));
+	}
+}
+
+#[test]
+fn test_sig_ver() {
+	let vectors = vectors();
+	let cases = records(include_str!("ml_dsa_sigver.txt"));
+	assert_eq!(cases.len(), 66);
+
+	for case in cases {
+		let vector = &vectors[case["vector"].parse::<usize>().unwrap()];
+		let mut fields: HashMap<&str, Vec<u8>> = ["pk", "msg", "ctx", "sig"].into_iter().map(|key| (key, unhex(&vector[key]))).collect();
+
+		// each case is a change to one of the vectors, written as a patch, a cut, or a replacement
+		if let Some(patch) = case.get("patch") {
+			let [field, offset, bytes]: [&str ; 3] = patch.split(' ').collect::<Vec<_>>().try_into().unwrap();
+			let offset: usize = offset.parse().unwrap();
+			let bytes = unhex(bytes);
+			fields.get_mut(field).unwrap()[offset..(offset + bytes.len())].copy_from_slice(&bytes);
+		}
+
+		if let Some(truncate) = case.get("truncate") {
+			let (field, length) = truncate.split_once(' ').unwrap();
+			fields.get_mut(field).unwrap().truncate(length.parse().unwrap());
+		}
+
+		if let Some(replace) = case.get("replace") {
+			let (field, bytes) = replace.split_once(' ').unwrap();
+			fields.insert(field, unhex(bytes));
+		}
+
+		let verified = params(&vector["params"]).verify(&fields["pk"], &fields["msg"], &fields["ctx"], &fields["sig"]);
+		assert_eq!(verified, case["testPassed"] == "true", "{}: {}", vector["params"], case["reason"]);
+	}
+}
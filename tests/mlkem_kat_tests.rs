@@ -0,0 +1,59 @@
+/// Known-answer tests for ML-KEM, checked against vectors from another FIPS 203 implementation
+use std::collections::HashMap;
+
+use rusty_crypto::mlkem::{DecapsulationKey, MlKemParams};
+
+fn unhex(s: &str) -> Vec<u8> {
+	(0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..(i + 2)], 16).unwrap()).collect()
+}
+
+/// Reads the blank-line separated `key = value` records, skipping comments
+fn vectors() -> Vec<HashMap<String, String>> {
+	include_str!("ml_kem_kat.txt")
+		.split("\n\n")
+		.map(|record| record.lines()
+			.filter(|line| !line.starts_with('#') && !line.is_empty())
+			.map(|line| {
+				let (key, value) = line.split_once(" = ").unwrap();
+				(key.to_string(), value.to_string())
+			})
+			.collect::<HashMap<String, String>>())
+		.filter(|record| !record.is_empty())
+		.collect()
+}
+
+fn params(name: &str) -> MlKemParams {
+	match name {
+		"ML-KEM-512" => MlKemParams::ML_KEM_512,
+		"ML-KEM-768" => MlKemParams::ML_KEM_768,
+		"ML-KEM-1024" => MlKemParams::ML_KEM_1024,
+		_ => panic!("Unknown parameter set {}", name)
+	}
+}
+
+#[test]
+fn test_ml_kem_kat() {
+	let vectors = vectors();
+	assert_eq!(vectors.len(), 6);
+
+	for vector in vectors {
+		let params = params(&vector["params"]);
+		let bytes = |key: &str| unhex(&vector[key]);
+
+		let (ek, dk) = params.keygen_internal(&bytes("d").try_into().unwrap(), &bytes("z").try_into().unwrap());
+		assert_eq!(ek, bytes("ek"), "{}: encapsulation key", vector["params"]);
+		assert_eq!(dk.expose(), &bytes("dk"), "{}: decapsulation key", vector["params"]);
+
+		let (c, k) = params.encaps_internal(&ek, &bytes("m").try_into().unwrap());
+		assert_eq!(c, bytes("c"), "{}: ciphertext", vector["params"]);
+		assert_eq!(k.expose()[..], bytes("k")[..], "{}: shared secret", vector["params"]);
+
+		let dk = DecapsulationKey::new(bytes("dk"));
+		assert_eq!(params.decaps(&dk, &c).unwrap().expose()[..], bytes("k")[..]);
+
+		// the implicit rejection secret for a ciphertext with its first bit flipped
+		let mut bad_c = c.clone();
+		bad_c[0] ^= 1;
+		assert_eq!(params.decaps(&dk, &bad_c).unwrap().expose()[..], bytes("bad_k")[..], "{}: rejection", vector["params"]);
+	}
+}
@@ -1,5 +1,3 @@
-#![feature(generic_const_exprs)]
-
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use rusty_crypto::lwe::Ciphertext;
@@ -11,14 +9,14 @@ use std::fs::File;
 use std::io::{stdin, ErrorKind, Read, Seek, Write};
 use std::io::Error;
 
-struct BlockStream<const N: usize> where [(); BLOCK_SIZE * N]: Sized {
-	blocks: Box<[u8 ; BLOCK_SIZE * N]>,
+struct BlockStream<const N: usize> {
+	blocks: Vec<u8>,
 	counter: usize
 }
 
-impl<const N: usize> BlockStream<N> where [(); BLOCK_SIZE * N]: Sized {
+impl<const N: usize> BlockStream<N> {
 	fn new() -> BlockStream<N> {
-		BlockStream { blocks: Box::new([0 ; BLOCK_SIZE * N]), counter: 0 }
+		BlockStream { blocks: vec![0 ; BLOCK_SIZE * N], counter: 0 }
 	}
 
 	fn reset(&mut self) {
@@ -26,7 +24,7 @@ impl<const N: usize> BlockStream<N> where [(); BLOCK_SIZE * N]: Sized {
 	}
 }
 
-impl<const N: usize> Read for BlockStream<N> where [(); BLOCK_SIZE * N]: Sized {
+impl<const N: usize> Read for BlockStream<N> {
 	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
 		if self.counter == N {
 			Err( Error::new(ErrorKind::Other, "No space") )
@@ -40,7 +38,7 @@ impl<const N: usize> Read for BlockStream<N> where [(); BLOCK_SIZE * N]: Sized {
 	}
 }
 
-impl<const N: usize> Write for BlockStream<N> where [(); BLOCK_SIZE * N]: Sized {
+impl<const N: usize> Write for BlockStream<N> {
 	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
 		if self.counter < N {
 			for i in 0..BLOCK_SIZE {
@@ -87,7 +85,7 @@ fn test_file_enc() {
 
 		let key = speck::gen();
 
-		speck::enc(key, &mut plaintext_file, &mut ciphertext_file);
+		speck::enc(&key, &mut plaintext_file, &mut ciphertext_file);
 
 
 		
@@ -99,7 +97,7 @@ fn test_file_enc() {
 		let mut recovered = open_file("tests/test_files/recovered");
 		recovered.rewind();
 
-		speck::dec(key, &mut ciphertext_file, &mut recovered);
+		speck::dec(&key, &mut ciphertext_file, &mut recovered);
 		
 		// compare both files!
 
@@ -131,4 +129,36 @@ fn test_file_enc() {
 		assert_eq!(rec_len, pt_len, "Files of unequal size upon decryption");
 
 	}
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_par_file_enc() {
+	let dir = std::env::temp_dir();
+	let pt_path = dir.join("rusty_crypto_par_plaintext");
+	let serial_path = dir.join("rusty_crypto_par_serial");
+	let parallel_path = dir.join("rusty_crypto_par_parallel");
+
+	let mut rng = StdRng::from_entropy();
+	let mut bytes = vec![0u8 ; 3_000_017]; // spans a few batches, with a partial last block
+	rng.fill(&mut bytes[..]);
+	std::fs::write(&pt_path, &bytes).unwrap();
+
+	let key = speck::gen();
+	let nonce: speck::Nonce = rng.gen();
+	let config = speck::ParallelConfig::new(4, 1 << 16).unwrap();
+
+	speck::enc_ctr(&key, nonce, &mut File::open(&pt_path).unwrap(), &mut File::create(&serial_path).unwrap()).unwrap();
+	speck::par_enc_ctr(&key, nonce, &mut File::open(&pt_path).unwrap(), &mut File::create(&parallel_path).unwrap(), &config).unwrap();
+
+	let serial = std::fs::read(&serial_path).unwrap();
+	let parallel = std::fs::read(&parallel_path).unwrap();
+	assert_eq!(serial, parallel, "Parallel ciphertext differs from the single-threaded one");
+
+	let mut recovered = Vec::new();
+	speck::par_dec_ctr(&key, nonce, &mut File::open(&parallel_path).unwrap(), &mut recovered, &config).unwrap();
+	assert_eq!(recovered, bytes);
+
+	for path in [pt_path, serial_path, parallel_path] {
+		let _ = std::fs::remove_file(path);
+	}
+}